@@ -8,7 +8,7 @@ use core::fmt;
 use std::fmt::{Display, Formatter};
 
 use crate::xdoc::error::XDocErr;
-use crate::ParseError;
+use crate::{Limit, ParseError};
 
 /// Alias for `Result<T, Error>`.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -34,6 +34,17 @@ impl Display for crate::error::Error {
     }
 }
 
+impl Error {
+    /// The kind of error, which can be matched on to handle different failures differently.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Parse(e) => e.kind,
+            Error::XdocErr(e) => e.kind,
+            Error::Other(e) => e.kind,
+        }
+    }
+}
+
 impl std::error::Error for crate::error::Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
@@ -60,6 +71,107 @@ fn convert_err<'a>(
 // public error data
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// The category of an error, which can be matched on to handle different failures differently.
+/// Every error produced by this library has a kind, see [`Error::kind`]. New kinds may be added, and
+/// an error may be given a more specific kind in the future, so a match should have a wildcard arm.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialOrd, Ord, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The document is not well-formed XML, and none of the more specific kinds apply.
+    #[default]
+    Syntax,
+    /// The document ended before the markup that was being parsed was complete.
+    UnexpectedEof,
+    /// An end tag does not match the start tag of the element that it closes.
+    MismatchedEndTag,
+    /// An element, attribute, entity or processing instruction name is not a valid XML name.
+    InvalidName,
+    /// A character appears where it is not allowed, e.g. a `<` in an attribute value.
+    InvalidCharacter,
+    /// A character or entity reference is malformed, refers to an illegal character, or refers to an
+    /// entity that is not declared.
+    InvalidReference,
+    /// The XML declaration is malformed, or is not at the start of the document.
+    InvalidDeclaration,
+    /// The `<!DOCTYPE>` declaration is malformed or misplaced.
+    InvalidDoctype,
+    /// A namespace prefix is not bound, or is bound or used incorrectly.
+    Namespace,
+    /// The document uses a feature of XML that this library does not support, e.g. external
+    /// entities.
+    Unsupported,
+    /// The document could not be decoded, or its declared encoding is unknown or wrong.
+    Encoding,
+    /// An element has more than one attribute with the same name.
+    DuplicateAttribute,
+    /// The document exceeded one of the [`crate::Limits`] that it was parsed with.
+    LimitExceeded(Limit),
+    /// Reading or writing failed, e.g. a file could not be opened.
+    Io,
+    /// An operation on a [`crate::Document`] or its nodes would make it invalid, e.g. a comment
+    /// containing `--`.
+    InvalidDocument,
+    /// A bug in this library.
+    Internal,
+}
+
+impl ErrorKind {
+    /// A short code that identifies this kind of error, e.g. `E0001`. Codes are stable, they do not
+    /// change between versions and are not reused, so they can be searched for or matched on by
+    /// tools that only see rendered errors.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::Syntax => "E0001",
+            ErrorKind::UnexpectedEof => "E0002",
+            ErrorKind::MismatchedEndTag => "E0003",
+            ErrorKind::InvalidName => "E0004",
+            ErrorKind::InvalidCharacter => "E0005",
+            ErrorKind::InvalidReference => "E0006",
+            ErrorKind::InvalidDeclaration => "E0007",
+            ErrorKind::InvalidDoctype => "E0008",
+            ErrorKind::Namespace => "E0009",
+            ErrorKind::Unsupported => "E0010",
+            ErrorKind::Encoding => "E0011",
+            ErrorKind::DuplicateAttribute => "E0012",
+            ErrorKind::LimitExceeded(limit) => match limit {
+                Limit::Depth => "E0101",
+                Limit::InputSize => "E0102",
+                Limit::Attributes => "E0103",
+                Limit::NameLength => "E0104",
+                Limit::TextLength => "E0105",
+                Limit::EntityDepth => "E0106",
+                Limit::EntityExpansion => "E0107",
+            },
+            ErrorKind::Io => "E0201",
+            ErrorKind::InvalidDocument => "E0301",
+            ErrorKind::Internal => "E0901",
+        }
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::Syntax => f.write_str("syntax error"),
+            ErrorKind::UnexpectedEof => f.write_str("unexpected end of document"),
+            ErrorKind::MismatchedEndTag => f.write_str("mismatched end tag"),
+            ErrorKind::InvalidName => f.write_str("invalid name"),
+            ErrorKind::InvalidCharacter => f.write_str("invalid character"),
+            ErrorKind::InvalidReference => f.write_str("invalid reference"),
+            ErrorKind::InvalidDeclaration => f.write_str("invalid XML declaration"),
+            ErrorKind::InvalidDoctype => f.write_str("invalid DOCTYPE"),
+            ErrorKind::Namespace => f.write_str("namespace error"),
+            ErrorKind::Unsupported => f.write_str("unsupported feature"),
+            ErrorKind::Encoding => f.write_str("encoding error"),
+            ErrorKind::DuplicateAttribute => f.write_str("duplicate attribute"),
+            ErrorKind::LimitExceeded(limit) => write!(f, "{limit} limit exceeded"),
+            ErrorKind::Io => f.write_str("I/O error"),
+            ErrorKind::InvalidDocument => f.write_str("invalid document"),
+            ErrorKind::Internal => f.write_str("internal error"),
+        }
+    }
+}
+
 /// The Rust sourcecode file and line number which is the 'throw' site of an error.
 #[derive(Debug, Clone, Eq, PartialOrd, PartialEq, Hash, Default)]
 pub struct ThrowSite {
@@ -78,6 +190,8 @@ impl Display for ThrowSite {
 /// Represents any error that is not related to the syntax of the XML file.
 #[derive(Debug, Default)]
 pub struct OtherError {
+    /// What kind of error this is.
+    pub kind: ErrorKind,
     /// The location in this library's sourcecode where the error was thrown.
     pub throw_site: ThrowSite,
     /// An optional error message.
@@ -5,23 +5,39 @@
 
 The goal, at least initially, is to provide an abstract syntax tree of an XML file.
 As such, this is a Exile is a dom parser and loads the complete contents of the document into memory.
+For documents that are too large for that, a streaming `Reader` is also available.
+Documents can be parsed from strings, files, or any `std::io::Read` object, in which case the bytes
+are decoded incrementally rather than read into a `String` first.
 
 Currently supported:
 - Attributes
+- Borrowed documents with `parse_borrowed`, whose names and text borrow from the input string
+  wherever parsing does not change them
 - CDATA Sections
 - Comment Parsing
+- Diagnostics that show the source line, column and element path of an error with
+  `ParseError::render`
+- Doctypes, parsed into a structured DTD model and written back out unchanged
+- DTD Validation of element content models and attributes, with `Document::validate_dtd`
 - Elements
+- Entity references to internal general entities declared in the DTD, with limits on expansion
+- Namespaces, resolving prefixes to URIs with `Document::resolve_namespaces` or while parsing,
+  and declaring them automatically for elements created with `Element::from_ns_name`
+- Parsing options, set with the `ParseOptions` builder and `parse_with`, to discard comments and
+  processing instructions, coalesce CDATA into text, resolve namespaces and limit resources
 - Processing Instructions
+- Recovering from errors with `parse_recovering`, which returns a partial document along with
+  every error
+- Source spans, recording the line, column and byte offset of each element, attribute and node
+- Streaming (pull-style) parsing
 - Text Nodes
-- UTF-8
-- Whitespace Normalization
+- UTF-8, UTF-16, ISO-8859-1 and Windows-1252, detected from the byte order mark and declaration
+- Whitespace Normalization, or preservation, trimming and `xml:space` with `WhitespacePolicy`
+- XML 1.1, with its line ends, restricted characters and control character references
 
 Not Supported:
-- Doctypes
-- Entities
-- Entity References
+- External Entities and Parameter Entities
 - Other Encodings
-- Whitesace Preservation: All text nodes are treated as if whitespace `collapse` were in-effect.
 
 # Example
 
@@ -43,17 +59,18 @@ for child in doc.root().children() {
     }
 }
 
-// we can create an index of elements
-let index = doc.index();
+// we can convert the document into an arena, in which every node has an id
+let arena = doc.into_arena();
+let root = arena.root().unwrap();
 
-// the element at index 2 is <thing>bar</thing>
-let thing = index.element(2).unwrap();
+// the second element in <root> is <thing>bar</thing>
+let mut elements = arena.children(root).filter(|&id| arena.element(id).is_some());
+let thing = elements.nth(1).unwrap();
 
-// the parent of index 2 is <root>
-let root = index.parent(&thing).unwrap();
-
-assert_eq!("bar", thing.text().unwrap());
-assert_eq!("root", root.name());
+// the parent of <thing> is <root>
+assert_eq!(Some(root), arena.parent(thing));
+let text = arena.first_child(thing).unwrap();
+assert_eq!(exile::NodeData::Text("bar".into()), arena[text]);
 ```
 
 Authoring XML looks like this.
@@ -77,18 +94,41 @@ The above program prints:
 </my_root>
 ```
 
+Streaming XML, one event at a time, looks like this.
+
+```
+use exile::{Event, Reader};
+let xml = r#"<root><thing name="foo"/><thing>bar</thing></root>"#;
+for event in Reader::new(xml).unwrap() {
+    match event.unwrap() {
+        Event::StartElement(element) => println!("start: {}", element.name()),
+        Event::EndElement(name) => println!("end: {}", name),
+        Event::Text(text) => println!("text: {}", text),
+        _ => {}
+    }
+}
+```
+
 */
 
 #![deny(rust_2018_idioms)]
 #![deny(missing_docs, unused_imports)]
+// errors carry where they were thrown, both in the document and in this library, which makes them
+// larger than clippy would like. they are only created on the failure path.
+#![allow(clippy::result_large_err)]
 
+use std::io::Read;
 use std::path::Path;
 
-pub use crate::parser::ParseError;
+pub use crate::parser::{Event, Limit, Limits, ParseError, ParseOptions, Reader, WhitespacePolicy};
+pub use crate::xdoc::borrowed;
+pub use crate::xdoc::doctype;
 pub use crate::xdoc::{
-    Declaration, Document, Element, Encoding, Index, Misc, Node, Pi, Version, WriteOpts,
+    Arena, AttributeOrder, Children, Declaration, DocTypeDecl, Document, DtdViolation, Edge,
+    Element, Encoding, Location, Misc, MixedContent, Node, NodeData, NodeId, Pi, Span, Traverse,
+    Version, WriteOpts,
 };
-pub use crate::xmlns::{Namespace, NcName, NsErr, QName};
+pub use crate::xmlns::{ExpandedName, Namespace, NcName, NsErr, QName};
 
 /// The `error` module defines the error types for this library.
 #[macro_use]
@@ -101,15 +141,86 @@ mod xmlns;
 
 /// Parse an XML file held in string contents.
 pub fn parse<S: AsRef<str>>(xml: S) -> crate::error::Result<Document> {
-    parser::document_from_string(xml)
+    parse_with(xml, &ParseOptions::default())
+}
+
+/// Parse an XML file held in string contents, as controlled by `opts`.
+pub fn parse_with<S: AsRef<str>>(xml: S, opts: &ParseOptions) -> crate::error::Result<Document> {
+    parser::document_from_string(xml, opts)
+}
+
+/// Parse an XML document held in string contents into a [`borrowed::Document`], whose names and
+/// text borrow from `xml`. Strings are only allocated where parsing changes them, e.g. to replace
+/// escapes or to normalize line ends and whitespace. Use [`borrowed::Document::into_owned`] to get
+/// a [`Document`].
+///
+/// ```
+/// use std::borrow::Cow;
+/// use exile::borrowed::Node;
+/// let xml = r#"<root><a x="1">plain text</a><b>&lt;escaped&gt;</b></root>"#;
+/// let doc = exile::parse_borrowed(xml).unwrap();
+/// let a = doc.root().child("a").unwrap();
+/// assert_eq!(Some("1"), a.attribute("x"));
+/// assert!(matches!(a.node(0), Some(Node::Text(Cow::Borrowed("plain text")))));
+/// let b = doc.root().child("b").unwrap();
+/// assert!(matches!(b.node(0), Some(Node::Text(Cow::Owned(_)))));
+/// assert_eq!(exile::parse(xml).unwrap(), doc.into_owned());
+/// ```
+pub fn parse_borrowed(xml: &str) -> crate::error::Result<borrowed::Document<'_>> {
+    parse_borrowed_with(xml, &ParseOptions::default())
+}
+
+/// Parse an XML document held in string contents into a [`borrowed::Document`], as controlled by
+/// `opts`. Borrowed documents do not support [`ParseOptions::spans`] or
+/// [`ParseOptions::namespaces`], which return an error of kind
+/// [`error::ErrorKind::Unsupported`]. Namespaces can be resolved by converting the document with
+/// [`borrowed::Document::into_owned`] and using [`Document::resolve_namespaces`].
+pub fn parse_borrowed_with<'a>(
+    xml: &'a str,
+    opts: &ParseOptions,
+) -> crate::error::Result<borrowed::Document<'a>> {
+    parser::borrowed_document_from_str(xml, opts)
+}
+
+/// Parse an XML file held in string contents, as controlled by `opts`, without stopping at the
+/// first error. After a syntax error, parsing resumes at the next markup, and elements that are
+/// left open by a missing or mismatched end tag are closed. Returns the document that could be
+/// built, along with every error that was encountered, which is empty if the document is
+/// well-formed. Exceeding one of the `Limits` stops the parse.
+///
+/// ```
+/// let xml = "<root><a x=1>text</a><b>&bad;</c></root>";
+/// let (doc, errors) = exile::parse_recovering(xml, &exile::ParseOptions::default());
+/// assert_eq!(4, errors.len());
+/// let root = doc.root();
+/// assert_eq!("text", root.child("a").unwrap().text().unwrap());
+/// assert!(root.child("b").is_some());
+/// ```
+pub fn parse_recovering<S: AsRef<str>>(xml: S, opts: &ParseOptions) -> (Document, Vec<ParseError>) {
+    parser::document_from_string_recovering(xml, opts)
 }
 
 /// Load a document from a file.
 pub fn load<P: AsRef<Path>>(path: P) -> crate::error::Result<Document> {
-    parser::document_from_file(path)
+    load_with(path, &ParseOptions::default())
 }
 
-// TODO - streaming https://github.com/webern/exile/issues/20
+/// Load a document from a file, as controlled by `opts`.
+pub fn load_with<P: AsRef<Path>>(path: P, opts: &ParseOptions) -> crate::error::Result<Document> {
+    parser::document_from_file(path, opts)
+}
+
+/// Parse an XML document from any `std::io::Read` object, e.g. a socket, a decompressor or stdin.
+/// The bytes are decoded as they are needed, so the raw document is never held in memory as a
+/// whole. The `reader` is buffered internally, so there is no need to wrap it in a `BufReader`.
+pub fn from_reader<R: Read>(reader: R) -> crate::error::Result<Document> {
+    from_reader_with(reader, &ParseOptions::default())
+}
+
+/// Parse an XML document from any `std::io::Read` object, as controlled by `opts`.
+pub fn from_reader_with<R: Read>(reader: R, opts: &ParseOptions) -> crate::error::Result<Document> {
+    parser::document_from_reader(reader, opts)
+}
 
 #[test]
 fn simple_document_test() {
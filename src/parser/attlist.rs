@@ -0,0 +1,214 @@
+/*!
+The `attlist` module keeps track of the attributes that are declared in the DTD so that attribute
+values can be normalized according to their type and so that default values can be supplied for
+attributes that are not specified.
+!*/
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::doctype::{AttDef, AttType, DefaultDecl, MarkupDeclValue};
+use crate::parser::error::Result;
+use crate::parser::source::Source;
+use crate::parser::string::{parse_string, StringType};
+use crate::parser::tree::Tree;
+use crate::parser::Iter;
+use crate::DocTypeDecl;
+
+/// The attributes that have been declared for each element name.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Attlists {
+    declared: HashMap<String, Vec<AttributeDecl>>,
+}
+
+#[derive(Debug, Clone)]
+struct AttributeDecl {
+    name: String,
+    /// Whether the attribute has a type other than `CDATA`, which means its value is normalized
+    /// further by trimming and collapsing spaces.
+    is_tokenized: bool,
+    /// The value that the attribute is given when it is not specified, if it has one.
+    default: Option<String>,
+}
+
+impl Attlists {
+    fn find(&self, element: &str, attribute: &str) -> Option<&AttributeDecl> {
+        self.declared
+            .get(element)
+            .and_then(|decls| decls.iter().find(|decl| decl.name == attribute))
+    }
+}
+
+/// Adds the attributes declared in the internal subset of `doctype` to those known by `iter`. When
+/// an attribute is declared more than once for the same element, the first declaration is binding.
+/// Entities must be declared before this is called so that they can be used in default values.
+pub(crate) fn declare_attlists(iter: &mut Iter<'_>, doctype: &DocTypeDecl) -> Result<()> {
+    for decl in doctype.markup_decls() {
+        let attlist = match decl {
+            MarkupDeclValue::AttlistDecl(attlist) => attlist,
+            _ => continue,
+        };
+        for att_def in attlist.att_defs() {
+            if iter.attlists.find(attlist.name(), att_def.name()).is_some() {
+                continue;
+            }
+            let decl = attribute_decl(iter, att_def)?;
+            iter.attlists
+                .declared
+                .entry(attlist.name().to_owned())
+                .or_default()
+                .push(decl);
+        }
+    }
+    Ok(())
+}
+
+fn attribute_decl(iter: &mut Iter<'_>, att_def: &AttDef) -> Result<AttributeDecl> {
+    let is_tokenized = *att_def.att_type() != AttType::CData;
+    let default = match att_def.default_decl() {
+        DefaultDecl::Value(value) => {
+            let value = parse_default_value(iter, &value.att_value().to_string())?;
+            Some(if is_tokenized {
+                normalize_tokenized(Cow::Owned(value)).into_owned()
+            } else {
+                value
+            })
+        }
+        DefaultDecl::Required | DefaultDecl::Implied => None,
+    };
+    Ok(AttributeDecl {
+        name: att_def.name().to_owned(),
+        is_tokenized,
+        default,
+    })
+}
+
+/// Parses the quoted `literal` of a default value in the same way as an attribute value in the
+/// document, using the entities that `iter` knows about.
+fn parse_default_value(iter: &mut Iter<'_>, literal: &str) -> Result<String> {
+    let string_type = if literal.starts_with('\'') {
+        StringType::AttributeSingle
+    } else {
+        StringType::AttributeDouble
+    };
+    let mut default_iter = Iter::from_source(Source::from_str(literal), iter.st.opts.clone())?;
+    default_iter.entities = std::mem::take(&mut iter.entities);
+    default_iter.advance();
+    let result = parse_string(&mut default_iter, string_type).map(Cow::into_owned);
+    iter.entities = std::mem::take(&mut default_iter.entities);
+    result.map_err(|e| create_parser_error!(&iter.st, e.kind, "bad default attribute value: {}", e))
+}
+
+/// Normalizes the `value` of the attribute `key` according to its declared type. Attributes that
+/// are not of type `CDATA` have their leading and trailing spaces removed and runs of spaces
+/// collapsed into a single space.
+pub(crate) fn normalize_attribute<'a>(
+    iter: &Iter<'_>,
+    element: &str,
+    key: &str,
+    value: Cow<'a, str>,
+) -> Cow<'a, str> {
+    match iter.attlists.find(element, key) {
+        Some(decl) if decl.is_tokenized => normalize_tokenized(value),
+        _ => value,
+    }
+}
+
+/// Values that are already normalized are returned as they are, so that they can stay borrowed.
+fn normalize_tokenized(value: Cow<'_, str>) -> Cow<'_, str> {
+    let is_normalized = !value.starts_with(' ') && !value.ends_with(' ') && !value.contains("  ");
+    if is_normalized {
+        return value;
+    }
+    Cow::Owned(
+        value
+            .split(' ')
+            .filter(|token| !token.is_empty())
+            .collect::<Vec<&str>>()
+            .join(" "),
+    )
+}
+
+/// Adds the default value of every declared attribute that `element` does not specify.
+pub(crate) fn apply_defaults<'a, E: Tree<'a>>(iter: &Iter<'a>, element: &mut E) {
+    let decls = match iter.attlists.declared.get(element.fullname()) {
+        Some(decls) => decls,
+        None => return,
+    };
+    for decl in decls {
+        if let Some(default) = &decl.default {
+            if element.attribute(&decl.name).is_none() {
+                element.add_defaulted_attribute(decl.name.as_str(), default.as_str());
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// tests
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn attlist_defaults() {
+    let xml = r#"<!DOCTYPE doc [
+<!ENTITY who "the &#38;#60;world&#62;">
+<!ATTLIST doc
+    greeting CDATA "hello &who;"
+    version CDATA #FIXED "1"
+    id ID #IMPLIED
+    specified CDATA "no">
+<!ATTLIST doc greeting CDATA "ignored">
+]>
+<doc specified="yes"/>"#;
+    let doc = crate::parse(xml).unwrap();
+    let root = doc.root();
+    assert_eq!(3, root.attributes_len());
+    assert_eq!("hello the <world>", root.attribute("greeting").unwrap());
+    assert_eq!("1", root.attribute("version").unwrap());
+    assert_eq!("yes", root.attribute("specified").unwrap());
+    assert!(root.is_attribute_defaulted("greeting"));
+    assert!(root.is_attribute_defaulted("version"));
+    assert!(!root.is_attribute_defaulted("specified"));
+    assert!(root.attribute("id").is_none());
+    // whether an attribute was defaulted does not take part in equality.
+    let mut specified = root.clone();
+    specified.add_attribute("version", "1");
+    assert!(!specified.is_attribute_defaulted("version"));
+    assert_eq!(*root, specified);
+    // defaulted attributes are not written, so a round trip does not add them to the document.
+    let written = doc.to_string();
+    assert!(written.contains("<doc specified=\"yes\"/>"), "{}", written);
+    assert_eq!(doc, crate::parse(&written).unwrap());
+}
+
+#[test]
+fn attlist_normalization() {
+    let xml = "<!DOCTYPE doc [
+<!ATTLIST doc tokens NMTOKENS '  a\tb  ' text CDATA #IMPLIED list IDREFS #IMPLIED>
+]>
+<doc text=' x\n\ty &#10; ' list='\n  a&#32;&#32;b  '/>";
+    let doc = crate::parse(xml).unwrap();
+    let root = doc.root();
+    assert_eq!("a b", root.attribute("tokens").unwrap());
+    assert_eq!(" x  y \n ", root.attribute("text").unwrap());
+    assert_eq!("a b", root.attribute("list").unwrap());
+}
+
+#[test]
+fn attlist_defaults_in_reader() {
+    use crate::{Event, Reader};
+    let xml = r#"<!DOCTYPE doc [<!ATTLIST a x CDATA "1">]><doc><a/><a x="2"/></doc>"#;
+    let values: Vec<(String, bool)> = Reader::new(xml)
+        .unwrap()
+        .filter_map(|event| match event.unwrap() {
+            Event::StartElement(e) if e.name() == "a" => Some((
+                e.attribute("x").unwrap().clone(),
+                e.is_attribute_defaulted("x"),
+            )),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        vec![("1".to_owned(), true), ("2".to_owned(), false)],
+        values
+    );
+}
@@ -2,17 +2,23 @@
 The `bang` module parses those constructs that start with `<!`.
 !*/
 
-// use crate::xdoc::xdocv2::doctype::DocTypeDecl;
-use crate::Node;
+use std::borrow::Cow;
 
+use crate::error::ErrorKind;
+use crate::{DocTypeDecl, Limit};
+
+use super::attlist::declare_attlists;
 use super::element::LtParse;
+use super::entity::declare_entities;
 use super::error::Result;
+use super::scratch::Scratch;
+use super::tree::Leaf;
 use super::Iter;
 
 /// Parses an XML node that starts with `<!`. Expected the iterator to be pointing at `<` when
 /// called, and expects the next character to be `!`. Returns the iterator pointing at the next
 /// character after the closing `>`.
-pub(super) fn parse_bang(iter: &mut Iter<'_>) -> Result<LtParse> {
+pub(super) fn parse_bang<'a>(iter: &mut Iter<'a>) -> Result<LtParse<'a>> {
     debug_assert_eq!('<', iter.st.c);
     iter.advance_or_die()?;
     debug_assert_eq!('!', iter.st.c);
@@ -21,30 +27,46 @@ pub(super) fn parse_bang(iter: &mut Iter<'_>) -> Result<LtParse> {
         '-' => {
             // skip comment expects the iter to be advanced passed lt
             let comment = parse_comment(iter)?;
-            Ok(LtParse::Some(Node::Comment(comment)))
+            if iter.st.opts.comments {
+                Ok(LtParse::Some(Leaf::Comment(comment)))
+            } else {
+                Ok(LtParse::Skip)
+            }
         }
         '[' => {
             let cdata = parse_cdata(iter)?;
-            Ok(LtParse::Some(Node::CData(cdata)))
+            if iter.st.opts.coalesce_cdata {
+                Ok(LtParse::Some(Leaf::Text(cdata)))
+            } else {
+                Ok(LtParse::Some(Leaf::CData(cdata)))
+            }
         }
         'D' => {
-            let s = parse_doctype(iter)?;
-            Ok(LtParse::DocType(s))
+            let doctype = DocTypeDecl::parse(iter)?;
+            declare_entities(iter, &doctype)?;
+            declare_attlists(iter, &doctype)?;
+            Ok(LtParse::DocType(doctype))
         }
-        _ => parse_err!(iter, "illegal char '{}' after <!", iter.st.c),
+        _ => parse_err!(
+            iter,
+            ErrorKind::Syntax,
+            "illegal char '{}' after <!",
+            iter.st.c
+        ),
     }
 }
 
 /// Parses a comment. Takes the iterator pointing at `!` and returns the iterator pointing at the
 /// first character after the closing '>'. Returns an error  if it is not a well-formed comment.
-pub(super) fn parse_comment(iter: &mut Iter<'_>) -> Result<String> {
+pub(super) fn parse_comment<'a>(iter: &mut Iter<'a>) -> Result<Cow<'a, str>> {
+    let depth = iter.it.depth();
     expect!(iter, '!')?;
     iter.advance_or_die()?;
     expect!(iter, '-')?;
     iter.advance_or_die()?;
     expect!(iter, '-')?;
     iter.advance_or_die()?;
-    let mut comment = String::new();
+    let mut comment = Scratch::new(iter);
     loop {
         if iter.is('-') && iter.peek_is('-') {
             // advance to the second dash
@@ -52,57 +74,30 @@ pub(super) fn parse_comment(iter: &mut Iter<'_>) -> Result<String> {
             // advance to the char after the second dash
             iter.advance_or_die()?;
             if iter.is('>') {
+                iter.expect_depth(depth)?;
                 break;
             } else {
-                return parse_err!(iter, "-- is not allowed in a comment string");
+                return parse_err!(
+                    iter,
+                    ErrorKind::Syntax,
+                    "-- is not allowed in a comment string"
+                );
             }
         } else {
-            comment.push(iter.st.c);
+            iter.expect_literal_char()?;
+            comment.push_current(iter);
+            iter.check_text_limit(Limit::TextLength, comment.as_str())?;
         }
         iter.advance_or_die()?;
     }
     // advance the iter to the char following -->
     iter.advance();
-    Ok(comment)
-}
-
-// TODO - support doctypes https://github.com/webern/exile/issues/22
-/// Advances the iterator past a `<!DOCTYPE` section. Expects the iterator to be pointing at `!` and
-/// returns the iterator pointing at the first character after the closing `>`. Does not inspect
-/// the contents to ensure they are well-formed.
-fn parse_doctype(iter: &mut Iter<'_>) -> Result<String> {
-    expect!(iter, '!')?;
-    let mut s = String::from("<");
-    while !iter.is('>') {
-        if iter.is('[') {
-            parse_nested_doctype_stuff(iter, &mut s)?
-        }
-        s.push(iter.st.c);
-        iter.advance_or_die()?;
-    }
-    s.push(iter.st.c);
-    // advance the iter to the char following ]>
-    iter.advance();
-    Ok(s)
-    // let _ = DocTypeDecl::parse(iter)?;
-    // Ok(())
-}
-
-// TODO - support doctypes https://github.com/webern/exile/issues/22
-/// Helps `skip_doctype` to find the end of a doctype section.
-fn parse_nested_doctype_stuff(iter: &mut Iter<'_>, s: &mut String) -> Result<()> {
-    expect!(iter, '[')?;
-    s.push(iter.st.c);
-    iter.advance_or_die()?;
-    while !iter.is(']') {
-        s.push(iter.st.c);
-        iter.advance_or_die()?;
-    }
-    Ok(())
+    Ok(comment.into_cow())
 }
 
 /// `<![CDATA[foo]]>`
-fn parse_cdata(iter: &mut Iter<'_>) -> Result<String> {
+fn parse_cdata<'a>(iter: &mut Iter<'a>) -> Result<Cow<'a, str>> {
+    let depth = iter.it.depth();
     // we assume the first char has been checked and is !
     iter.advance_or_die()?;
     // we assume the second char has been checked and is [
@@ -119,28 +114,33 @@ fn parse_cdata(iter: &mut Iter<'_>) -> Result<String> {
     iter.advance_or_die()?;
     expect!(iter, '[')?;
     iter.advance_or_die()?;
-    let mut data = String::new();
-    let mut backet_buffer = String::new();
+    let mut data = Scratch::new(iter);
+    // the offsets of the `]` characters that came just before the current one. they are only part
+    // of the data if they do not end the section.
+    let mut brackets: Vec<Option<usize>> = Vec::new();
     loop {
-        let c = iter.st.c;
-        if c == ']' {
-            backet_buffer.push(']');
+        if iter.is(']') {
+            brackets.push(iter.offset());
             iter.advance_or_die()?;
             continue;
-        } else if c == '>' && backet_buffer.len() >= 2 {
-            if backet_buffer.len() > 2 {
-                data.push_str(&backet_buffer[2..])
+        } else if iter.is('>') && brackets.len() >= 2 {
+            iter.expect_depth(depth)?;
+            for &offset in &brackets[..brackets.len() - 2] {
+                data.push_at(']', offset);
             }
             break;
-        } else if !backet_buffer.is_empty() {
-            data.push_str(&backet_buffer);
-            backet_buffer.clear();
+        } else {
+            for offset in brackets.drain(..) {
+                data.push_at(']', offset);
+            }
         }
-        data.push(c);
+        iter.expect_literal_char()?;
+        data.push_current(iter);
+        iter.check_text_limit(Limit::TextLength, data.as_str())?;
         iter.advance_or_die()?
     }
     iter.advance();
-    Ok(data)
+    Ok(data.into_cow())
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -150,7 +150,7 @@ fn parse_bang_cdata_1() {
     let data = "foo";
     let iter_char_after = 'b';
     let input = format!("<![CDATA[{data}]]>bar");
-    let expected = LtParse::Some(Node::CData(data.into()));
+    let expected = LtParse::Some(Leaf::CData(data.into()));
     let mut iter = Iter::new(&input).unwrap();
     let actual = parse_bang(&mut iter).unwrap();
     assert_eq!(expected, actual);
@@ -162,7 +162,7 @@ fn parse_bang_cdata_2() {
     let data = "foo]] >bar]>]>x";
     let iter_char_after = 'x';
     let input = format!("<![CDATA[{data}]]>x");
-    let expected = LtParse::Some(Node::CData(data.into()));
+    let expected = LtParse::Some(Leaf::CData(data.into()));
     let mut iter = Iter::new(&input).unwrap();
     let actual = parse_bang(&mut iter).unwrap();
     assert_eq!(expected, actual);
@@ -174,7 +174,7 @@ fn parse_bang_cdata_3() {
     let data = "foo]]>bar]>]>x";
     let iter_char_after = 'b';
     let input = format!("<![CDATA[{data}]]>x");
-    let expected = LtParse::Some(Node::CData("foo".into()));
+    let expected = LtParse::Some(Leaf::CData("foo".into()));
     let mut iter = Iter::new(&input).unwrap();
     let actual = parse_bang(&mut iter).unwrap();
     assert_eq!(expected, actual);
@@ -186,7 +186,7 @@ fn parse_bang_cdata_4() {
     let data = "<xml>bloop</xml>";
     let iter_char_after = '<';
     let input = format!("<![CDATA[{data}]]><foo></foo>");
-    let expected = LtParse::Some(Node::CData(data.into()));
+    let expected = LtParse::Some(Leaf::CData(data.into()));
     let mut iter = Iter::new(&input).unwrap();
     let actual = parse_bang(&mut iter).unwrap();
     assert_eq!(expected, actual);
@@ -198,7 +198,7 @@ fn parse_bang_cdata_5() {
     let data = "<![CDATA[";
     let iter_char_after = '<';
     let input = format!("<![CDATA[{data}]]><foo></foo>");
-    let expected = LtParse::Some(Node::CData(data.into()));
+    let expected = LtParse::Some(Leaf::CData(data.into()));
     let mut iter = Iter::new(&input).unwrap();
     let actual = parse_bang(&mut iter).unwrap();
     assert_eq!(expected, actual);
@@ -210,7 +210,7 @@ fn parse_bang_cdata_6() {
     let data = "<&]>]";
     let iter_char_after = 'b';
     let input = format!("<![CDATA[{data}]]>bar");
-    let expected = LtParse::Some(Node::CData(data.into()));
+    let expected = LtParse::Some(Leaf::CData(data.into()));
     let mut iter = Iter::new(&input).unwrap();
     let actual = parse_bang(&mut iter).unwrap();
     assert_eq!(expected, actual);
@@ -222,7 +222,7 @@ fn parse_bang_cdata_7() {
     let data = "]";
     let iter_char_after = 'b';
     let input = format!("<![CDATA[{data}]]>bar");
-    let expected = LtParse::Some(Node::CData(data.into()));
+    let expected = LtParse::Some(Leaf::CData(data.into()));
     let mut iter = Iter::new(&input).unwrap();
     let actual = parse_bang(&mut iter).unwrap();
     assert_eq!(expected, actual);
@@ -234,7 +234,7 @@ fn parse_bang_cdata_8() {
     let data = "]]";
     let iter_char_after = 'b';
     let input = format!("<![CDATA[{data}]]>bar");
-    let expected = LtParse::Some(Node::CData(data.into()));
+    let expected = LtParse::Some(Leaf::CData(data.into()));
     let mut iter = Iter::new(&input).unwrap();
     let actual = parse_bang(&mut iter).unwrap();
     assert_eq!(expected, actual);
@@ -246,14 +246,13 @@ fn parse_bang_cdata_9() {
     let data = "]]]";
     let iter_char_after = 'b';
     let input = format!("<![CDATA[{data}]]>bar");
-    let expected = LtParse::Some(Node::CData(data.into()));
+    let expected = LtParse::Some(Leaf::CData(data.into()));
     let mut iter = Iter::new(&input).unwrap();
     let actual = parse_bang(&mut iter).unwrap();
     assert_eq!(expected, actual);
     assert_eq!(iter_char_after, iter.st.c);
 }
 
-// TODO - write real tests for doctypes https://github.com/webern/exile/issues/22
 #[test]
 fn parse_bang_doctype() {
     let data = r#"<!DOCTYPE doc [
@@ -261,10 +260,12 @@ fn parse_bang_doctype() {
 <!ATTLIST doc a1 CDATA #IMPLIED>
 ]>x"#;
     let iter_char_after = 'x';
-    let expected = LtParse::DocType(data[..data.len() - 1].to_owned());
     let mut iter = Iter::new(data).unwrap();
-    let actual = parse_bang(&mut iter).unwrap();
-    assert_eq!(expected, actual);
+    let actual = match parse_bang(&mut iter).unwrap() {
+        LtParse::DocType(doctype) => doctype,
+        other => panic!("expected a doctype, got {:?}", other),
+    };
+    assert_eq!(&data[..data.len() - 1], actual.to_string());
     assert_eq!(iter_char_after, iter.st.c);
 }
 
@@ -272,7 +273,7 @@ fn parse_bang_doctype() {
 fn parse_bang_comment_1() {
     let data = r#"<!-- foo -->x"#;
     let iter_char_after = 'x';
-    let expected = LtParse::Some(Node::Comment(" foo ".into()));
+    let expected = LtParse::Some(Leaf::Comment(" foo ".into()));
     let mut iter = Iter::new(data).unwrap();
     let actual = parse_bang(&mut iter).unwrap();
     assert_eq!(expected, actual);
@@ -283,7 +284,7 @@ fn parse_bang_comment_1() {
 fn parse_bang_comment_2() {
     let data = r#"<!-- foo-->x"#;
     let iter_char_after = 'x';
-    let expected = LtParse::Some(Node::Comment(" foo".into()));
+    let expected = LtParse::Some(Leaf::Comment(" foo".into()));
     let mut iter = Iter::new(data).unwrap();
     let actual = parse_bang(&mut iter).unwrap();
     assert_eq!(expected, actual);
@@ -294,7 +295,7 @@ fn parse_bang_comment_2() {
 fn parse_bang_comment_3() {
     let data = r#"<!--foo-->x"#;
     let iter_char_after = 'x';
-    let expected = LtParse::Some(Node::Comment("foo".into()));
+    let expected = LtParse::Some(Leaf::Comment("foo".into()));
     let mut iter = Iter::new(data).unwrap();
     let actual = parse_bang(&mut iter).unwrap();
     assert_eq!(expected, actual);
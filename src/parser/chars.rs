@@ -1,3 +1,5 @@
+use crate::Version;
+
 // name start char range boundries
 const U_00C0: char = '\u{00C0}';
 const U_00D6: char = '\u{00D6}';
@@ -31,6 +33,12 @@ const U_036F: char = '\u{036F}';
 const U_203F: char = '\u{203F}';
 const U_2040: char = '\u{2040}';
 
+// line end characters that are normalized to `\n` in XML 1.1
+pub(crate) const NEXT_LINE: char = '\u{0085}';
+pub(crate) const LINE_SEPARATOR: char = '\u{2028}';
+
+/// The name characters of XML 1.1 are the same as those of the fifth edition of XML 1.0, so these
+/// functions serve both versions.
 pub(crate) fn is_name_start_char(c: char) -> bool {
     // https://www.w3.org/TR/2008/REC-xml-20081126/#NT-NameStartChar
     // [4]   	NameStartChar	   ::=   	":" | [A-Z] | "_" | [a-z] | [#xC0-#xD6] | [#xD8-#xF6] |
@@ -64,3 +72,38 @@ pub(crate) fn is_name_char(c: char) -> bool {
     }
     matches!(c, U_00B7 | U_0300..=U_036F | U_203F..=U_2040 | '0'..='9' | '-' | '.')
 }
+
+/// Whether `c` is allowed in a document of the given version, i.e. it matches the `Char`
+/// production. Every `char` is either a legal character or one of the code points that only the
+/// character references of XML 1.1 can give, so this is what a character reference is checked with.
+pub(crate) fn is_char(c: char, version: Version) -> bool {
+    // https://www.w3.org/TR/2008/REC-xml-20081126/#NT-Char
+    // [2] Char ::= #x9 | #xA | #xD | [#x20-#xD7FF] | [#xE000-#xFFFD] | [#x10000-#x10FFFF]
+    // https://www.w3.org/TR/xml11/#NT-Char
+    // [2] Char ::= [#x1-#xD7FF] | [#xE000-#xFFFD] | [#x10000-#x10FFFF]
+    match version {
+        Version::V10 => matches!(c,
+            '\t' |
+            '\n' |
+            '\r' |
+            '\u{20}'..='\u{D7FF}' |
+            '\u{E000}'..='\u{FFFD}' |
+            '\u{10000}'..='\u{10FFFF}'),
+        Version::V11 => matches!(c,
+            '\u{1}'..='\u{D7FF}' |
+            '\u{E000}'..='\u{FFFD}' |
+            '\u{10000}'..='\u{10FFFF}'),
+    }
+}
+
+/// Whether `c` is a control character that XML 1.1 only allows as a character reference.
+pub(crate) fn is_restricted_char(c: char) -> bool {
+    // https://www.w3.org/TR/xml11/#NT-RestrictedChar
+    // [2a] RestrictedChar ::= [#x1-#x8] | [#xB-#xC] | [#xE-#x1F] | [#x7F-#x84] | [#x86-#x9F]
+    matches!(c,
+        '\u{1}'..='\u{8}' |
+        '\u{B}'..='\u{C}' |
+        '\u{E}'..='\u{1F}' |
+        '\u{7F}'..='\u{84}' |
+        '\u{86}'..='\u{9F}')
+}
@@ -1,8 +1,14 @@
+/*!
+The `doctype` module parses the `<!DOCTYPE ...>` section of a document into the model found in
+`crate::xdoc::doctype`.
+!*/
+
 use crate::constants::{CARRIAGE_RETURN, NEWLINE, SPACE, TAB};
+use crate::error::ErrorKind;
 use crate::parser::bang::parse_comment;
 use crate::parser::pi::parse_pi;
 use crate::parser::Iter;
-use crate::xdoc::xdocv2::doctype::{
+use crate::xdoc::doctype::{
     AttDef, AttType, AttValue, AttValueData, AttlistDeclValue, CharRefValue, CharRefValueType,
     ChildrenType, ChildrenValue, ChoiceValue, ContentSpec, CpItem, CpValue, DeclSep, DefaultDecl,
     DefaultDeclAttValue, DelimitedListItem, DocTypeDecl, DocTypeName, ElementDeclValue,
@@ -18,30 +24,48 @@ use crate::xdoc::xdocv2::doctype::{
 
 use super::error::Result;
 
+/// Parses a string that holds nothing but a `<!DOCTYPE ...>` declaration.
+pub(crate) fn parse_doctype_str(s: &str) -> crate::error::Result<DocTypeDecl> {
+    let mut iter = Iter::new(s).map_err(crate::error::Error::Parse)?;
+    let result = parse_doctype_only(&mut iter);
+    iter.finish(result)
+}
+
+fn parse_doctype_only(iter: &mut Iter<'_>) -> Result<DocTypeDecl> {
+    expect!(iter, '<')?;
+    iter.advance_or_die()?;
+    let doctype = DocTypeDecl::parse(iter)?;
+    if !iter.end() {
+        return parse_err!(
+            iter,
+            ErrorKind::InvalidDoctype,
+            "unexpected '{}' after the DOCTYPE",
+            iter.st.c
+        );
+    }
+    Ok(doctype)
+}
+
 impl DocTypeDecl {
+    /// Expects the iter pointing at the `!` of `<!DOCTYPE` and returns it pointing at the first
+    /// character after the closing `>`.
     pub(crate) fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        debug_assert!(iter.is('!'));
+        expect!(iter, '!')?;
         iter.advance_or_die()?;
-        iter.consume(STR_DOCTYPE);
+        iter.consume(STR_DOCTYPE)?;
         let space_before_name = Whitespace::parse(iter)?;
         let name = DocTypeName::parse(iter)?;
-        let mystery_space = Whitespace::parse_optional(iter);
-        let (external_id, mut space_before_int_subset) = if iter.is('S') || iter.is('P') {
-            if let Some(ws) = mystery_space {
-                (Some((ws, ExternalID::parse(iter)?)), None)
-            } else {
-                return parse_err!(iter, "i am confused");
-            }
-        } else {
-            (None, mystery_space)
+        let space_after_name = Whitespace::parse_optional(iter);
+        let (external_id, space_before_int_subset) = match space_after_name {
+            Some(ws) if iter.is('S') || iter.is('P') => (
+                Some((ws, ExternalID::parse(iter)?)),
+                Whitespace::parse_optional(iter),
+            ),
+            _ => (None, space_after_name),
         };
-        if space_before_int_subset.is_none() {
-            space_before_int_subset = Whitespace::parse_optional(iter);
-        }
         let int_subsets = if iter.is('[') {
             iter.advance_or_die()?;
             let int_subset = IntSubsets::parse(iter, ']')?;
-            expect!(iter, ']')?;
             iter.advance_or_die()?;
             Some((int_subset, Whitespace::parse_optional(iter)))
         } else {
@@ -61,20 +85,13 @@ impl DocTypeDecl {
 
 impl DocTypeName {
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
+        iter.expect_name_start_char()?;
         let mut name = String::new();
-        if !iter.is_name_start_char() {
-            return parse_err!(iter, "expected name start char");
-        }
         name.push(iter.st.c);
         iter.advance_or_die()?;
-        loop {
-            if !iter.is_name_char() {
-                break;
-            }
+        while iter.is_name_char() {
             name.push(iter.st.c);
-            if !iter.advance() {
-                break;
-            }
+            iter.advance_or_die()?;
         }
         Ok(Self { name })
     }
@@ -82,8 +99,9 @@ impl DocTypeName {
 
 impl Whitespace {
     pub(crate) fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        Self::parse_optional(iter)
-            .ok_or_else(|| create_parser_error!(&iter.st, "expected whitespace"))
+        Self::parse_optional(iter).ok_or_else(|| {
+            create_parser_error!(&iter.st, ErrorKind::InvalidDoctype, "expected whitespace")
+        })
     }
 
     pub(crate) fn parse_optional(iter: &mut Iter<'_>) -> Option<Self> {
@@ -107,12 +125,6 @@ impl Whitespace {
     }
 }
 
-// impl Space {
-//     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-//         unimplemented!();
-//     }
-// }
-
 impl ExternalID {
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
         match iter.st.c {
@@ -124,7 +136,13 @@ impl ExternalID {
                 iter.consume(STR_PUBLIC)?;
                 Ok(ExternalID::Public(PublicExternalID::parse(iter)?))
             }
-            _ => parse_err!(iter, "expected {} or {}", STR_SYSTEM, STR_PUBLIC),
+            _ => parse_err!(
+                iter,
+                ErrorKind::InvalidDoctype,
+                "expected {} or {}",
+                STR_SYSTEM,
+                STR_PUBLIC
+            ),
         }
     }
 }
@@ -150,58 +168,52 @@ impl PublicExternalID {
 }
 
 impl Quote {
+    /// Expects the iter pointing at the opening quote and returns it pointing at the next char.
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        let q = Self::new(iter.st.c).map_err(|e| from_xe!(iter, e))?;
-        iter.advance();
+        let q = Self::new(iter.st.c).map_err(|e| from_xe!(iter, ErrorKind::InvalidDoctype, e))?;
+        iter.advance_or_die()?;
         Ok(q)
     }
 }
 
 impl SystemLiteral {
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        let q = Quote::new(iter.st.c).map_err(|e| from_xe!(iter, e))?;
+        let quote = Quote::parse(iter)?;
         let mut value = String::new();
-        loop {
-            iter.advance_or_die()?;
-            if iter.is(q.char()) {
-                break;
-            }
+        while !iter.is(quote.char()) {
+            iter.expect_literal_char()?;
             value.push(iter.st.c);
+            iter.advance_or_die()?;
         }
         iter.advance();
-        Ok(Self { quote: q, value })
+        Ok(Self { quote, value })
     }
 }
 
 impl PubIDLiteral {
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        let q = Quote::new(iter.st.c).map_err(|e| from_xe!(iter, e))?;
-        iter.advance_or_die()?;
-        let mut s = String::new();
-        loop {
-            if Self::forbidden(iter.st.c, q) {
-                return parse_err!(iter, "forbidden character in pubid literal");
-            } else if iter.st.c == q.char() {
-                break;
+        let quote = Quote::parse(iter)?;
+        let mut value = String::new();
+        while !iter.is(quote.char()) {
+            if Self::forbidden(iter.st.c, quote) {
+                return parse_err!(
+                    iter,
+                    ErrorKind::InvalidDoctype,
+                    "forbidden character in pubid literal"
+                );
             }
-            s.push(iter.st.c);
+            value.push(iter.st.c);
             iter.advance_or_die()?
         }
         iter.advance();
-        Ok(Self {
-            quote: Quote::Single,
-            value: s,
-        })
+        Ok(Self { quote, value })
     }
 }
 
 impl IntSubsets {
     fn parse(iter: &mut Iter<'_>, end: char) -> Result<Self> {
         let mut int_subsets = Vec::new();
-        loop {
-            if iter.is(end) {
-                break;
-            }
+        while !iter.is(end) {
             int_subsets.push(IntSubset::parse(iter)?)
         }
         Ok(Self::new(int_subsets))
@@ -211,7 +223,9 @@ impl IntSubsets {
 impl IntSubset {
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
         if iter.is('<') {
-            Ok(IntSubset::MarkupDecl(MarkupDeclValue::parse(iter)?))
+            Ok(IntSubset::MarkupDecl(Box::new(MarkupDeclValue::parse(
+                iter,
+            )?)))
         } else {
             Ok(IntSubset::DeclSep(DeclSep::parse(iter)?))
         }
@@ -222,72 +236,79 @@ impl DeclSep {
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
         if iter.is_whitespace() {
             Ok(DeclSep::Space(Whitespace::parse(iter)?))
-        } else {
+        } else if iter.is('%') {
             Ok(DeclSep::PEReference(PEReferenceValue::parse(iter)?))
+        } else {
+            parse_err!(
+                iter,
+                ErrorKind::InvalidDoctype,
+                "unexpected '{}' in the internal subset",
+                iter.st.c
+            )
         }
     }
 }
 
 impl ReferenceValue {
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        debug_assert!(iter.is('&'));
+        expect!(iter, '&')?;
         iter.advance_or_die()?;
         let name = DocTypeName::parse(iter)?;
-        expect!(iter, ';');
-        iter.advance();
+        expect!(iter, ';')?;
+        iter.advance_or_die()?;
         Ok(Self { value: name })
     }
 }
 
 impl PEReferenceValue {
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        debug_assert!(iter.is('%'));
+        expect!(iter, '%')?;
         iter.advance_or_die()?;
         let name = DocTypeName::parse(iter)?;
-        expect!(iter, ';');
-        iter.advance();
+        expect!(iter, ';')?;
+        iter.advance_or_die()?;
         Ok(Self { value: name })
     }
 }
 
 impl MarkupDeclValue {
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        debug_assert!(iter.is('<'));
+        expect!(iter, '<')?;
+        if iter.peek_is('?') {
+            return Ok(MarkupDeclValue::PI(parse_pi(iter)?));
+        }
         iter.advance_or_die()?;
-        match iter.st.c {
-            '!' => match iter.peek_or_die()? {
-                'A' => Ok(MarkupDeclValue::AttlistDecl(AttlistDeclValue::parse(iter)?)),
-                'E' => {
-                    iter.advance_or_die()?;
-                    match iter.peek_or_die()? {
-                        'L' => {
-                            iter.consume(STR_ELEMENT)?;
-                            Ok(MarkupDeclValue::ElementDecl(ElementDeclValue::parse(iter)?))
-                        }
-                        'N' => {
-                            iter.consume(STR_ENTITY)?;
-                            Ok(MarkupDeclValue::EntityDecl(EntityDeclValue::parse(iter)?))
-                        }
-                        _ => parse_err!(iter, "expected {} or {}", STR_ELEMENT, STR_ENTITY),
+        expect!(iter, '!')?;
+        match iter.peek_or_die()? {
+            'A' => Ok(MarkupDeclValue::AttlistDecl(AttlistDeclValue::parse(iter)?)),
+            'E' => {
+                iter.advance_or_die()?;
+                match iter.peek_or_die()? {
+                    'L' => {
+                        iter.consume(STR_ELEMENT)?;
+                        Ok(MarkupDeclValue::ElementDecl(ElementDeclValue::parse(iter)?))
                     }
+                    'N' => {
+                        iter.consume(STR_ENTITY)?;
+                        Ok(MarkupDeclValue::EntityDecl(EntityDeclValue::parse(iter)?))
+                    }
+                    _ => parse_err!(
+                        iter,
+                        ErrorKind::InvalidDoctype,
+                        "expected {} or {}",
+                        STR_ELEMENT,
+                        STR_ENTITY
+                    ),
                 }
-                'N' => Ok(MarkupDeclValue::NotationDecl(NotationDeclValue::parse(
-                    iter,
-                )?)),
-                '-' => Ok(MarkupDeclValue::Comment(parse_comment(iter)?)),
-                _ => parse_err!(
-                    iter,
-                    "expected {}, {}, {}, {} or comment",
-                    STR_ATTLIST,
-                    STR_ELEMENT,
-                    STR_ENTITY,
-                    STR_NOTATION
-                ),
-            },
-            '?' => Ok(MarkupDeclValue::PI(parse_pi(iter)?)),
+            }
+            'N' => Ok(MarkupDeclValue::NotationDecl(NotationDeclValue::parse(
+                iter,
+            )?)),
+            '-' => Ok(MarkupDeclValue::Comment(parse_comment(iter)?.into_owned())),
             _ => parse_err!(
                 iter,
-                "expected {}, {}, {}, {}, processing instruction or comment",
+                ErrorKind::InvalidDoctype,
+                "expected {}, {}, {}, {} or comment",
                 STR_ATTLIST,
                 STR_ELEMENT,
                 STR_ENTITY,
@@ -299,18 +320,19 @@ impl MarkupDeclValue {
 
 impl ElementDeclValue {
     /// > elementdecl ::= '<!ELEMENT' S Name S contentspec S? '>'
-    /// expects iter at the first space following `<!ELEMENT`
+    ///
+    /// Expects iter at the first space following `<!ELEMENT`.
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        let result = Ok(Self {
+        let value = Self {
             space_before_name: Whitespace::parse(iter)?,
             name: DocTypeName::parse(iter)?,
             space_after_name: Whitespace::parse(iter)?,
             content_spec: ContentSpec::parse(iter)?,
             space_after_content_spec: Whitespace::parse_optional(iter),
-        });
+        };
         expect!(iter, '>')?;
-        iter.advance();
-        result
+        iter.advance_or_die()?;
+        Ok(value)
     }
 }
 
@@ -325,29 +347,18 @@ impl ContentSpec {
                 iter.consume(STR_ANY)?;
                 Ok(ContentSpec::Any)
             }
-            '(' => {
-                let mystery = parse_mystery(iter)?;
-                match mystery {
-                    ParsedMystery::ChoiceOrSeq(c_or_s) => match c_or_s.t {
-                        ParsedChoiceOrSeqType::Choice => Ok(ContentSpec::Children(ChildrenValue {
-                            children_type: ChildrenType::Choice(ChoiceValue {
-                                cps: c_or_s.cps,
-                                space_before_close: c_or_s.ws_before_close,
-                            }),
-                            repetitions: Repetitions::parse(iter),
-                        })),
-                        ParsedChoiceOrSeqType::Seq => Ok(ContentSpec::Children(ChildrenValue {
-                            children_type: ChildrenType::Seq(SeqValue {
-                                cps: c_or_s.cps,
-                                space_before_close: c_or_s.ws_before_close,
-                            }),
-                            repetitions: Repetitions::parse(iter),
-                        })),
-                    },
-                    ParsedMystery::Mixed(m) => Ok(ContentSpec::Mixed(m)),
-                }
-            }
-            _ => parse_err!(iter, "unable to parse ContentSpec"),
+            '(' => match parse_mystery(iter)? {
+                ParsedMystery::ChoiceOrSeq(c_or_s) => Ok(ContentSpec::Children(ChildrenValue {
+                    children_type: c_or_s.into_children_type(),
+                    repetitions: Repetitions::parse(iter)?,
+                })),
+                ParsedMystery::Mixed(m) => Ok(ContentSpec::Mixed(m)),
+            },
+            _ => parse_err!(
+                iter,
+                ErrorKind::InvalidDoctype,
+                "unable to parse ContentSpec"
+            ),
         }
     }
 }
@@ -359,87 +370,65 @@ impl MixedValue {
         space_after_open_parenthesis: Option<Whitespace>,
     ) -> Result<Self> {
         iter.consume(STR_PCDATA)?;
-        let mut space_before_delimiter = None;
         let mut element_names = Vec::new();
-        loop {
-            space_before_delimiter = Whitespace::parse_optional(iter);
+        let space_before_close_parenthesis = loop {
+            let space_before_delimiter = Whitespace::parse_optional(iter);
             if iter.is(')') {
-                break;
+                break space_before_delimiter;
             }
             expect!(iter, '|')?;
+            iter.advance_or_die()?;
             let space_after_delimiter = Whitespace::parse_optional(iter);
             element_names.push(DelimitedListItem {
                 space_before_delimiter,
                 space_after_delimiter,
                 item: DocTypeName::parse(iter)?,
             });
+        };
+        iter.advance_or_die()?;
+        let zero_or_more = iter.is('*');
+        if zero_or_more {
+            iter.advance_or_die()?;
+        } else if !element_names.is_empty() {
+            return parse_err!(
+                iter,
+                ErrorKind::InvalidDoctype,
+                "mixed content with element names must end with ')*'"
+            );
         }
-        iter.advance();
         Ok(Self {
             space_after_open_parenthesis,
             element_names,
-            space_before_close_parenthesis: space_before_delimiter,
+            space_before_close_parenthesis,
+            zero_or_more,
         })
     }
 }
 
 impl Repetitions {
-    fn parse(iter: &mut Iter<'_>) -> Self {
+    fn parse(iter: &mut Iter<'_>) -> Result<Self> {
         let r = match iter.st.c {
             '?' => Repetitions::Optional,
             '+' => Repetitions::OneOrMore,
             '*' => Repetitions::ZeroOrMore,
-            _ => return Repetitions::Once,
+            _ => return Ok(Repetitions::Once),
         };
-        iter.advance();
-        r
-    }
-}
-
-impl ChildrenType {
-    fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        let choice_or_seq = match parse_mystery(iter)? {
-            ParsedMystery::ChoiceOrSeq(val) => val,
-            ParsedMystery::Mixed(_) => return parse_err!(iter, "#PCDATA cannot exist here"),
-        };
-        match choice_or_seq.t {
-            ParsedChoiceOrSeqType::Choice => Ok(ChildrenType::Choice(ChoiceValue {
-                cps: choice_or_seq.cps,
-                space_before_close: choice_or_seq.ws_before_close,
-            })),
-            ParsedChoiceOrSeqType::Seq => Ok(ChildrenType::Seq(SeqValue {
-                cps: choice_or_seq.cps,
-                space_before_close: choice_or_seq.ws_before_close,
-            })),
-        }
-    }
-}
-
-impl ChildrenValue {
-    fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        Ok(ChildrenValue {
-            children_type: ChildrenType::parse(iter)?,
-            repetitions: Repetitions::parse(iter),
-        })
+        iter.advance_or_die()?;
+        Ok(r)
     }
 }
 
 impl CpItem {
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
         if iter.is('(') {
-            let choice_or_seq = match parse_mystery(iter)? {
-                ParsedMystery::ChoiceOrSeq(val) => val,
-                ParsedMystery::Mixed(_) => return parse_err!(iter, "#PCDATA cannot exist here"),
-            };
-            match choice_or_seq.t {
-                ParsedChoiceOrSeqType::Choice => Ok(CpItem::Choice(ChoiceValue {
-                    cps: choice_or_seq.cps,
-                    space_before_close: choice_or_seq.ws_before_close,
-                })),
-                ParsedChoiceOrSeqType::Seq => Ok(CpItem::Seq(SeqValue {
-                    cps: choice_or_seq.cps,
-                    space_before_close: choice_or_seq.ws_before_close,
-                })),
+            match parse_mystery(iter)? {
+                ParsedMystery::ChoiceOrSeq(val) => Ok(match val.into_children_type() {
+                    ChildrenType::Choice(choice) => CpItem::Choice(choice),
+                    ChildrenType::Seq(seq) => CpItem::Seq(seq),
+                }),
+                ParsedMystery::Mixed(_) => {
+                    parse_err!(iter, ErrorKind::InvalidDoctype, "#PCDATA cannot exist here")
+                }
             }
         } else {
             Ok(CpItem::Name(DocTypeName::parse(iter)?))
@@ -451,7 +440,7 @@ impl CpValue {
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
         Ok(CpValue {
             cp_item: CpItem::parse(iter)?,
-            repetitions: Repetitions::parse(iter),
+            repetitions: Repetitions::parse(iter)?,
         })
     }
 }
@@ -469,6 +458,21 @@ struct ParsedChoiceOrSeq {
     ws_before_close: Option<Whitespace>,
 }
 
+impl ParsedChoiceOrSeq {
+    fn into_children_type(self) -> ChildrenType {
+        match self.t {
+            ParsedChoiceOrSeqType::Choice => ChildrenType::Choice(ChoiceValue {
+                cps: self.cps,
+                space_before_close: self.ws_before_close,
+            }),
+            ParsedChoiceOrSeqType::Seq => ChildrenType::Seq(SeqValue {
+                cps: self.cps,
+                space_before_close: self.ws_before_close,
+            }),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 enum ParsedMystery {
     ChoiceOrSeq(ParsedChoiceOrSeq),
@@ -477,7 +481,7 @@ enum ParsedMystery {
 
 /// Choice, Seq and Mixed cannot be determined until we have started parsing.
 fn parse_mystery(iter: &mut Iter<'_>) -> Result<ParsedMystery> {
-    debug_assert!(iter.is('('));
+    expect!(iter, '(')?;
     iter.advance_or_die()?;
     let space_after_open = Whitespace::parse_optional(iter);
     if iter.is('#') {
@@ -494,62 +498,44 @@ fn parse_mystery(iter: &mut Iter<'_>) -> Result<ParsedMystery> {
     }
 }
 
-/// Takes the iter after `(` and first `Whitespace`.
+/// Takes the iter after `(` and first `Whitespace`. A list with a single member is a seq.
 fn parse_choice_or_seq(
     iter: &mut Iter<'_>,
     space_after_open: Option<Whitespace>,
 ) -> Result<ParsedChoiceOrSeq> {
-    let first_cp_value = CpValue::parse(iter)?;
-    let mut cps = Vec::new();
-    cps.push(DelimitedListItem {
+    let mut cps = vec![DelimitedListItem {
         space_before_delimiter: None,
         space_after_delimiter: space_after_open,
-        item: first_cp_value,
-    });
+        item: CpValue::parse(iter)?,
+    }];
     let mut ws_before_delimiter = Whitespace::parse_optional(iter);
     let mut choice_or_seq = None;
-    loop {
-        if iter.is(')') {
-            iter.advance();
-            break;
-        }
-        if let Some(t) = choice_or_seq {
-            match t {
-                ParsedChoiceOrSeqType::Choice => expect!(iter, ',')?,
-                ParsedChoiceOrSeqType::Seq => expect!(iter, '|')?,
-            }
-        } else {
-            match iter.st.c {
-                ',' => choice_or_seq = Some(ParsedChoiceOrSeqType::Seq),
-                '|' => choice_or_seq = Some(ParsedChoiceOrSeqType::Choice),
-                _ => return parse_err!(iter, "unexpected char when parsing choice or seq"),
+    while !iter.is(')') {
+        match (choice_or_seq, iter.st.c) {
+            (Some(ParsedChoiceOrSeqType::Choice), '|')
+            | (Some(ParsedChoiceOrSeqType::Seq), ',') => {}
+            (None, ',') => choice_or_seq = Some(ParsedChoiceOrSeqType::Seq),
+            (None, '|') => choice_or_seq = Some(ParsedChoiceOrSeqType::Choice),
+            _ => {
+                return parse_err!(
+                    iter,
+                    ErrorKind::InvalidDoctype,
+                    "unexpected char when parsing choice or seq"
+                )
             }
         }
+        iter.advance_or_die()?;
         let ws_after_delimiter = Whitespace::parse_optional(iter);
-        let cp = CpValue::parse(iter)?;
         cps.push(DelimitedListItem {
             space_before_delimiter: ws_before_delimiter,
             space_after_delimiter: ws_after_delimiter,
-            item: cp,
+            item: CpValue::parse(iter)?,
         });
         ws_before_delimiter = Whitespace::parse_optional(iter);
     }
-    let t = choice_or_seq
-        .ok_or_else(|| create_parser_error!(&iter.st, "unable to determine choice or seq"))?;
-    match t {
-        ParsedChoiceOrSeqType::Choice => {
-            if cps.len() < 2 {
-                return parse_err!(iter, "choice must have at least two members");
-            }
-        }
-        ParsedChoiceOrSeqType::Seq => {
-            if cps.is_empty() {
-                return parse_err!(iter, "seq must have at least one member");
-            }
-        }
-    }
+    iter.advance_or_die()?;
     Ok(ParsedChoiceOrSeq {
-        t,
+        t: choice_or_seq.unwrap_or(ParsedChoiceOrSeqType::Seq),
         cps,
         ws_before_close: ws_before_delimiter,
     })
@@ -557,17 +543,14 @@ fn parse_choice_or_seq(
 
 impl AttlistDeclValue {
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        debug_assert!(iter.is('!'));
+        expect!(iter, '!')?;
         iter.advance_or_die()?;
         iter.consume(STR_ATTLIST)?;
         let space_before_name = Whitespace::parse(iter)?;
         let name = DocTypeName::parse(iter)?;
         let mut att_defs = Vec::new();
         let mut leftover_ws = None;
-        loop {
-            if iter.is('>') {
-                break;
-            }
+        while !iter.is('>') {
             let lead_ws = Whitespace::parse(iter)?;
             if iter.is('>') {
                 leftover_ws = Some(lead_ws);
@@ -582,6 +565,7 @@ impl AttlistDeclValue {
                 default_decl: DefaultDecl::parse(iter)?,
             })
         }
+        iter.advance_or_die()?;
         Ok(Self {
             space_before_name,
             name,
@@ -591,19 +575,6 @@ impl AttlistDeclValue {
     }
 }
 
-impl AttDef {
-    fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        Ok(Self {
-            space_before_name: Whitespace::parse(iter)?,
-            name: DocTypeName::parse(iter)?,
-            space_before_att_type: Whitespace::parse(iter)?,
-            att_type: AttType::parse(iter)?,
-            space_before_default_decl: Whitespace::parse(iter)?,
-            default_decl: DefaultDecl::parse(iter)?,
-        })
-    }
-}
-
 impl AttType {
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
         match iter.st.c {
@@ -625,14 +596,14 @@ impl AttType {
             'I' => {
                 iter.consume("ID")?;
                 if !iter.is('R') {
-                    Ok(AttType::ID)
+                    return Ok(AttType::ID);
+                }
+                iter.consume("REF")?;
+                if iter.is('S') {
+                    iter.advance_or_die()?;
+                    Ok(AttType::IDRefs)
                 } else {
-                    iter.consume("REF")?;
-                    if iter.is('S') {
-                        Ok(AttType::IDRefs)
-                    } else {
-                        Ok(AttType::IDRef)
-                    }
+                    Ok(AttType::IDRef)
                 }
             }
             'N' => {
@@ -641,13 +612,14 @@ impl AttType {
                 } else {
                     iter.consume(STR_NMTOKEN)?;
                     if iter.is('S') {
+                        iter.advance_or_die()?;
                         Ok(AttType::NMTokens)
                     } else {
                         Ok(AttType::NMToken)
                     }
                 }
             }
-            _ => parse_err!(iter, "unable to parse AttType"),
+            _ => parse_err!(iter, ErrorKind::InvalidDoctype, "unable to parse AttType"),
         }
     }
 }
@@ -661,46 +633,67 @@ impl EnumeratedType {
         } else if iter.is('(') {
             Ok(EnumeratedType::Enumeration(EnumerationValue::parse(iter)?))
         } else {
-            parse_err!(iter, "expected N or )")
+            parse_err!(
+                iter,
+                ErrorKind::InvalidDoctype,
+                "expected {} or '('",
+                STR_NOTATION
+            )
         }
     }
 }
 
+/// Parses the `Name (S? '|' S? Name)* S? ')'` part of an enumeration, given the whitespace that
+/// follows the opening parenthesis. Returns the iter pointing at the char after the `)`.
+fn parse_enumeration<T, F>(
+    iter: &mut Iter<'_>,
+    space_after_open: Option<Whitespace>,
+    parse_item: F,
+) -> Result<(Vec<DelimitedListItem<T>>, Option<Whitespace>)>
+where
+    F: Fn(&mut Iter<'_>) -> Result<T>,
+{
+    let mut items = vec![DelimitedListItem {
+        space_before_delimiter: None,
+        space_after_delimiter: space_after_open,
+        item: parse_item(iter)?,
+    }];
+    let space_before_close = loop {
+        let ws = Whitespace::parse_optional(iter);
+        if iter.is(')') {
+            break ws;
+        } else if !iter.is('|') {
+            return parse_err!(
+                iter,
+                ErrorKind::InvalidDoctype,
+                "unexpected char in enumeration list"
+            );
+        }
+        iter.advance_or_die()?;
+        let ws2 = Whitespace::parse_optional(iter);
+        items.push(DelimitedListItem {
+            space_before_delimiter: ws,
+            space_after_delimiter: ws2,
+            item: parse_item(iter)?,
+        });
+    };
+    iter.advance_or_die()?;
+    Ok((items, space_before_close))
+}
+
 impl NotationTypeValue {
     /// NotationType ::= 'NOTATION' S '(' S? Name (S? '|' S? Name)* S? ')'
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
         iter.consume(STR_NOTATION)?;
-        let ws_before_paren = Whitespace::parse(iter)?;
+        let space_before_open = Whitespace::parse(iter)?;
         expect!(iter, '(')?;
-        let ws_after_paren = Whitespace::parse_optional(iter);
-        let first_name = DocTypeName::parse(iter)?;
-        let mut names = Vec::new();
-        names.push(DelimitedListItem {
-            space_before_delimiter: None,
-            space_after_delimiter: ws_after_paren,
-            item: first_name,
-        });
-        let mut ws;
-        loop {
-            ws = Whitespace::parse_optional(iter);
-            if iter.is(')') {
-                break;
-            } else if !iter.is('|') {
-                return parse_err!(iter, "unexpected char in enumeration list");
-            }
-            iter.advance_or_die()?;
-            let ws2 = Whitespace::parse_optional(iter);
-            let name = DocTypeName::parse(iter)?;
-            names.push(DelimitedListItem {
-                space_before_delimiter: ws,
-                space_after_delimiter: ws2,
-                item: name,
-            });
-        }
+        iter.advance_or_die()?;
+        let ws = Whitespace::parse_optional(iter);
+        let (names, space_before_close) = parse_enumeration(iter, ws, DocTypeName::parse)?;
         Ok(Self {
-            space_before_open: ws_before_paren,
+            space_before_open,
             names,
-            space_before_close: ws,
+            space_before_close,
         })
     }
 }
@@ -708,64 +701,26 @@ impl NotationTypeValue {
 impl EnumerationValue {
     /// Enumeration ::= '(' S? Nmtoken (S? '|' S? Nmtoken)* S? ')'
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        debug_assert!(iter.is('('));
+        expect!(iter, '(')?;
         iter.advance_or_die()?;
-        let mut nmtokens = Vec::new();
-        nmtokens.push(DelimitedListItem {
-            space_before_delimiter: None,
-            space_after_delimiter: Whitespace::parse_optional(iter),
-            item: NmToken::parse(iter, None)?,
-        });
-        let mut ws;
-        loop {
-            ws = Whitespace::parse_optional(iter);
-            if iter.is(')') {
-                break;
-            } else if !iter.is('|') {
-                return parse_err!(iter, "unexpected char in enumeration list");
-            }
-            iter.advance_or_die()?;
-            let ws2 = Whitespace::parse_optional(iter);
-            let nmtoken = NmToken::parse(iter, None)?;
-            nmtokens.push(DelimitedListItem {
-                space_before_delimiter: ws,
-                space_after_delimiter: ws2,
-                item: nmtoken,
-            });
-        }
-
+        let ws = Whitespace::parse_optional(iter);
+        let (names, space_before_close) = parse_enumeration(iter, ws, NmToken::parse)?;
         Ok(Self {
-            names: nmtokens,
-            space_before_close: ws,
+            names,
+            space_before_close,
         })
     }
 }
 
 impl NmToken {
-    fn parse(iter: &mut Iter<'_>, end_marker: Option<char>) -> Result<Self> {
+    fn parse(iter: &mut Iter<'_>) -> Result<Self> {
+        iter.expect_name_char()?;
         let mut value = String::new();
-        loop {
-            if !iter.is_name_char() {
-                return if let Some(end) = end_marker {
-                    if iter.is(end) {
-                        Ok(Self { value })
-                    } else {
-                        parse_err!(iter, "unexpected char before '{}' was reached", end)
-                    }
-                } else {
-                    Ok(Self { value })
-                };
-            } else {
-                value.push(iter.st.c);
-                if end_marker.is_some() {
-                    iter.advance_or_die()?
-                } else {
-                    if !iter.advance() {
-                        return Ok(Self { value });
-                    }
-                }
-            }
+        while iter.is_name_char() {
+            value.push(iter.st.c);
+            iter.advance_or_die()?;
         }
+        Ok(Self { value })
     }
 }
 
@@ -775,67 +730,76 @@ impl EntityDeclValue {
     /// PEDecl ::=  '<!ENTITY' S '%' S Name S PEDef S? '>'
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
         let space_after_entity = Whitespace::parse(iter)?;
-        if iter.is('%') {
+        let value = if iter.is('%') {
             iter.advance_or_die()?;
-            Ok(EntityDeclValue::PEDecl(PEDeclValue {
+            EntityDeclValue::PEDecl(PEDeclValue {
                 space_before_percent: space_after_entity,
                 space_before_name: Whitespace::parse(iter)?,
                 name: DocTypeName::parse(iter)?,
                 space_before_pe_def: Whitespace::parse(iter)?,
                 pe_def: PEDef::parse(iter)?,
                 space_before_close: Whitespace::parse_optional(iter),
-            }))
+            })
         } else {
-            Ok(EntityDeclValue::GEDecl(GEDeclValue {
+            let name = DocTypeName::parse(iter)?;
+            let space_before_entity_def = Whitespace::parse(iter)?;
+            let (entity_def, space_before_close) = EntityDef::parse(iter)?;
+            EntityDeclValue::GEDecl(GEDeclValue {
                 space_before_name: space_after_entity,
-                name: DocTypeName::parse(iter)?,
-                space_before_entity_def: Whitespace::parse(iter)?,
-                entity_def: EntityDef::parse(iter)?,
-                space_before_close: Whitespace::parse_optional(iter),
-            }))
-        }
+                name,
+                space_before_entity_def,
+                entity_def,
+                space_before_close,
+            })
+        };
+        expect!(iter, '>')?;
+        iter.advance_or_die()?;
+        Ok(value)
     }
 }
 
 impl EntityDef {
-    fn parse(iter: &mut Iter<'_>) -> Result<Self> {
+    /// Also returns the whitespace that follows the `EntityDef`, since it can only be known
+    /// whether it precedes `NDATA` by parsing it.
+    fn parse(iter: &mut Iter<'_>) -> Result<(Self, Option<Whitespace>)> {
         if iter.is('"') || iter.is('\'') {
-            Ok(EntityDef::Entity(EntityValue::parse(iter)?))
+            let value = EntityValue::parse(iter)?;
+            Ok((EntityDef::Entity(value), Whitespace::parse_optional(iter)))
         } else {
-            Ok(EntityDef::External(EntityDefExternal::parse(iter)?))
+            let external_id = ExternalID::parse(iter)?;
+            let ws = Whitespace::parse_optional(iter);
+            match ws {
+                Some(space_before_ndata) if iter.is('N') => {
+                    iter.consume(STR_NDATA)?;
+                    let ndata_decl = NDataDecl {
+                        space_before_ndata,
+                        space_before_name: Whitespace::parse(iter)?,
+                        name: DocTypeName::parse(iter)?,
+                    };
+                    let external = EntityDefExternal {
+                        external_id,
+                        ndata_decl: Some(ndata_decl),
+                    };
+                    Ok((
+                        EntityDef::External(external),
+                        Whitespace::parse_optional(iter),
+                    ))
+                }
+                _ => {
+                    let external = EntityDefExternal {
+                        external_id,
+                        ndata_decl: None,
+                    };
+                    Ok((EntityDef::External(external), ws))
+                }
+            }
         }
     }
 }
 
-impl EntityDefExternal {
-    fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        Ok(Self {
-            external_id: ExternalID::parse(iter)?,
-            ndata_decl: if iter.is_whitespace() {
-                Some(NDataDecl::parse(iter)?)
-            } else {
-                None
-            },
-        })
-    }
-}
-
-impl NDataDecl {
-    fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        let space_before_ndata = Whitespace::parse(iter)?;
-        iter.consume(STR_NDATA)?;
-        Ok(Self {
-            space_before_ndata,
-            space_before_name: Whitespace::parse(iter)?,
-            name: DocTypeName::parse(iter)?,
-        })
-    }
-}
-
 impl PEDef {
     /// Iter should be pointing at SYSTEM, PUBLIC, ' or "
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        debug_assert!(matches!(iter.st.c, 'S' | 'P' | '\'' | '"'));
         match iter.st.c {
             'S' | 'P' => Ok(PEDef::External(ExternalID::parse(iter)?)),
             _ => Ok(PEDef::Entity(EntityValue::parse(iter)?)),
@@ -847,39 +811,28 @@ impl EntityValue {
     /// should be pointing at " or '
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
         let mut data = Vec::new();
-        let q = Quote::new(iter.st.c).map_err(|e| from_xe!(iter, e))?;
-        iter.advance_or_die()?;
-        loop {
-            if iter.st.c == q.char() {
-                break;
-            }
-            data.push(EntityValueData::parse(iter, q)?);
+        let quote = Quote::parse(iter)?;
+        while !iter.is(quote.char()) {
+            data.push(EntityValueData::parse(iter, quote)?);
         }
-        iter.advance();
-        Ok(Self { quote: q, data })
+        iter.advance_or_die()?;
+        Ok(Self { quote, data })
     }
 }
 
 impl EntityValueData {
     /// Expects the iter pointing at `&` or `%` or any non-forbidden character.
     fn parse(iter: &mut Iter<'_>, q: Quote) -> Result<Self> {
-        let c = iter.st.c;
-        if c == '&' {
-            return Ok(EntityValueData::Reference(ReferenceValue::parse(iter)?));
-        } else if c == '%' {
+        if iter.is('&') {
+            return Ok(EntityValueData::Reference(Reference::parse(iter)?));
+        } else if iter.is('%') {
             return Ok(EntityValueData::PEReference(PEReferenceValue::parse(iter)?));
         }
         // parse as text if it is not a reference
         let mut s = String::new();
-        loop {
-            if iter.st.c == q.char() {
-                break;
-            } else if iter.st.c == '&' {
-                break;
-            } else if iter.st.c == '%' {
-                break;
-            }
-            s.push(c);
+        while !iter.is(q.char()) && !iter.is('&') && !iter.is('%') {
+            iter.expect_literal_char()?;
+            s.push(iter.st.c);
             iter.advance_or_die()?;
         }
         Ok(EntityValueData::String(s))
@@ -899,35 +852,39 @@ impl Reference {
 impl CharRefValue {
     /// Expects iter pointing to `&`.
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        debug_assert!(iter.is('&'));
+        expect!(iter, '&')?;
         iter.advance_or_die()?;
         expect!(iter, '#')?;
         iter.advance_or_die()?;
-        let next = iter.peek_or_die()?;
-        let t = if next == 'x' {
+        let t = if iter.is('x') {
             iter.advance_or_die()?;
             CharRefValueType::Hex
-        } else if next.is_ascii_digit() {
-            CharRefValueType::Decimal
         } else {
-            return parse_err!(iter, "expected hex or decimal number");
+            CharRefValueType::Decimal
         };
         let mut value = String::new();
-        loop {
-            if iter.is(';') {
-                break;
+        while !iter.is(';') {
+            let valid = match t {
+                CharRefValueType::Decimal => iter.is_digit(),
+                CharRefValueType::Hex => iter.is_hex(),
+            };
+            if !valid {
+                return parse_err!(
+                    iter,
+                    ErrorKind::InvalidReference,
+                    "unexpected '{}' in character reference",
+                    iter.st.c
+                );
             }
             value.push(iter.st.c);
             iter.advance_or_die()?;
         }
-        iter.advance();
         let value = match t {
-            CharRefValueType::Decimal => value
-                .parse::<u64>()
-                .map_err(|e| create_parser_error!(&iter.st, "{}", e))?,
-            CharRefValueType::Hex => u64::from_str_radix(value.as_str(), 16)
-                .map_err(|e| create_parser_error!(&iter.st, "{}", e))?,
-        };
+            CharRefValueType::Decimal => value.parse::<u64>(),
+            CharRefValueType::Hex => u64::from_str_radix(value.as_str(), 16),
+        }
+        .map_err(|e| create_parser_error!(&iter.st, ErrorKind::InvalidReference, "{}", e))?;
+        iter.advance_or_die()?;
         Ok(Self {
             char_ref_type: t,
             value,
@@ -935,50 +892,89 @@ impl CharRefValue {
     }
 }
 
-/// Expects iter pointing at `!NOTATION` (i.e. after the `<`).
 impl NotationDeclValue {
+    /// Expects iter pointing at `!NOTATION` (i.e. after the `<`).
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        debug_assert!(iter.is('!'));
+        expect!(iter, '!')?;
         iter.advance_or_die()?;
         iter.consume(STR_NOTATION)?;
+        let space_before_name = Whitespace::parse(iter)?;
+        let name = DocTypeName::parse(iter)?;
+        let space_before_id = Whitespace::parse(iter)?;
+        let (id, space_before_close) = ExternalOrPublicID::parse(iter)?;
+        expect!(iter, '>')?;
+        iter.advance_or_die()?;
         Ok(Self {
-            space_before_name: Whitespace::parse(iter)?,
-            name: DocTypeName::parse(iter)?,
-            space_before_id: Whitespace::parse(iter)?,
-            id: ExternalOrPublicID::parse(iter)?,
-            space_before_close: Whitespace::parse_optional(iter),
+            space_before_name,
+            name,
+            space_before_id,
+            id,
+            space_before_close,
         })
     }
 }
 
 impl ExternalOrPublicID {
-    fn parse(iter: &mut Iter<'_>) -> Result<Self> {
+    /// Also returns the whitespace that follows, since it can only be known whether it precedes a
+    /// system literal by parsing it.
+    fn parse(iter: &mut Iter<'_>) -> Result<(Self, Option<Whitespace>)> {
         match iter.st.c {
-            'S' => Ok(ExternalOrPublicID::External(ExternalID::parse(iter)?)),
-            'P' => Ok(ExternalOrPublicID::Public(PublicID::parse(iter)?)),
-            _ => parse_err!(iter, "expected SYSTEM or PUBLIC"),
+            'S' => {
+                let id = ExternalID::parse(iter)?;
+                Ok((
+                    ExternalOrPublicID::External(id),
+                    Whitespace::parse_optional(iter),
+                ))
+            }
+            'P' => {
+                iter.consume(STR_PUBLIC)?;
+                let space_before_pub_id = Whitespace::parse(iter)?;
+                let pub_id_literal = PubIDLiteral::parse(iter)?;
+                let ws = Whitespace::parse_optional(iter);
+                match ws {
+                    Some(space_after_pub_id) if iter.is('"') || iter.is('\'') => {
+                        let id = PublicExternalID {
+                            space_before_pub_id,
+                            pub_id_literal,
+                            space_after_pub_id,
+                            system_literal: SystemLiteral::parse(iter)?,
+                        };
+                        Ok((
+                            ExternalOrPublicID::External(ExternalID::Public(id)),
+                            Whitespace::parse_optional(iter),
+                        ))
+                    }
+                    _ => Ok((
+                        ExternalOrPublicID::Public(PublicID {
+                            space_before_name: space_before_pub_id,
+                            public_id_literal: pub_id_literal,
+                        }),
+                        ws,
+                    )),
+                }
+            }
+            _ => parse_err!(
+                iter,
+                ErrorKind::InvalidDoctype,
+                "expected {} or {}",
+                STR_SYSTEM,
+                STR_PUBLIC
+            ),
         }
     }
 }
 
-impl PublicID {
-    fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        Ok(Self {
-            space_before_name: Whitespace::parse(iter)?,
-            public_id_literal: PubIDLiteral::parse(iter)?,
-        })
-    }
-}
-
 impl DefaultDecl {
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        let next = iter.peek_or_die()?;
-        match iter.st.c {
-            '#' if next == 'R' => {
+        if !iter.is('#') {
+            return Ok(DefaultDecl::Value(DefaultDeclAttValue::parse(iter)?));
+        }
+        match iter.peek_or_die()? {
+            'R' => {
                 iter.consume(STR_REQUIRED)?;
                 Ok(DefaultDecl::Required)
             }
-            '#' if next == 'I' => {
+            'I' => {
                 iter.consume(STR_IMPLIED)?;
                 Ok(DefaultDecl::Implied)
             }
@@ -995,7 +991,6 @@ impl DefaultDeclAttValue {
         } else {
             None
         };
-
         Ok(Self {
             fixed,
             att_value: AttValue::parse(iter)?,
@@ -1005,31 +1000,159 @@ impl DefaultDeclAttValue {
 
 impl AttValue {
     fn parse(iter: &mut Iter<'_>) -> Result<Self> {
-        let q = Quote::new(iter.st.c).map_err(|e| create_parser_error!(&iter.st, "{}", e))?;
+        let quote = Quote::parse(iter)?;
+        let mut data = Vec::new();
+        while !iter.is(quote.char()) {
+            data.push(AttValueData::parse(iter, quote)?);
+        }
         iter.advance_or_die()?;
-        Ok(Self {
-            quote: q,
-            data: AttValueData::parse(iter, q)?,
-        })
+        Ok(Self { quote, data })
     }
 }
 
 impl AttValueData {
     fn parse(iter: &mut Iter<'_>, q: Quote) -> Result<Self> {
         if iter.is('&') {
-            Ok(AttValueData::Reference(ReferenceValue::parse(iter)?))
-        } else {
-            let mut s = String::new();
-            loop {
-                if iter.is(q.char()) {
-                    iter.advance();
-                    return Ok(AttValueData::Text(s));
-                } else if Self::forbidden(iter.st.c, q) {
-                    return parse_err!(iter, "forbidden character in attribute value");
-                }
-                s.push(iter.st.c);
-                iter.advance_or_die()?;
+            return Ok(AttValueData::Reference(Reference::parse(iter)?));
+        }
+        let mut s = String::new();
+        while !iter.is(q.char()) && !iter.is('&') {
+            if Self::forbidden(iter.st.c, q) {
+                return parse_err!(
+                    iter,
+                    ErrorKind::InvalidDoctype,
+                    "forbidden character in attribute value"
+                );
             }
+            iter.expect_literal_char()?;
+            s.push(iter.st.c);
+            iter.advance_or_die()?;
         }
+        Ok(AttValueData::Text(s))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+fn round_trip(s: &str) -> DocTypeDecl {
+    let doctype = parse_doctype_str(s).unwrap();
+    assert_eq!(s, doctype.to_string());
+    doctype
+}
+
+#[test]
+fn doctype_external_only() {
+    let doctype =
+        round_trip(r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN" 'x.dtd'>"#);
+    assert_eq!("html", doctype.name());
+    let id = doctype.external_id().unwrap();
+    assert_eq!(
+        Some("-//W3C//DTD XHTML 1.0 Strict//EN"),
+        id.public_id_literal()
+    );
+    assert_eq!("x.dtd", id.system_literal());
+    assert_eq!(0, doctype.markup_decls().count());
+}
+
+#[test]
+fn doctype_element_decls() {
+    let doctype = round_trip(
+        "<!DOCTYPE doc [\n<!ELEMENT doc ( a , (b|c)* , d? )+>\n<!ELEMENT a (#PCDATA)>\
+        <!ELEMENT b (#PCDATA | x|y )*><!ELEMENT c EMPTY><!ELEMENT d ANY >\
+        <!ELEMENT x (y)><!ELEMENT y (#PCDATA)*>]>",
+    );
+    // line ends are normalized before the doctype is parsed.
+    let normalized = parse_doctype_str("<!DOCTYPE doc [\r\n<!ELEMENT doc ANY>\r]>").unwrap();
+    assert_eq!(
+        "<!DOCTYPE doc [\n<!ELEMENT doc ANY>\n]>",
+        normalized.to_string()
+    );
+    let names: Vec<&str> = doctype
+        .markup_decls()
+        .map(|decl| match decl {
+            MarkupDeclValue::ElementDecl(e) => e.name(),
+            _ => panic!("unexpected {:?}", decl),
+        })
+        .collect();
+    assert_eq!(vec!["doc", "a", "b", "c", "d", "x", "y"], names);
+    let first = doctype.markup_decls().next().unwrap();
+    match first {
+        MarkupDeclValue::ElementDecl(e) => match e.content_spec() {
+            ContentSpec::Children(children) => {
+                assert_eq!(Repetitions::OneOrMore, children.repetitions());
+                assert!(
+                    matches!(children.children_type(), ChildrenType::Seq(s) if s.cps().count() == 3)
+                );
+            }
+            other => panic!("unexpected {:?}", other),
+        },
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn doctype_attlist_decls() {
+    let doctype = round_trip(
+        r#"<!DOCTYPE doc [
+<!ATTLIST doc a1 CDATA #IMPLIED a2 ID #REQUIRED
+  a3 IDREF #IMPLIED a4 IDREFS #IMPLIED a5 ENTITY #IMPLIED a6 ENTITIES #IMPLIED
+  a7 NMTOKEN #IMPLIED a8 NMTOKENS "x y" a9 (x|y| z ) 'x' a10 NOTATION ( n ) #FIXED "&#38;&amp;&#x3C;">
+]>"#,
+    );
+    let attlist = match doctype.markup_decls().next().unwrap() {
+        MarkupDeclValue::AttlistDecl(attlist) => attlist,
+        other => panic!("unexpected {:?}", other),
+    };
+    assert_eq!("doc", attlist.name());
+    assert_eq!(10, attlist.att_defs().count());
+    let last = attlist.att_defs().last().unwrap();
+    assert_eq!("a10", last.name());
+    match last.default_decl() {
+        DefaultDecl::Value(value) => {
+            assert!(value.is_fixed());
+            assert_eq!(3, value.att_value().data().count());
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn doctype_entity_and_notation_decls() {
+    round_trip(
+        r#"<!DOCTYPE doc SYSTEM "doc.dtd" [
+<!ENTITY e1 "a &e2; &#60; b">
+<!ENTITY e2 SYSTEM 'e2.xml' >
+<!ENTITY e3 PUBLIC "pub" "e3.gif" NDATA gif>
+<!ENTITY % pe "<!ELEMENT x EMPTY>">
+%pe;
+<!NOTATION gif PUBLIC "image/gif">
+<!NOTATION png PUBLIC "image/png" 'png' >
+<!NOTATION jpg SYSTEM "jpg">
+<?pi some data?>
+<!-- comment -->
+]>"#,
+    );
+}
+
+#[test]
+fn doctype_errors() {
+    for bad in [
+        "<!DOCTYPE>",
+        "<!DOCTYPE doc [<!ELEMENT doc (a|b,c)>]>",
+        "<!DOCTYPE doc [<!ELEMENT doc (#PCDATA|a)>]>",
+        "<!DOCTYPE doc [<!ELEMENT doc (#PCDATA)>",
+        "<!DOCTYPE doc [<!ATTLIST doc a CDATA \"<\">]>",
+        "<!DOCTYPE doc [<!ATTLIST doc a (x|) #IMPLIED>]>",
+        "<!DOCTYPE doc PUBLIC \"{bad}\" \"x\">",
+        "<!DOCTYPE doc [<!ENTITY e \"&#xZZ;\">]>",
+        "<!DOCTYPE doc [ x ]>",
+        "<!DOCTYPE doc> x",
+    ] {
+        assert!(
+            parse_doctype_str(bad).is_err(),
+            "expected an error: {}",
+            bad
+        );
     }
 }
@@ -1,17 +1,249 @@
+use std::borrow::Cow;
+
+use crate::error::ErrorKind;
+use crate::parser::attlist::{apply_defaults, normalize_attribute};
 use crate::parser::bang::parse_bang;
 use crate::parser::chars::is_name_start_char;
 use crate::parser::error::Result;
-use crate::parser::pi::parse_pi;
+use crate::parser::pi::parse_pi_logic;
+use crate::parser::scratch::Scratch;
 use crate::parser::string::{parse_string, StringType};
+use crate::parser::tree::{Leaf, Tree};
 use crate::parser::{parse_name, Iter};
-use crate::{Element, Node};
+use crate::{DocTypeDecl, Limit, Location, Span};
+
+/// Parses an element and all of its content. Expects the iter to be pointing at `<` and leaves it
+/// pointing at the character after the element's closing `>`. Rather than recursing into child
+/// elements, the elements that are open are kept on a stack so that the nesting depth of a document
+/// is not limited by the size of the call stack. When recovering from errors, parsing resumes at the
+/// next markup after an error, and the elements that are still open at the end of the document are
+/// closed.
+pub(crate) fn parse_element<'a, E: Tree<'a>>(iter: &mut Iter<'a>) -> Result<E> {
+    let depth = iter.it.depth();
+    let (element, is_empty) = parse_start_tag(iter)?;
+    if is_empty {
+        iter.st.path.close();
+        return Ok(element);
+    }
+    iter.enter_element(&element);
+    // the elements whose start tag has been parsed, but not their end tag, along with the entity
+    // depth at which each of them started.
+    let mut open: Vec<(E, usize)> = vec![(element, depth)];
+    loop {
+        match parse_element_step(iter, &mut open) {
+            Ok(Some(element)) => return Ok(element),
+            Ok(None) => {}
+            Err(e) => {
+                match iter.recover(e) {
+                    Ok(()) => iter.resync(),
+                    Err(e) => iter.halt(e)?,
+                }
+                if iter.end() {
+                    let end = iter.location();
+                    while open.len() > 1 {
+                        pop_element(iter, &mut open, end);
+                    }
+                    return match open.pop() {
+                        Some((element, _)) => Ok(end_span(element, end)),
+                        None => {
+                            parse_err!(
+                                iter,
+                                ErrorKind::Internal,
+                                "the parser state is inconsistent, no open element"
+                            )
+                        }
+                    };
+                }
+            }
+        }
+    }
+}
+
+/// Parses the content of the innermost open element up to, and including, the next start or end
+/// tag. Returns the outermost element once its end tag has been parsed.
+fn parse_element_step<'a, E: Tree<'a>>(
+    iter: &mut Iter<'a>,
+    open: &mut Vec<(E, usize)>,
+) -> Result<Option<E>> {
+    let parent = match open.last_mut() {
+        Some((parent, _)) => parent,
+        None => {
+            return parse_err!(
+                iter,
+                ErrorKind::Internal,
+                "the parser state is inconsistent, no open element"
+            )
+        }
+    };
+    match parse_content(iter, parent)? {
+        Content::StartTag => {
+            let depth = iter.it.depth();
+            let (element, is_empty) = parse_start_tag(iter)?;
+            if is_empty {
+                // an empty element is never entered, so it ends along with its start tag.
+                iter.st.path.close();
+                add_element(open, element);
+            } else {
+                iter.enter_element(&element);
+                open.push((element, depth));
+            }
+            Ok(None)
+        }
+        Content::EndTag(name, start) => close_element(iter, open, &name, start),
+    }
+}
+
+/// Closes the innermost open element once the end tag `name`, which started at `start`, has been
+/// parsed. Returns the element if it is the outermost. Leaves the iter pointing at the character
+/// after `>`. When recovering from errors, an end tag that closes an outer element also closes the
+/// elements inside of it, and an end tag that closes nothing is ignored.
+fn close_element<'a, E: Tree<'a>>(
+    iter: &mut Iter<'a>,
+    open: &mut Vec<(E, usize)>,
+    name: &str,
+    start: Location,
+) -> Result<Option<E>> {
+    debug_assert_eq!('>', iter.st.c);
+    let expected = match open.last() {
+        Some((element, _)) => element.fullname(),
+        None => {
+            return parse_err!(
+                iter,
+                ErrorKind::Internal,
+                "the parser state is inconsistent, no open element"
+            )
+        }
+    };
+    if name != expected {
+        let e = create_parser_error!(
+            &iter.st,
+            ErrorKind::MismatchedEndTag,
+            "closing element name '{}' does not match openeing element name '{}'",
+            name,
+            expected
+        );
+        iter.recover(e)?;
+        if !open.iter().any(|(element, _)| element.fullname() == name) {
+            iter.advance();
+            return Ok(None);
+        }
+        while open.last().map(|(element, _)| element.fullname()) != Some(name) {
+            pop_element(iter, open, start);
+        }
+    }
+    let depth = open.last().map(|(_, depth)| *depth).unwrap_or_default();
+    if iter.it.depth() != depth {
+        let e = create_parser_error!(
+            &iter.st,
+            ErrorKind::Syntax,
+            "the element '{}' must start and end in the same entity",
+            name
+        );
+        iter.recover(e)?;
+    }
+    iter.advance(); // TODO - should this be advance_or_die?
+    debug_assert_ne!('>', iter.st.c);
+    let end = iter.location();
+    Ok(pop_element(iter, open, end))
+}
+
+/// Pops the innermost open element, whose content ends at `end`, and adds it to its parent. Returns
+/// the element if it is the outermost.
+fn pop_element<'a, E: Tree<'a>>(
+    iter: &mut Iter<'a>,
+    open: &mut Vec<(E, usize)>,
+    end: Location,
+) -> Option<E> {
+    iter.exit_element();
+    let (element, _) = open.pop()?;
+    add_element(open, end_span(element, end))
+}
+
+/// Extends the span of `element`, if it has one, to `end`.
+fn end_span<'a, E: Tree<'a>>(mut element: E, end: Location) -> E {
+    if let Some(span) = element.span() {
+        element.set_span(Span {
+            start: span.start,
+            end,
+        });
+    }
+    element
+}
+
+/// Adds a completed element to the innermost open element. Returns the element if there is no open
+/// element, i.e. it is the outermost.
+fn add_element<'a, E: Tree<'a>>(open: &mut [(E, usize)], element: E) -> Option<E> {
+    match open.last_mut() {
+        Some((parent, _)) => {
+            parent.add_child(element);
+            None
+        }
+        None => Some(element),
+    }
+}
+
+/// Parses an element's opening tag, i.e. its name and attributes. Expects the iter to be pointing at
+/// `<`. Returns the element, which will not have any child nodes, and `true` if the tag was an
+/// empty, self-closing tag like `<foo/>`. The iter is left pointing at the character after `>`.
+/// Attributes that the DTD gives a default value are added if they were not specified, and then the
+/// element's namespaces are resolved if `ParseOptions::namespaces` is set. If
+/// `ParseOptions::spans` is set, the element's span is that of the start tag.
+pub(crate) fn parse_start_tag<'a, E: Tree<'a>>(iter: &mut Iter<'a>) -> Result<(E, bool)> {
+    let start = iter.location();
+    let max_depth = iter.st.opts.limits.max_depth;
+    if iter.scopes.len() >= max_depth {
+        return limit_err!(
+            iter,
+            Limit::Depth,
+            "elements are nested more than {} deep",
+            max_depth
+        );
+    }
+    let (mut element, is_empty) = parse_start_tag_as_written::<E>(iter)?;
+    if iter.st.opts.spans {
+        let end = iter.location();
+        element.set_span(Span { start, end });
+    }
+    apply_defaults(iter, &mut element);
+    if let Some(parent) = iter.namespaces() {
+        match element.resolve(&parent) {
+            Ok(bindings) => element.set_namespaces(bindings),
+            Err(e) => {
+                iter.recover(crate::parser::error::parse_err(
+                    &iter.st,
+                    ErrorKind::Namespace,
+                    throw_site!(),
+                    Some(format!("namespace error in '{}'", element.fullname())),
+                    Some(e),
+                ))?;
+                // when recovering, the element's declarations are ignored.
+                element.set_namespaces(parent);
+            }
+        }
+    }
+    Ok((element, is_empty))
+}
 
-pub(crate) fn parse_element(iter: &mut Iter<'_>) -> Result<Element> {
+fn parse_start_tag_as_written<'a, E: Tree<'a>>(iter: &mut Iter<'a>) -> Result<(E, bool)> {
     expect!(iter, '<')?;
     iter.advance_or_die()?;
     let name = parse_name(iter)?;
-    let mut element = Element::from_name(name);
+    iter.st.path.open(&name);
+    let mut element = E::from_name(name);
+    match parse_start_tag_rest(iter, &mut element) {
+        Ok(is_empty) => Ok((element, is_empty)),
+        Err(e) => {
+            // when recovering, the element keeps the attributes that were parsed before the error.
+            iter.recover(e)?;
+            let is_empty = skip_tag(iter);
+            Ok((element, is_empty))
+        }
+    }
+}
 
+// parses the part of a start tag that follows the element's name, i.e. its attributes and the `>`
+// or `/>` that ends it. returns true if it was an empty, self-closing tag.
+fn parse_start_tag_rest<'a, E: Tree<'a>>(iter: &mut Iter<'a>, element: &mut E) -> Result<bool> {
     // absorb whitespace
     iter.skip_whitespace()?;
 
@@ -20,12 +252,12 @@ pub(crate) fn parse_element(iter: &mut Iter<'_>) -> Result<Element> {
         iter.advance_or_die()?;
         expect!(iter, '>')?;
         iter.advance();
-        return Ok(element);
+        return Ok(true);
     }
 
     // now the only valid chars are '>' or the start of an attribute name
     if iter.is_name_start_char() {
-        parse_attributes(iter, &mut element)?;
+        parse_attributes(iter, element)?;
     }
 
     // check and return early if it is an empty, self-closing tag that had attributes
@@ -33,40 +265,93 @@ pub(crate) fn parse_element(iter: &mut Iter<'_>) -> Result<Element> {
         iter.advance_or_die()?;
         expect!(iter, '>')?;
         iter.advance();
-        return Ok(element);
+        return Ok(true);
     }
 
     // now the only valid char is '>' and we reach the child nodes
     expect!(iter, '>')?;
-    iter.advance_or_die()?; // TODO - is it really fatal if we cannot advance?
-    parse_children(iter, &mut element)?;
-    debug_assert_eq!('>', iter.st.c);
-    iter.advance(); // TODO - should this be advance_or_die?
-    debug_assert_ne!('>', iter.st.c);
-    Ok(element)
+    // if the document ends here, then the content reports that the end tag is missing.
+    iter.advance();
+    Ok(false)
 }
 
-fn parse_attributes(iter: &mut Iter<'_>, element: &mut Element) -> Result<()> {
+// after an error in a start tag has been recovered from, skips to the character after the `>` that
+// ends the tag and returns true if it was an empty, self-closing tag. if a `<` comes first, then the
+// tag was never closed, and the iter is left pointing at the `<`.
+fn skip_tag(iter: &mut Iter<'_>) -> bool {
+    let mut is_empty = false;
+    while !iter.end() && !iter.is('<') {
+        if iter.is('>') {
+            iter.advance();
+            return is_empty;
+        }
+        is_empty = iter.is('/');
+        iter.advance();
+    }
+    false
+}
+
+fn parse_attributes<'a, E: Tree<'a>>(iter: &mut Iter<'a>, element: &mut E) -> Result<()> {
+    // the iter is at the first attribute's name, whitespace before it has already been skipped.
+    let mut is_separated = true;
     loop {
-        iter.skip_whitespace()?;
+        if iter.is_whitespace() {
+            is_separated = true;
+            iter.skip_whitespace()?;
+        }
         if iter.is('/') || iter.is('>') {
             break;
         }
+        if !is_separated {
+            return parse_err!(
+                iter,
+                ErrorKind::Syntax,
+                "expected whitespace between the attributes of '{}'",
+                element.fullname()
+            );
+        }
+        let start = iter.location();
         let key = if iter.is_name_start_char() {
             parse_name(iter)?
         } else {
-            String::default()
+            Cow::Borrowed("")
         };
         iter.skip_whitespace()?;
         expect!(iter, '=')?;
         iter.advance_or_die()?;
         iter.skip_whitespace()?;
-        let (start, string_type) = attribute_start_quote(iter)?;
+        let (quote, string_type) = attribute_start_quote(iter)?;
         iter.advance_or_die()?;
         let value = parse_attribute_value(iter, string_type)?;
-        expect!(iter, start)?;
-        element.add_attribute(key, value);
-        if !iter.advance() {
+        let value = normalize_attribute(iter, element.fullname(), &key, value);
+        expect!(iter, quote)?;
+        if element.attribute(&key).is_some() {
+            return parse_err!(
+                iter,
+                ErrorKind::DuplicateAttribute,
+                "the attribute '{}' appears more than once in element '{}'",
+                key,
+                element.fullname()
+            );
+        }
+        let max_attributes = iter.st.opts.limits.max_attributes;
+        if element.attributes_len() >= max_attributes {
+            return limit_err!(
+                iter,
+                Limit::Attributes,
+                "element '{}' has more than {} attributes",
+                element.fullname(),
+                max_attributes
+            );
+        }
+        iter.advance();
+        let span = iter.st.opts.spans.then(|| Span {
+            start,
+            end: iter.location(),
+        });
+        element.add_attribute(key, value, span);
+        is_separated = false;
+        if iter.end() {
             break;
         }
     }
@@ -80,6 +365,7 @@ fn attribute_start_quote(iter: &Iter<'_>) -> Result<(char, StringType)> {
         '"' => Ok((c, StringType::AttributeDouble)),
         _ => parse_err!(
             iter,
+            ErrorKind::Syntax,
             "expected attribute value to start with either a single or double quote, got '{}'",
             c
         ),
@@ -87,7 +373,7 @@ fn attribute_start_quote(iter: &Iter<'_>) -> Result<(char, StringType)> {
 }
 
 /// Expects the iter to be pointing at the first character of the string.
-fn parse_attribute_value(iter: &mut Iter<'_>, string_type: StringType) -> Result<String> {
+fn parse_attribute_value<'a>(iter: &mut Iter<'a>, string_type: StringType) -> Result<Cow<'a, str>> {
     debug_assert!(matches!(
         string_type,
         StringType::AttributeDouble | StringType::AttributeSingle
@@ -95,124 +381,151 @@ fn parse_attribute_value(iter: &mut Iter<'_>, string_type: StringType) -> Result
     parse_string(iter, string_type)
 }
 
+// what `parse_content` stopped at.
+enum Content<'a> {
+    // the iter is pointing at the `<` of a child element's start tag.
+    StartTag,
+    // an end tag, which started at the given location, has been parsed and the iter is pointing at
+    // its `>`. it is not necessarily the parent's end tag.
+    EndTag(Cow<'a, str>, Location),
+}
+
 // this function takes over after an element's opening tag (the parent element) has been parsed.
-// the nodes that are contained by the parent are parsed and added to the parent. this function is
-// recursive descending until an element with no children is reached.
-fn parse_children(iter: &mut Iter<'_>, parent: &mut Element) -> Result<()> {
+// the nodes that are contained by the parent are parsed and added to the parent until either the
+// start tag of a child element or an end tag is reached.
+fn parse_content<'a, E: Tree<'a>>(iter: &mut Iter<'a>, parent: &mut E) -> Result<Content<'a>> {
+    // whether a comment or processing instruction was discarded since the last node was added, in
+    // which case the text on either side of it is merged as if it had not been there.
+    let mut skipped = false;
     loop {
-        iter.skip_whitespace()?;
-        if iter.is('<') {
-            let lt_parse = parse_lt(iter, parent)?;
-            match lt_parse {
-                LtParse::EndTag => {
-                    // this is the recursion's breaking condition
-                    return Ok(());
-                }
+        iter.skip_content_whitespace()?;
+        let start = iter.location();
+        if iter.end() {
+            return parse_err!(
+                iter,
+                ErrorKind::UnexpectedEof,
+                "the document ended before the end tag of '{}'",
+                parent.fullname()
+            );
+        }
+        let leaf = if iter.is('<') {
+            let next = iter.peek_or_die()?;
+            if is_name_start_char(next) {
+                return Ok(Content::StartTag);
+            } else if next == '/' {
+                return Ok(Content::EndTag(parse_end_tag(iter)?, start));
+            }
+            match parse_lt(iter)? {
                 LtParse::Skip => {
-                    // do nothing
+                    skipped = true;
+                    continue;
+                }
+                LtParse::Some(leaf) => leaf,
+                LtParse::DocType(_) => {
+                    return parse_err!(iter, ErrorKind::InvalidDoctype, "doctype not allowed here")
                 }
-                LtParse::Some(node) => match node {
-                    Node::Element(elem) => parent.add_child(elem),
-                    Node::Text(text) => parent.add_text(text),
-                    Node::CData(cdata) => parent
-                        .add_cdata(cdata)
-                        .map_err(|e| create_parser_error!(&iter.st, "{}", e))?,
-                    Node::Comment(comment) => parent
-                        .add_comment(comment)
-                        .map_err(|e| create_parser_error!(&iter.st, "{}", e))?,
-                    Node::Pi(pi) => parent.add_pi(pi),
-                    Node::DocType(_) => panic!("doctype unsupported"),
-                },
-                LtParse::DocType(_) => return parse_err!(iter, "doctype not allowed here"),
             }
         } else {
             let text = parse_text(iter)?;
-            if !text.is_empty() {
-                parent.add_text(text);
+            if text.is_empty() {
+                continue;
             }
-        }
+            Leaf::Text(text)
+        };
+        let span = iter.st.opts.spans.then(|| Span {
+            start,
+            end: iter.location(),
+        });
+        parent.add_leaf(leaf, span, iter.st.opts.coalesce_cdata || skipped);
+        skipped = false;
     }
 }
 
 // the return type for `parse_lt`. since the caller of `parse_lt` doesn't know what type of node
 // has been encountered, this enum is used to describe what was parsed.
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
-pub(super) enum LtParse {
-    // the parsed entity was an EndTag.
-    EndTag,
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[allow(clippy::large_enum_variant)]
+pub(super) enum LtParse<'a> {
     // the parsed entity was an unsupported node type, i.e. something we want to skip.
-    #[allow(dead_code)] // TODO - this is because of doctype_wip
     Skip,
     // the parsed entity was a supported node type.
-    Some(Node),
-    // TODO - make this a struct to support doctypes https://github.com/webern/exile/issues/22
-    DocType(String),
+    Some(Leaf<'a>),
+    // the parsed entity was a `<!DOCTYPE ...>` declaration.
+    DocType(DocTypeDecl),
 }
 
-// parse the correct type of node (or end tag) when encountering a '<'
-fn parse_lt(iter: &mut Iter<'_>, parent: &Element) -> Result<LtParse> {
+// parse the correct type of node when encountering a '<'. start and end tags are handled by
+// `parse_element`, so that child elements are not parsed recursively.
+fn parse_lt<'a>(iter: &mut Iter<'a>) -> Result<LtParse<'a>> {
     debug_assert_eq!('<', iter.st.c);
     let next = iter.peek_or_die()?;
-    // do the most common case first
-    if is_name_start_char(next) {
-        let element = parse_element(iter)?;
-        debug_assert_ne!('>', iter.st.c);
-        return Ok(LtParse::Some(Node::Element(element)));
-    }
     match next {
-        '/' => {
-            parse_end_tag_name(iter, parent)?;
-            Ok(LtParse::EndTag)
-        }
         '?' => {
-            let pi = parse_pi(iter)?;
-            Ok(LtParse::Some(Node::Pi(pi)))
+            let (target, data) = parse_pi_logic(iter)?;
+            if iter.st.opts.processing_instructions {
+                Ok(LtParse::Some(Leaf::Pi(target, data)))
+            } else {
+                Ok(LtParse::Skip)
+            }
         }
         '!' => parse_bang(iter),
         _ => {
             // this error occurred on the peeked char, so to report the correct position of the
             // error, we will first advance the iter (if possible).
             iter.advance();
-            parse_err!(iter, "unexpected char following '<'")
+            parse_err!(iter, ErrorKind::Syntax, "unexpected char following '<'")
         }
     }
 }
 
 // takes an iter pointing at '<' where the next character is required to be '/'. parses the name of
-// the end tag and compares it to make sure it matches `parent`. if anything goes wrong, Err.
-fn parse_end_tag_name(iter: &mut Iter<'_>, parent: &Element) -> Result<()> {
+// the end tag and compares it to make sure it matches `expected`, which is the fullname of the
+// element being closed. if anything goes wrong, Err. leaves the iter pointing at the closing '>'.
+pub(crate) fn parse_end_tag_name(iter: &mut Iter<'_>, expected: &str) -> Result<()> {
+    let name = parse_end_tag(iter)?;
+    if name != expected {
+        return parse_err!(
+            iter,
+            ErrorKind::MismatchedEndTag,
+            "closing element name '{}' does not match openeing element name '{}'",
+            name,
+            expected
+        );
+    }
+    Ok(())
+}
+
+// takes an iter pointing at '<' where the next character is required to be '/'. parses and returns
+// the name of the end tag, leaving the iter pointing at the closing '>'.
+fn parse_end_tag<'a>(iter: &mut Iter<'a>) -> Result<Cow<'a, str>> {
     expect!(iter, '<')?;
     iter.advance_or_die()?;
     expect!(iter, '/')?;
     iter.advance_or_die()?;
-    iter.skip_whitespace()?;
     iter.expect_name_start_char()?;
-    let mut name = String::default();
-    name.push(iter.st.c);
+    let mut name = Scratch::new(iter);
+    name.push_current(iter);
     loop {
         iter.advance_or_die()?;
         if iter.is('>') || iter.is_whitespace() {
             break;
         } else if iter.is_name_char() {
-            name.push(iter.st.c);
+            name.push_current(iter);
         } else {
-            return parse_err!(iter);
+            return parse_err!(
+                iter,
+                ErrorKind::InvalidName,
+                "expected name char, found '{}'",
+                iter.st.c
+            );
         }
     }
     iter.skip_whitespace()?;
     expect!(iter, '>')?;
-    if name != parent.fullname() {
-        return parse_err!(
-            iter,
-            "closing element name '{}' does not match openeing element name '{}'",
-            name,
-            parent.fullname()
-        );
-    }
-    Ok(())
+    Ok(name.into_cow())
 }
 
-fn parse_text(iter: &mut Iter<'_>) -> Result<String> {
+pub(crate) fn parse_text<'a>(iter: &mut Iter<'a>) -> Result<Cow<'a, str>> {
     parse_string(iter, StringType::Element)
 }
 
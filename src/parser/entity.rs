@@ -0,0 +1,246 @@
+/*!
+The `entity` module keeps track of the general entities that are declared in the DTD and expands
+references to them by pushing their replacement text onto the parser's `Source`.
+!*/
+use std::collections::HashMap;
+
+use crate::doctype::{
+    EntityDeclValue, EntityDef, EntityValue, EntityValueData, MarkupDeclValue, Reference,
+};
+use crate::error::ErrorKind;
+use crate::parser::error::Result;
+use crate::parser::Iter;
+use crate::{DocTypeDecl, Limit};
+
+/// The entities that are predefined by the XML specification. Declarations of these are ignored.
+const PREDEFINED: [&str; 5] = ["amp", "apos", "gt", "lt", "quot"];
+
+/// The general entities that have been declared, and the number of characters that have been added
+/// to the document by expanding them.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Entities {
+    declared: HashMap<String, Entity>,
+    expanded: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Entity {
+    /// An internal entity and its replacement text.
+    Internal(String),
+    /// A parsed entity that lives in some other resource.
+    External,
+    /// An unparsed entity, i.e. one with an `NDATA` declaration.
+    Unparsed,
+}
+
+/// Adds the general entities declared in the internal subset of `doctype` to those known by `iter`.
+/// When an entity is declared more than once, the first declaration is binding.
+pub(crate) fn declare_entities(iter: &mut Iter<'_>, doctype: &DocTypeDecl) -> Result<()> {
+    for decl in doctype.markup_decls() {
+        let ge = match decl {
+            MarkupDeclValue::EntityDecl(EntityDeclValue::GEDecl(ge)) => ge,
+            _ => continue,
+        };
+        if PREDEFINED.contains(&ge.name()) || iter.entities.declared.contains_key(ge.name()) {
+            continue;
+        }
+        let entity = match ge.entity_def() {
+            EntityDef::Entity(value) => Entity::Internal(replacement_text(iter, value)?),
+            EntityDef::External(external) if external.ndata().is_some() => Entity::Unparsed,
+            EntityDef::External(_) => Entity::External,
+        };
+        iter.entities.declared.insert(ge.name().to_owned(), entity);
+    }
+    Ok(())
+}
+
+/// Builds the replacement text of an internal entity. Character references are replaced with the
+/// characters they stand for while entity references are left in place, to be expanded when the
+/// replacement text is parsed.
+fn replacement_text(iter: &Iter<'_>, value: &EntityValue) -> Result<String> {
+    let mut text = String::new();
+    for data in value.data() {
+        match data {
+            EntityValueData::String(s) => text.push_str(s),
+            EntityValueData::PEReference(pe) => {
+                return parse_err!(
+                    iter,
+                    ErrorKind::Unsupported,
+                    "parameter entity references are not supported, found '%{};'",
+                    pe.name()
+                )
+            }
+            EntityValueData::Reference(Reference::CharRef(char_ref)) => match char_ref.char() {
+                Some(c) => text.push(c),
+                None => {
+                    return parse_err!(
+                        iter,
+                        ErrorKind::InvalidReference,
+                        "illegal character reference '{}'",
+                        char_ref
+                    )
+                }
+            },
+            EntityValueData::Reference(Reference::EntityRef(entity_ref)) => {
+                text.push('&');
+                text.push_str(entity_ref.name());
+                text.push(';');
+            }
+        }
+    }
+    Ok(text)
+}
+
+/// Expands a reference to the entity `name` by pushing its replacement text onto the iter, which
+/// is expected to be pointing at the `;` that ends the reference. The replacement text is read when
+/// the iter is next advanced.
+pub(crate) fn expand_entity(iter: &mut Iter<'_>, name: &str) -> Result<()> {
+    let text = match iter.entities.declared.get(name) {
+        Some(Entity::Internal(text)) => text,
+        Some(Entity::External) => {
+            return parse_err!(
+                iter,
+                ErrorKind::Unsupported,
+                "external entities are not supported, found '&{};'",
+                name
+            )
+        }
+        Some(Entity::Unparsed) => {
+            return parse_err!(
+                iter,
+                ErrorKind::InvalidReference,
+                "the unparsed entity '{}' cannot be referenced",
+                name
+            )
+        }
+        None => {
+            return parse_err!(
+                iter,
+                ErrorKind::InvalidReference,
+                "reference to undeclared entity '&{};'",
+                name
+            )
+        }
+    };
+    if iter.it.is_expanding(name) {
+        return parse_err!(
+            iter,
+            ErrorKind::InvalidReference,
+            "the entity '{}' references itself",
+            name
+        );
+    }
+    let limits = &iter.st.opts.limits;
+    if iter.it.depth() >= limits.max_entity_depth {
+        return limit_err!(
+            iter,
+            Limit::EntityDepth,
+            "expanding '&{};' would nest entities more than {} deep",
+            name,
+            limits.max_entity_depth
+        );
+    }
+    let expanded = iter.entities.expanded + text.chars().count();
+    if expanded > limits.max_entity_expansion {
+        return limit_err!(
+            iter,
+            Limit::EntityExpansion,
+            "expanding '&{};' would exceed the limit of {} characters of entity expansion",
+            name,
+            limits.max_entity_expansion
+        );
+    }
+    iter.it.push_entity(name, text);
+    iter.entities.expanded = expanded;
+    Ok(())
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// tests
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+fn parse_entities(xml: &str) -> crate::error::Result<crate::Document> {
+    crate::parse(xml)
+}
+
+#[test]
+fn entity_text() {
+    let xml = r#"<!DOCTYPE doc [
+<!ENTITY name "exile">
+<!ENTITY greeting "hello &name;&#33;">
+]>
+<doc a="&greeting;">&greeting; &amp; goodbye</doc>"#;
+    let doc = parse_entities(xml).unwrap();
+    assert_eq!("hello exile! & goodbye", doc.root().text().unwrap());
+    assert_eq!("hello exile!", doc.root().attribute("a").unwrap());
+}
+
+#[test]
+fn entity_markup() {
+    let xml = r#"<!DOCTYPE doc [
+<!ENTITY inner "<b x='&quot;q&quot;'>bold</b>">
+<!ENTITY quotes 'say "hi" &#38;#60;'>
+]>
+<doc a="&quotes;">x &inner; y</doc>"#;
+    let doc = parse_entities(xml).unwrap();
+    let root = doc.root();
+    assert_eq!(3, root.nodes_len());
+    let b = root.child("b").unwrap();
+    assert_eq!("bold", b.text().unwrap());
+    assert_eq!("\"q\"", b.attribute("x").unwrap());
+    assert_eq!("say \"hi\" <", root.attribute("a").unwrap());
+    // a '<' from an entity is markup, which is not allowed in an attribute value.
+    let xml = r#"<!DOCTYPE doc [<!ENTITY lt2 "&#60;">]><doc a="&lt2;"/>"#;
+    assert!(parse_entities(xml).is_err());
+}
+
+#[test]
+fn entity_first_declaration_binds() {
+    let xml =
+        r#"<!DOCTYPE doc [<!ENTITY e "1"><!ENTITY e "2"><!ENTITY lt "x">]><doc>&e;&lt;</doc>"#;
+    let doc = parse_entities(xml).unwrap();
+    assert_eq!("1<", doc.root().text().unwrap());
+}
+
+#[test]
+fn entity_errors() {
+    let undeclared = "<doc>&nope;</doc>";
+    let recursive = r#"<!DOCTYPE doc [<!ENTITY a "&b;"><!ENTITY b "&a;">]><doc>&a;</doc>"#;
+    let external = r#"<!DOCTYPE doc [<!ENTITY e SYSTEM "e.xml">]><doc>&e;</doc>"#;
+    let unbalanced = r#"<!DOCTYPE doc [<!ENTITY e "<a>">]><doc>&e;</a></doc>"#;
+    for xml in &[undeclared, recursive, external, unbalanced] {
+        assert!(
+            parse_entities(xml).is_err(),
+            "expected an error for {}",
+            xml
+        );
+    }
+}
+
+#[test]
+fn entity_billion_laughs() {
+    let xml = r#"<!DOCTYPE lolz [
+<!ENTITY lol "lol">
+<!ENTITY lol1 "&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;">
+<!ENTITY lol2 "&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;">
+<!ENTITY lol3 "&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;">
+<!ENTITY lol4 "&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;">
+<!ENTITY lol5 "&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;">
+<!ENTITY lol6 "&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;">
+<!ENTITY lol7 "&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;">
+<!ENTITY lol8 "&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;">
+<!ENTITY lol9 "&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;">
+]>
+<lolz>&lol9;</lolz>"#;
+    assert!(parse_entities(xml).is_err());
+
+    let mut opts = crate::ParseOptions::default();
+    opts.limits.max_entity_depth = 2;
+    assert!(crate::parse_with(xml.replace("&lol9;", "&lol1;"), &opts).is_ok());
+    assert!(crate::parse_with(xml.replace("&lol9;", "&lol2;"), &opts).is_err());
+    opts.limits.max_entity_depth = 16;
+    opts.limits.max_entity_expansion = 100;
+    assert!(crate::parse_with(xml.replace("&lol9;", "&lol1;"), &opts).is_ok());
+    assert!(crate::parse_with(xml.replace("&lol9;", "&lol2;"), &opts).is_err());
+}
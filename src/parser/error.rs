@@ -1,7 +1,9 @@
 use std::fmt;
 use std::fmt::{Debug, Display, Formatter};
 
-use crate::error::ThrowSite;
+use crate::constants::{CARRIAGE_RETURN, NEWLINE};
+use crate::error::{ErrorKind, ThrowSite};
+use crate::parser::chars::{LINE_SEPARATOR, NEXT_LINE};
 use crate::parser::ParserState;
 
 /// Alias for `Result<T, Error>`.
@@ -9,11 +11,18 @@ pub(crate) type Result<T> = std::result::Result<T, ParseError>;
 
 /// Represents an error that occurred during parsing because the XML document is not well-formed.
 #[derive(Debug, Default)]
+#[non_exhaustive]
 pub struct ParseError {
+    /// What kind of error this is.
+    pub kind: ErrorKind,
     /// The location in this library's sourcecode where the error was thrown.
     pub throw_site: ThrowSite,
     /// The location in the XML file where the syntax error was encountered.
     pub xml_site: Option<XmlSite>,
+    /// The path to the element that was being parsed when the error was encountered, e.g.
+    /// `/catalog/cd[3]/title`, where `cd[3]` is the third `cd` element in `catalog`. `None` if the
+    /// error was not inside of the root element.
+    pub element_path: Option<Box<str>>,
     /// An optional error message.
     pub message: Option<String>,
     /// An optional underlying error (i.e. an optional wrapped error)
@@ -92,6 +101,129 @@ impl Display for ParseError {
     }
 }
 
+impl ParseError {
+    /// The stable code of the error's kind, see [`ErrorKind::code`].
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+
+    /// Renders the error for a person to read. `source` must be the document that was parsed. The
+    /// rendering has the error's code and message, the line of `source` where the error was found
+    /// (preceded by the line before it), a caret under the column, and the path to the element
+    /// that was being parsed.
+    ///
+    /// ```
+    /// let xml = "<catalog>\n  <cd><title>x</titel></cd>\n</catalog>";
+    /// let e = match exile::parse(xml) {
+    ///     Err(exile::error::Error::Parse(e)) => e,
+    ///     _ => panic!("expected a parse error"),
+    /// };
+    /// let expected = r#"error[E0003]: closing element name 'titel' does not match openeing element name 'title'
+    ///  --> 2:22
+    ///   |
+    /// 1 | <catalog>
+    /// 2 |   <cd><title>x</titel></cd>
+    ///   |                      ^
+    ///   = element: /catalog/cd/title
+    /// "#;
+    /// assert_eq!(expected, e.render(xml));
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        let mut out = format!("error[{}]: ", self.code());
+        match self.message.as_deref() {
+            Some(message) if !message.is_empty() => out.push_str(message),
+            _ => out.push_str(&self.kind.to_string()),
+        }
+        if let Some(e) = &self.source {
+            out.push_str(&format!(": {e}"));
+        }
+        out.push('\n');
+        let gutter = match &self.xml_site {
+            Some(site) => render_excerpt(&mut out, site, source),
+            None => 0,
+        };
+        if let Some(path) = &self.element_path {
+            out.push_str(&format!("{:gutter$} = element: {}\n", "", path));
+        }
+        out
+    }
+}
+
+/// Writes the location of `site`, the line of `source` that it is on, the line before it and a caret
+/// under its column. Returns the width of the line numbers.
+fn render_excerpt(out: &mut String, site: &XmlSite, source: &str) -> usize {
+    let line = site.line as usize;
+    let column = site.column as usize;
+    let gutter = line.to_string().len();
+    out.push_str(&format!(
+        "{:gutter$}--> {}:{}\n",
+        "", site.line, site.column
+    ));
+    out.push_str(&format!("{:gutter$} |\n", ""));
+    let lines = split_lines(source);
+    let first = if line > 1 { line - 1 } else { line };
+    for number in first..=line {
+        if let Some(text) = lines.get(number.wrapping_sub(1)) {
+            out.push_str(&format!("{number:>gutter$} | {text}\n"));
+        }
+    }
+    // the caret is aligned with the column, tabs included.
+    let text = lines.get(line.wrapping_sub(1)).copied().unwrap_or_default();
+    let mut indent: String = text
+        .chars()
+        .take(column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    // the column can be past the end of the line, i.e. at the end of the document.
+    let padding = column
+        .saturating_sub(1)
+        .saturating_sub(indent.chars().count());
+    indent.push_str(&" ".repeat(padding));
+    out.push_str(&format!("{:gutter$} | {indent}^\n", ""));
+    gutter
+}
+
+/// Splits `source` into lines the way the parser counts them, i.e. at `\r\n`, `\n` or a lone `\r`,
+/// and for an XML 1.1 document also at `\r\u{85}`, `\u{85}` and `\u{2028}`.
+fn split_lines(source: &str) -> Vec<&str> {
+    let v11 = declares_xml_11(source);
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut chars = source.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let is_line_end = match c {
+            CARRIAGE_RETURN => {
+                if let Some(&(_, next)) = chars.peek() {
+                    if next == NEWLINE || (v11 && next == NEXT_LINE) {
+                        chars.next();
+                    }
+                }
+                true
+            }
+            NEWLINE => true,
+            NEXT_LINE | LINE_SEPARATOR => v11,
+            _ => false,
+        };
+        if is_line_end {
+            lines.push(&source[start..i]);
+            start = chars.peek().map_or(source.len(), |&(next, _)| next);
+        }
+    }
+    lines.push(&source[start..]);
+    lines
+}
+
+/// Whether `source` starts with an XML declaration of version 1.1.
+fn declares_xml_11(source: &str) -> bool {
+    let version = source
+        .strip_prefix("<?xml")
+        .and_then(|rest| rest.split("?>").next())
+        .and_then(|declaration| declaration.split_once("version"))
+        .and_then(|(_, rest)| rest.trim_start().strip_prefix('='))
+        .map(str::trim_start);
+    matches!(version, Some(v) if v.starts_with("'1.1'") || v.starts_with("\"1.1\""))
+}
+
 impl std::error::Error for ParseError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         self.source
@@ -102,6 +234,7 @@ impl std::error::Error for ParseError {
 
 pub(super) fn parse_err<S, E>(
     parser_state: &ParserState,
+    kind: ErrorKind,
     throw_site: ThrowSite,
     message: Option<S>,
     source: Option<E>,
@@ -111,8 +244,10 @@ where
     E: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
 {
     crate::parser::error::ParseError {
+        kind,
         throw_site,
         xml_site: Some(XmlSite::from_parser(parser_state)),
+        element_path: parser_state.path.get(),
         message: message.map(|s| s.into()),
         source: source.map(|e| e.into()),
     }
@@ -1,28 +1,32 @@
 /// Creates a ParseError object.
 /// parser_state: required as the first argument
+/// kind: required as the second argument, the `ErrorKind`
 /// message: optional, can be a string or a format
 macro_rules! create_parser_error {
-    // required: first argument must be the ParserState object
-    ($parser_state:expr) => {
+    // required: first argument must be the ParserState object, second must be the ErrorKind
+    ($parser_state:expr, $kind:expr) => {
         crate::parser::error::parse_err(
             $parser_state,
+            $kind,
             throw_site!(),
             Option::<String>::None,
             Option::<crate::parser::error::ParseError>::None,
         )
     };
-    // optional: second argument can be a simple string message
-    ($parser_state:expr, $msg:expr) => {
+    // optional: third argument can be a simple string message
+    ($parser_state:expr, $kind:expr, $msg:expr) => {
         crate::parser::error::parse_err(
             $parser_state,
+            $kind,
             throw_site!(),
             Some($msg),
             Option::<crate::parser::error::ParseError>::None,
         )
     };
-    ($parser_state:expr, $fmt:expr, $($arg:expr),+) => {
+    ($parser_state:expr, $kind:expr, $fmt:expr, $($arg:expr),+) => {
         crate::parser::error::parse_err(
             $parser_state,
+            $kind,
             throw_site!(),
             Some(format!($fmt, $($arg),+)),
             Option::<crate::parser::error::ParseError>::None,
@@ -39,20 +43,31 @@ macro_rules! expect {
 
 /// Creates a Result populated by a ParseError
 /// iter: required as the first argument, `Iter`
+/// kind: required as the second argument, the `ErrorKind`
 /// message: optional, can be a string or a format
 macro_rules! parse_err {
-    // required: first argument must be the Iter object
-    ($iter:expr) => { Err(create_parser_error!(&$iter.st)) };
-    // optional: second argument can be a simple string message
-    ($iter:expr, $msg:expr) => { Err(create_parser_error!(&$iter.st, $msg) ) };
+    // required: first argument must be the Iter object, second must be the ErrorKind
+    ($iter:expr, $kind:expr) => { Err(create_parser_error!(&$iter.st, $kind)) };
+    // optional: third argument can be a simple string message
+    ($iter:expr, $kind:expr, $msg:expr) => { Err(create_parser_error!(&$iter.st, $kind, $msg) ) };
     // optional: format!
-    ($iter:expr, $fmt:expr, $($arg:expr),+) => {
-        Err(create_parser_error!(&$iter.st, $fmt, $($arg),+))
+    ($iter:expr, $kind:expr, $fmt:expr, $($arg:expr),+) => {
+        Err(create_parser_error!(&$iter.st, $kind, $fmt, $($arg),+))
     };
 }
 
 macro_rules! from_xe {
-    ($iter:expr, $xdoc_error:expr) => {{
-        create_parser_error!(&$iter.st, "{}", $xdoc_error)
+    ($iter:expr, $kind:expr, $xdoc_error:expr) => {{
+        create_parser_error!(&$iter.st, $kind, "{}", $xdoc_error)
     }};
 }
+
+/// Creates a Result populated by a ParseError of kind `ErrorKind::LimitExceeded`
+/// iter: required as the first argument, `Iter`
+/// limit: required as the second argument, the `Limit` that was exceeded
+/// message: required, a format
+macro_rules! limit_err {
+    ($iter:expr, $limit:expr, $fmt:expr, $($arg:expr),+) => {
+        parse_err!($iter, crate::error::ErrorKind::LimitExceeded($limit), $fmt, $($arg),+)
+    };
+}
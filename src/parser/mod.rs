@@ -1,59 +1,159 @@
 /*!
-This module is responsible for parsing XML from string representations.
+This module is responsible for parsing XML from string representations and `std::io::Read`
+objects.
 !*/
-use std::collections::HashMap;
-use std::iter::Peekable;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
 use std::path::Path;
-use std::str::Chars;
 
 use crate::constants::{CARRIAGE_RETURN, NEWLINE, SPACE, TAB};
-use crate::error::{OtherError, ThrowSite};
+use crate::error::{ErrorKind, OtherError, ThrowSite};
+use crate::parser::attlist::Attlists;
 use crate::parser::bang::parse_bang;
-use crate::parser::chars::{is_name_char, is_name_start_char};
+use crate::parser::chars::{
+    is_char, is_name_char, is_name_start_char, is_restricted_char, LINE_SEPARATOR, NEXT_LINE,
+};
+pub(crate) use crate::parser::doctype::parse_doctype_str;
 use crate::parser::element::{parse_element, LtParse};
+use crate::parser::entity::Entities;
 use crate::parser::error::{display_char, Result};
 pub use crate::parser::error::{ParseError, XmlSite};
-use crate::parser::pi::{parse_pi, parse_pi_logic};
-use crate::{Declaration, Document, Encoding, Misc, Node, Version};
+pub use crate::parser::options::{Limit, Limits, ParseOptions, WhitespacePolicy};
+use crate::parser::pi::parse_pi_logic;
+pub use crate::parser::reader::{Event, Reader};
+use crate::parser::scratch::Scratch;
+use crate::parser::source::Source;
+use crate::parser::tree::{DocumentTree, Leaf, Tree};
+use crate::xdoc::borrowed;
+use crate::xmlns::{initial_bindings, Bindings};
+use crate::{Declaration, Document, Encoding, Location, Version};
 
 #[macro_use]
 mod macros;
 
+mod attlist;
 mod bang;
-mod chars;
-#[cfg(feature = "doctype_wip")]
+pub(crate) mod chars;
 mod doctype;
 mod element;
+mod entity;
 mod error;
+mod options;
 mod pi;
+mod reader;
+mod scratch;
+mod source;
 mod string;
+mod tree;
 
+/// The position of the character that the parser is at. Lines, columns and the absolute position
+/// are 1-based, the byte offset is 0-based.
 #[derive(Debug, Clone, Copy, Eq, PartialOrd, PartialEq, Hash)]
 pub(super) struct Position {
     pub line: u64,
     pub column: u64,
     pub absolute: u64,
+    pub byte: u64,
+    /// The character at this position, `None` before the start or past the end of the document.
+    current: Option<char>,
+    /// Whether the character at this position ends a line, which is the case for a `\n` and for
+    /// the other line ends of XML 1.1.
+    line_end: bool,
 }
 
 impl Default for Position {
     fn default() -> Self {
-        // These are the magic values needed to make the Position values 1-based.
+        // the position is before the first character until the parser advances to it.
         Position {
             line: 1,
-            column: 1,
-            absolute: 0, // this gets advanced when we start parsing (?)
+            column: 0,
+            absolute: 0,
+            byte: 0,
+            current: None,
+            line_end: false,
         }
     }
 }
 
 impl Position {
-    fn increment(&mut self, current_char: char) {
-        self.absolute += 1;
-        if current_char == '\n' {
-            self.line += 1;
-            self.column = 0;
+    /// Moves to the character `next`, or past the end of the document if `next` is `None`.
+    fn increment(&mut self, next: Option<char>) {
+        match self.current {
+            None if self.absolute == 0 => {
+                self.column = 1;
+                self.absolute = 1;
+            }
+            None => return,
+            Some(current) => {
+                self.absolute += 1;
+                self.byte += current.len_utf8() as u64;
+                if self.line_end {
+                    self.line += 1;
+                    self.column = 1;
+                } else {
+                    self.column += 1;
+                }
+            }
+        }
+        self.current = next;
+        self.line_end = next == Some(NEWLINE);
+    }
+
+    /// The public representation of this position.
+    pub(crate) fn location(&self) -> Location {
+        Location {
+            line: self.line,
+            column: self.column,
+            offset: self.byte,
+        }
+    }
+}
+
+/// The path from the root to the element that is being parsed, e.g. `/catalog/cd[3]/title`, which
+/// is reported along with errors.
+#[derive(Debug, Default, Clone, Eq, PartialOrd, PartialEq, Hash)]
+pub(crate) struct ElementPath {
+    /// A step for each open element, e.g. `cd[3]`.
+    steps: Vec<String>,
+    /// For each depth, how many times each element name has been seen among the children of the
+    /// open element at that depth, i.e. `counts[0]` is for the children of the document.
+    counts: Vec<BTreeMap<String, usize>>,
+}
+
+impl ElementPath {
+    /// Called when the start tag of an element named `name` is parsed.
+    pub(crate) fn open(&mut self, name: &str) {
+        let depth = self.steps.len();
+        self.counts.resize_with(depth + 1, Default::default);
+        let count = self.counts[depth].entry(name.to_owned()).or_insert(0);
+        *count += 1;
+        let step = Self::step(name, *count);
+        self.steps.push(step);
+    }
+
+    /// The step of a path for the `count`th child named `name` of its parent, counting from 1. The
+    /// position is only given when there is more than one such child, e.g. `cd` and then `cd[2]`.
+    /// Element paths are written this way wherever the crate reports them.
+    pub(crate) fn step(name: &str, count: usize) -> String {
+        if count > 1 {
+            format!("{name}[{count}]")
+        } else {
+            name.to_owned()
+        }
+    }
+
+    /// Called when the element that was opened last has ended.
+    pub(crate) fn close(&mut self) {
+        self.steps.pop();
+    }
+
+    /// The path, or `None` if no element is open.
+    pub(crate) fn get(&self) -> Option<Box<str>> {
+        if self.steps.is_empty() {
+            None
         } else {
-            self.column += 1;
+            Some(format!("/{}", self.steps.join("/")).into())
         }
     }
 }
@@ -64,6 +164,13 @@ pub(crate) struct ParserState {
     pub(crate) c: char,
     pub(crate) doc_status: DocStatus,
     pub(crate) tag_status: TagStatus,
+    /// The element that is being parsed.
+    pub(crate) path: ElementPath,
+    /// The XML version given by the declaration, which changes how the rest of the document is
+    /// parsed.
+    pub(crate) version: Version,
+    /// The options that control how the document is parsed.
+    pub(crate) opts: ParseOptions,
 }
 
 impl Default for ParserState {
@@ -73,53 +180,203 @@ impl Default for ParserState {
             c: '_',
             doc_status: Default::default(),
             tag_status: Default::default(),
+            path: Default::default(),
+            version: Default::default(),
+            opts: Default::default(),
         }
     }
 }
 
 pub(crate) struct Iter<'a> {
-    pub(crate) it: Peekable<Chars<'a>>,
+    pub(crate) it: Source<'a>,
     pub(crate) st: ParserState,
+    pub(crate) entities: Entities,
+    pub(crate) attlists: Attlists,
+    /// The scope of each open element, innermost last.
+    pub(crate) scopes: Vec<Scope>,
+    /// Set when `Limits::max_input_size` has been reached.
+    exceeded: Option<ParseError>,
+    /// The errors that have been recovered from, if the parser is recovering from errors.
+    diagnostics: Option<Vec<ParseError>>,
+    /// Set when an error could not be recovered from, after which the iter behaves as if the end of
+    /// the document has been reached.
+    halted: bool,
+}
+
+/// What an open element determines about the parsing of its content.
+pub(crate) struct Scope {
+    /// The whitespace policy for the text of the element.
+    whitespace: WhitespacePolicy,
+    /// The namespace bindings of the element, if namespaces are being resolved.
+    namespaces: Option<Bindings>,
 }
 
 impl<'a> Iter<'a> {
     /// Returns an `Iter` primed with the first character, otherwise returns an error.
     fn new(s: &'a str) -> Result<Self> {
+        Self::from_source(Source::from_str(s), ParseOptions::default())
+    }
+
+    fn from_source(it: Source<'a>, opts: ParseOptions) -> Result<Self> {
         let mut i = Iter {
-            it: s.chars().peekable(),
+            it,
             st: ParserState {
                 position: Default::default(),
                 c: '\0',
                 doc_status: Default::default(),
                 tag_status: Default::default(),
+                path: Default::default(),
+                version: Default::default(),
+                opts,
             },
+            entities: Entities::default(),
+            attlists: Attlists::default(),
+            scopes: Vec::new(),
+            exceeded: None,
+            diagnostics: None,
+            halted: false,
         };
         if !i.advance() {
+            let source = i.it.take_error();
             return Err(ParseError {
+                kind: ErrorKind::UnexpectedEof,
                 throw_site: throw_site!(),
                 xml_site: None,
+                element_path: None,
                 message: Some("iter could not be initialized, empty document".to_string()),
-                source: None,
+                source: source.map(|e| Box::new(e) as _),
             });
         }
         Ok(i)
     }
 
-    /// Returns `false` if the iterator could not be advanced (end).
+    /// Converts a parser result into the library's result type. If the underlying reader failed,
+    /// then the parser only saw a premature end of the document, so the reader's error is returned
+    /// instead. Invalid UTF-8 is reported as a parse error, other I/O errors are not.
+    pub(crate) fn finish<T>(&mut self, result: Result<T>) -> crate::error::Result<T> {
+        if let Some(e) = self.exceeded.take() {
+            return Err(crate::error::Error::Parse(e));
+        }
+        match self.it.take_error() {
+            None => result.map_err(crate::error::Error::Parse),
+            Some(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+                Err(crate::error::Error::Parse(ParseError {
+                    kind: ErrorKind::Encoding,
+                    throw_site: throw_site!(),
+                    xml_site: Some(XmlSite::from_parser(&self.st)),
+                    element_path: self.st.path.get(),
+                    message: Some("the document could not be decoded".to_string()),
+                    source: Some(Box::new(e)),
+                }))
+            }
+            Some(e) => Err(crate::error::Error::Other(OtherError {
+                kind: ErrorKind::Io,
+                throw_site: throw_site!(),
+                message: Some("unable to read the document".to_string()),
+                source: Some(Box::new(e)),
+            })),
+        }
+    }
+
+    /// Called with an error that was raised while parsing. If the parser is recovering from errors,
+    /// and `e` is because the document is not well-formed, then `e` is recorded and `Ok` is
+    /// returned so that the caller can resynchronize and continue. Otherwise `e` is returned.
+    pub(crate) fn recover(&mut self, e: ParseError) -> Result<()> {
+        let fatal = matches!(
+            e.kind,
+            ErrorKind::LimitExceeded(_) | ErrorKind::Encoding | ErrorKind::Io | ErrorKind::Internal
+        );
+        match &mut self.diagnostics {
+            Some(diagnostics) if !fatal && self.exceeded.is_none() && !self.halted => {
+                diagnostics.push(e);
+                Ok(())
+            }
+            _ => Err(e),
+        }
+    }
+
+    /// Called with an error that could not be recovered from. If the parser is recovering from
+    /// errors, then `e` is recorded and parsing stops, i.e. the iter acts as if it has reached the
+    /// end of the document, so that what has been parsed so far can be returned. Otherwise `e` is
+    /// returned.
+    pub(crate) fn halt(&mut self, e: ParseError) -> Result<()> {
+        // an error that was raised because the input was cut short is not the cause.
+        let e = self.exceeded.take().unwrap_or(e);
+        match &mut self.diagnostics {
+            Some(diagnostics) => {
+                if !self.halted {
+                    diagnostics.push(e);
+                }
+                self.halted = true;
+                self.st.c = '\0';
+                Ok(())
+            }
+            None => Err(e),
+        }
+    }
+
+    /// After an error has been recovered from, skips to the next `<` so that parsing can resume with
+    /// the markup that follows. The iter is first moved past the character where the error was
+    /// raised, which may be the `<` of the markup that failed, so that the same error is not raised
+    /// again.
+    pub(crate) fn resync(&mut self) {
+        self.advance();
+        while !self.end() && !self.is('<') {
+            self.advance();
+        }
+    }
+
+    /// The location of the current character, for recording spans.
+    pub(crate) fn location(&self) -> Location {
+        self.st.position.location()
+    }
+
+    /// Returns an error if `s`, which is being parsed, is longer than allowed by the `limit`, which
+    /// must be either `Limit::NameLength` or `Limit::TextLength`.
+    pub(crate) fn check_text_limit(&self, limit: Limit, s: &str) -> Result<()> {
+        let max = match limit {
+            Limit::NameLength => self.st.opts.limits.max_name_length,
+            _ => self.st.opts.limits.max_text_length,
+        };
+        if s.len() > max {
+            limit_err!(self, limit, "the {} is longer than {} bytes", limit, max)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns `false` if the iterator could not be advanced (end). The characters of an entity's
+    /// replacement text do not move the position, so errors are reported at the entity reference.
+    /// Reaching `Limits::max_input_size` is also treated as the end, and the limit error is
+    /// returned by `finish`.
     pub(crate) fn advance(&mut self) -> bool {
+        if self.exceeded.is_some() || self.halted {
+            return false;
+        }
         let option_char = self.it.next();
         match option_char {
             Some(c) => {
-                self.st.c = c;
-                self.st.position.increment(self.st.c);
+                if self.it.depth() == 0 {
+                    let max = self.st.opts.limits.max_input_size;
+                    if self.st.position.absolute >= max as u64 {
+                        self.exceeded = Some(create_parser_error!(
+                            &self.st,
+                            ErrorKind::LimitExceeded(Limit::InputSize),
+                            "the document is longer than {} characters",
+                            max
+                        ));
+                        return false;
+                    }
+                    self.st.position.increment(Some(c));
+                    self.st.c = self.normalize_line_end(c);
+                } else {
+                    self.st.c = c;
+                }
                 true
             }
             None => {
-                // if we haven't already hit the end, the character will not be null. if that's the
-                // case, we increment the position one more time to point past the end.
-                if self.st.c != '\0' {
-                    self.st.position.increment(self.st.c);
-                }
+                // point past the end, if we haven't already.
+                self.st.position.increment(None);
                 // set the character to a null so nobody reads the previous position's character
                 self.st.c = '\0';
                 false
@@ -127,11 +384,33 @@ impl<'a> Iter<'a> {
         }
     }
 
+    /// Normalizes line ends to `\n` before anything else sees them, as described in
+    /// https://www.w3.org/TR/xml/#sec-line-ends, i.e. `\r\n` and a lone `\r` become `\n`. XML 1.1
+    /// also normalizes `\r\u{85}`, `\u{85}` and `\u{2028}`. `c` is the character that the iter has
+    /// just moved to. When it is a `\r` that is followed by a line end, the iter moves past the `\r`,
+    /// so that the position still counts the characters of the document.
+    fn normalize_line_end(&mut self, c: char) -> char {
+        let v11 = self.st.version == Version::V11;
+        match c {
+            CARRIAGE_RETURN => {
+                let next = self.it.peek().copied();
+                if next == Some(NEWLINE) || (v11 && next == Some(NEXT_LINE)) {
+                    self.it.next();
+                    self.st.position.increment(next);
+                }
+            }
+            NEXT_LINE | LINE_SEPARATOR if v11 => {}
+            _ => return c,
+        }
+        self.st.position.line_end = true;
+        NEWLINE
+    }
+
     pub(crate) fn advance_or_die(&mut self) -> Result<()> {
         if self.advance() {
             Ok(())
         } else {
-            parse_err!(self, "iter could not be advanced")
+            parse_err!(self, ErrorKind::UnexpectedEof, "iter could not be advanced")
         }
     }
 
@@ -139,9 +418,17 @@ impl<'a> Iter<'a> {
         if self.is(expected) {
             Ok(())
         } else {
+            // a character that was expected is missing if the document ended.
+            let kind = if self.end() {
+                ErrorKind::UnexpectedEof
+            } else {
+                ErrorKind::Syntax
+            };
             Err(ParseError {
+                kind,
                 throw_site: site,
                 xml_site: Some(XmlSite::from_parser(&self.st)),
+                element_path: self.st.path.get(),
                 message: Some(format!(
                     "expected '{}' but found '{}'",
                     display_char(expected),
@@ -152,6 +439,57 @@ impl<'a> Iter<'a> {
         }
     }
 
+    /// Returns an error if the current character may not appear literally in the document, i.e. it
+    /// does not match the `Char` production or it is one of the restricted characters of XML 1.1.
+    /// The characters of an entity's replacement text are not checked, they were given by
+    /// references.
+    pub(crate) fn expect_literal_char(&self) -> Result<()> {
+        let c = self.st.c;
+        if self.it.depth() > 0 {
+            return Ok(());
+        }
+        if !is_char(c, self.st.version) {
+            return parse_err!(
+                self,
+                ErrorKind::InvalidCharacter,
+                "the character U+{:04X} is not allowed in an XML document",
+                c as u32
+            );
+        }
+        if self.st.version == Version::V11 && is_restricted_char(c) {
+            return parse_err!(
+                self,
+                ErrorKind::InvalidCharacter,
+                "the control character U+{:04X} must be written as a character reference",
+                c as u32
+            );
+        }
+        Ok(())
+    }
+
+    /// The byte offset of the current character in the string that is being parsed. `None` if the
+    /// character comes from the replacement text of an entity, or if bytes are being decoded. Note
+    /// that a normalized line end is not the character that is found at its offset.
+    pub(crate) fn offset(&self) -> Option<usize> {
+        if self.it.depth() > 0 || self.it.as_str().is_none() {
+            return None;
+        }
+        Some(self.st.position.byte as usize)
+    }
+
+    /// Returns an error if the current character is not at the entity depth `depth`. Markup that
+    /// starts in the replacement text of an entity must end in it, and vice versa.
+    pub(crate) fn expect_depth(&self, depth: usize) -> Result<()> {
+        if self.it.depth() != depth {
+            return parse_err!(
+                self,
+                ErrorKind::Syntax,
+                "markup must begin and end in the same entity"
+            );
+        }
+        Ok(())
+    }
+
     pub(crate) fn is_name_start_char(&self) -> bool {
         is_name_start_char(self.st.c)
     }
@@ -171,7 +509,12 @@ impl<'a> Iter<'a> {
         if self.is_name_start_char() {
             Ok(())
         } else {
-            parse_err!(self, "expected name start char, found '{}'", self.st.c)
+            parse_err!(
+                self,
+                ErrorKind::InvalidName,
+                "expected name start char, found '{}'",
+                self.st.c
+            )
         }
     }
 
@@ -179,7 +522,12 @@ impl<'a> Iter<'a> {
         if self.is_name_char() {
             Ok(())
         } else {
-            parse_err!(self, "expected name char, found '{}'", self.st.c)
+            parse_err!(
+                self,
+                ErrorKind::InvalidName,
+                "expected name char, found '{}'",
+                self.st.c
+            )
         }
     }
 
@@ -225,13 +573,12 @@ impl<'a> Iter<'a> {
         let opt = self.it.peek();
         match opt {
             Some(c) => Ok(*c),
-            None => parse_err!(self, "unexpected end of document"),
+            None => parse_err!(self, ErrorKind::UnexpectedEof, "unexpected end of document"),
         }
     }
 
     /// Advance the iter past the end of the string given as `s`. Error if we are not point at the
     /// beginning of `s`.
-    #[allow(dead_code)] // TODO - this is because of doctype_wip
     pub(crate) fn consume<S: AsRef<str>>(&mut self, s: S) -> Result<()> {
         for c in s.as_ref().chars() {
             expect!(self, c)?;
@@ -240,34 +587,169 @@ impl<'a> Iter<'a> {
         Ok(())
     }
 
-    /// Returns true if the character is `'\0'`, which means that the iter is exhausted.
+    /// The policy for whitespace in the text of the innermost open element. This is never
+    /// `HonorXmlSpace`, which is resolved to either `Preserve` or `Collapse`.
+    pub(crate) fn whitespace(&self) -> WhitespacePolicy {
+        match self.scopes.last() {
+            Some(scope) => scope.whitespace,
+            None if self.st.opts.whitespace == WhitespacePolicy::HonorXmlSpace => {
+                WhitespacePolicy::Collapse
+            }
+            None => self.st.opts.whitespace,
+        }
+    }
+
+    /// The namespace bindings of the innermost open element, if namespaces are being resolved.
+    pub(crate) fn namespaces(&self) -> Option<Bindings> {
+        if !self.st.opts.namespaces {
+            return None;
+        }
+        match self.scopes.last() {
+            Some(scope) => scope.namespaces.clone(),
+            None => Some(initial_bindings()),
+        }
+    }
+
+    /// Must be called when the content of `element` is about to be parsed, so that its `xml:space`
+    /// attribute and namespace declarations are in scope.
+    pub(crate) fn enter_element<'b, E: Tree<'b>>(&mut self, element: &E) {
+        let xml_space = element.attribute("xml:space");
+        let whitespace = match (self.st.opts.whitespace, xml_space) {
+            (WhitespacePolicy::HonorXmlSpace, Some("preserve")) => WhitespacePolicy::Preserve,
+            (WhitespacePolicy::HonorXmlSpace, Some("default")) => WhitespacePolicy::Collapse,
+            _ => self.whitespace(),
+        };
+        self.scopes.push(Scope {
+            whitespace,
+            namespaces: element.namespaces().cloned(),
+        });
+    }
+
+    /// Must be called when the content of the element last given to `enter_element` has been
+    /// parsed.
+    pub(crate) fn exit_element(&mut self) {
+        self.scopes.pop();
+        self.st.path.close();
+    }
+
+    /// Skips whitespace in the content of an element, unless it is to be preserved.
+    pub(crate) fn skip_content_whitespace(&mut self) -> Result<()> {
+        if self.whitespace() == WhitespacePolicy::Preserve {
+            Ok(())
+        } else {
+            self.skip_whitespace()
+        }
+    }
+
+    /// Returns true if the character is `'\0'` and the iter is exhausted. A `'\0'` that is in the
+    /// document is an illegal character rather than the end.
     pub(super) fn end(&self) -> bool {
-        self.st.c == '\0'
+        self.st.c == '\0' && (self.halted || self.st.position.current.is_none())
     }
 }
 
-pub(crate) fn document_from_string<S: AsRef<str>>(s: S) -> crate::error::Result<Document> {
-    let mut iter = crate::parser::Iter::new(s.as_ref()).map_err(crate::error::Error::Parse)?;
-    let mut document = Document::new();
-    // TODO - this loop seems weird
-    loop {
-        parse_document(&mut iter, &mut document).map_err(crate::error::Error::Parse)?;
-        if !iter.advance() {
-            break;
-        }
+pub(crate) fn document_from_string<S: AsRef<str>>(
+    s: S,
+    opts: &ParseOptions,
+) -> crate::error::Result<Document> {
+    let iter = Iter::from_source(Source::from_str(s.as_ref()), opts.clone())
+        .map_err(crate::error::Error::Parse)?;
+    document_from_iter(iter)
+}
+
+/// Parses a document whose names and text borrow from `s` wherever parsing does not change them.
+/// Spans and namespaces are not recorded by borrowed documents, so those options are rejected.
+pub(crate) fn borrowed_document_from_str<'a>(
+    s: &'a str,
+    opts: &ParseOptions,
+) -> crate::error::Result<borrowed::Document<'a>> {
+    if opts.spans || opts.namespaces {
+        return Err(crate::error::Error::Other(OtherError {
+            kind: ErrorKind::Unsupported,
+            throw_site: throw_site!(),
+            message: Some(
+                "spans and namespaces are not supported when parsing a borrowed document".into(),
+            ),
+            source: None,
+        }));
     }
-    Ok(document)
+    let iter =
+        Iter::from_source(Source::from_str(s), opts.clone()).map_err(crate::error::Error::Parse)?;
+    document_from_iter(iter)
 }
 
-pub(crate) fn document_from_file<P: AsRef<Path>>(path: P) -> crate::error::Result<Document> {
-    let s = std::fs::read_to_string(path.as_ref()).map_err(|e| {
+pub(crate) fn document_from_reader<R: Read>(
+    reader: R,
+    opts: &ParseOptions,
+) -> crate::error::Result<Document> {
+    let iter = Iter::from_source(Source::from_read(Box::new(reader)), opts.clone())
+        .map_err(crate::error::Error::Parse)?;
+    document_from_iter(iter)
+}
+
+pub(crate) fn document_from_file<P: AsRef<Path>>(
+    path: P,
+    opts: &ParseOptions,
+) -> crate::error::Result<Document> {
+    let file = std::fs::File::open(path.as_ref()).map_err(|e| {
         crate::error::Error::Other(OtherError {
+            kind: ErrorKind::Io,
             throw_site: throw_site!(),
             message: Some(format!("Unable to read file '{}'", path.as_ref().display())),
             source: Some(Box::new(e)),
         })
     })?;
-    document_from_string(s)
+    document_from_reader(file, opts)
+}
+
+/// Parses a document, recovering from syntax errors. Returns as much of the document as could be
+/// parsed, along with all of the errors that were encountered.
+pub(crate) fn document_from_string_recovering<S: AsRef<str>>(
+    s: S,
+    opts: &ParseOptions,
+) -> (Document, Vec<ParseError>) {
+    let mut iter = match Iter::from_source(Source::from_str(s.as_ref()), opts.clone()) {
+        Ok(iter) => iter,
+        Err(e) => return (Document::new(), vec![e]),
+    };
+    iter.diagnostics = Some(Vec::new());
+    let mut document = Document::new();
+    loop {
+        match parse_document(&mut iter, &mut document) {
+            Ok(()) => {
+                if !iter.advance() {
+                    break;
+                }
+            }
+            Err(e) => {
+                match iter.recover(e) {
+                    Ok(()) => iter.resync(),
+                    // halting cannot fail when recovering.
+                    Err(e) => iter.halt(e).unwrap_or(()),
+                }
+                if iter.end() {
+                    break;
+                }
+            }
+        }
+    }
+    if let Some(e) = iter.exceeded.take() {
+        let _ = iter.halt(e);
+    }
+    (document, iter.diagnostics.take().unwrap_or_default())
+}
+
+fn document_from_iter<'a, D: DocumentTree<'a>>(mut iter: Iter<'a>) -> crate::error::Result<D> {
+    let mut document = D::default();
+    // TODO - this loop seems weird
+    loop {
+        let result = parse_document(&mut iter, &mut document);
+        iter.finish(result)?;
+        if !iter.advance() {
+            break;
+        }
+    }
+    iter.finish(Ok(document))
 }
 
 // TODO - disallow dead code
@@ -293,9 +775,11 @@ pub(crate) enum DocStatus {
     Epilog,
 }
 
-fn parse_document(iter: &mut Iter<'_>, document: &mut Document) -> Result<()> {
+fn parse_document<'a, D: DocumentTree<'a>>(iter: &mut Iter<'a>, document: &mut D) -> Result<()> {
     loop {
-        if iter.st.c.is_ascii_whitespace() {
+        if iter.is_whitespace() {
+            // the declaration must be the very first thing in the document.
+            prolog_started(iter);
             if !iter.advance() {
                 break;
             }
@@ -304,43 +788,49 @@ fn parse_document(iter: &mut Iter<'_>, document: &mut Document) -> Result<()> {
         expect!(iter, '<')?;
         let next = iter.peek_or_die()?;
         match next {
-            '?' => match iter.st.doc_status {
-                DocStatus::Declaration => {
-                    parse_declaration_pi(iter, document)?;
-                    iter.st.doc_status = DocStatus::Prolog
-                }
-                DocStatus::Prolog => {
-                    let pi = parse_pi(iter)?;
-                    document.add_prolog_pi(pi);
-                }
-                DocStatus::Epilog => {
-                    let pi = parse_pi(iter)?;
-                    document.add_epilog_pi(pi);
-                }
-                DocStatus::Root => {
-                    return parse_err!(
-                        iter,
-                        "the parser state is inconsistent, should not be {:?}",
-                        DocStatus::Root
-                    );
+            '?' => {
+                // the target is checked by parse_pi_logic, `xml` is only allowed at the start.
+                let (target, data) = parse_pi_logic(iter)?;
+                if target == "xml" {
+                    document.set_declaration(parse_declaration(iter, &target, &data)?);
+                } else if iter.st.opts.processing_instructions {
+                    let is_epilog = iter.st.doc_status == DocStatus::Epilog;
+                    document.add_pi(target, data, is_epilog);
                 }
-            },
+                prolog_started(iter);
+            }
             '!' => {
+                prolog_started(iter);
                 let ltparse = parse_bang(iter)?;
                 match ltparse {
-                    LtParse::Some(node) => match node {
-                        Node::Comment(c) => add_doc_misc(iter, document, Misc::Comment(c))?,
-                        Node::Pi(p) => add_doc_misc(iter, document, Misc::Pi(p))?,
-                        _ => return parse_err!(iter, "can not add document node '{:?}", node),
-                    },
+                    LtParse::Some(leaf) => add_doc_misc(iter, document, leaf)?,
                     LtParse::Skip => {}
-                    LtParse::EndTag => return parse_err!(iter, "unexpected {:?}", LtParse::EndTag),
-                    LtParse::DocType(s) => {
-                        document.set_doctype(s).map_err(|e| from_xe!(iter, e))?
+                    LtParse::DocType(_) if iter.st.doc_status == DocStatus::Epilog => {
+                        return parse_err!(
+                            iter,
+                            ErrorKind::InvalidDoctype,
+                            "the DOCTYPE must come before the root element"
+                        )
+                    }
+                    LtParse::DocType(_) if document.has_doctype() => {
+                        return parse_err!(
+                            iter,
+                            ErrorKind::InvalidDoctype,
+                            "a document can only have one DOCTYPE"
+                        )
                     }
+                    LtParse::DocType(doctype) => document.set_doctype(doctype),
                 }
             }
             _ => {
+                if iter.st.doc_status == DocStatus::Epilog {
+                    return parse_err!(
+                        iter,
+                        ErrorKind::Syntax,
+                        "a document can only have one root element"
+                    );
+                }
+                prolog_started(iter);
                 document.set_root(parse_element(iter)?);
                 iter.st.doc_status = DocStatus::Epilog;
             }
@@ -350,146 +840,248 @@ fn parse_document(iter: &mut Iter<'_>, document: &mut Document) -> Result<()> {
             break;
         }
     }
+    if iter.st.doc_status != DocStatus::Epilog {
+        return parse_err!(
+            iter,
+            ErrorKind::UnexpectedEof,
+            "the document does not have a root element"
+        );
+    }
     Ok(())
 }
 
+/// Once anything other than the XML declaration is encountered, the declaration is disallowed.
+pub(crate) fn prolog_started(iter: &mut Iter<'_>) {
+    if iter.st.doc_status == DocStatus::Declaration {
+        iter.st.doc_status = DocStatus::Prolog;
+    }
+}
+
 // TODO - this is horrible
-fn add_doc_misc(iter: &Iter<'_>, document: &mut Document, misc: Misc) -> Result<()> {
-    match misc {
-        Misc::Comment(c) if iter.st.doc_status == DocStatus::Prolog => document
-            .add_prolog_comment(c)
-            .map_err(|e| create_parser_error!(&iter.st, "{}", e)),
-        Misc::Comment(c) if iter.st.doc_status == DocStatus::Epilog => document
-            .add_epilog_comment(c)
-            .map_err(|e| create_parser_error!(&iter.st, "{}", e)),
-        Misc::Pi(p) if iter.st.doc_status == DocStatus::Prolog => {
-            document.add_prolog_pi(p);
-            Ok(())
+fn add_doc_misc<'a, D: DocumentTree<'a>>(
+    iter: &Iter<'a>,
+    document: &mut D,
+    leaf: Leaf<'a>,
+) -> Result<()> {
+    let is_epilog = match iter.st.doc_status {
+        DocStatus::Prolog => false,
+        DocStatus::Epilog => true,
+        _ => {
+            return parse_err!(
+                iter,
+                ErrorKind::Syntax,
+                "unable to add '{:?}' to the '{:?}' section of the document",
+                leaf,
+                iter.st.doc_status
+            )
         }
-        Misc::Pi(p) if iter.st.doc_status == DocStatus::Epilog => {
-            document.add_epilog_pi(p);
-            Ok(())
+    };
+    match leaf {
+        Leaf::Comment(comment) => document.add_comment(comment, is_epilog),
+        Leaf::Pi(target, data) => document.add_pi(target, data, is_epilog),
+        _ => {
+            return parse_err!(
+                iter,
+                ErrorKind::Syntax,
+                "can not add document node '{:?}",
+                leaf
+            )
         }
-        _ => parse_err!(
-            iter,
-            "unable to add '{:?}' to the '{:?}' section of the document",
-            misc,
-            iter.st.doc_status
-        ),
     }
-}
-
-// takes the iter pointing to '<' and already expected to be '<?xml ...'. parses this and places
-// the values found into the mutable document parameter
-fn parse_declaration_pi(iter: &mut Iter<'_>, document: &mut Document) -> Result<()> {
-    state_must_be_before_declaration(iter)?;
-    let (target, data) = parse_pi_logic(iter)?;
-    document.set_declaration(parse_declaration(iter, &target, &data)?);
     Ok(())
 }
 
-/// Given the target and data from the declaration processing instruction, parse the XML version and
-/// encoding. For example. `iter` is only passed to make error construction easier.
-fn parse_declaration(iter: &Iter<'_>, target: &str, data: &str) -> Result<Declaration> {
+/// The pseudo-attributes that the XML declaration can have.
+const DECLARATION_KEYS: [&str; 3] = ["version", "encoding", "standalone"];
+
+/// Given the target and data from the declaration processing instruction, parse the XML version,
+/// encoding and standalone declaration. The version is recorded in the parser state. The iter is
+/// pointing at the character after the declaration, which is normalized again if it is a line end
+/// of XML 1.1.
+fn parse_declaration(iter: &mut Iter<'_>, target: &str, data: &str) -> Result<Declaration> {
     let mut declaration = Declaration::default();
     if target != "xml" {
-        return parse_err!(iter, "pi_data.target != xml");
-    }
-    let instructions: Vec<&str> = data.split_whitespace().collect();
-    let map = parse_as_map(iter, &instructions)?;
-    if let Some(&val) = map.get("version") {
-        match val {
-            "1.0" => {
-                declaration.version = Some(Version::V10);
-            }
-            "1.1" => {
-                declaration.version = Some(Version::V11);
-            }
-            _ => {
-                return parse_err!(iter, "unknown or unsupported XML version number '{}'", val);
-            }
-        }
+        return parse_err!(iter, ErrorKind::Internal, "pi_data.target != xml");
+    }
+    let pairs = parse_pseudo_attributes(iter, data)?;
+    let map: HashMap<&str, &str> = pairs.iter().copied().collect();
+    if let Some(key) = map.keys().find(|key| !DECLARATION_KEYS.contains(key)) {
+        return parse_err!(
+            iter,
+            ErrorKind::InvalidDeclaration,
+            "unknown pseudo-attribute '{}' in the XML declaration",
+            key
+        );
+    }
+    // the pseudo-attributes must be in the order of DECLARATION_KEYS.
+    let order: Vec<usize> = pairs
+        .iter()
+        .filter_map(|(key, _)| DECLARATION_KEYS.iter().position(|k| k == key))
+        .collect();
+    if order.windows(2).any(|pair| pair[0] > pair[1]) {
+        return parse_err!(
+            iter,
+            ErrorKind::InvalidDeclaration,
+            "the pseudo-attributes of the XML declaration must be in the order version, encoding, \
+            standalone"
+        );
     }
+    declaration.standalone = match map.get("standalone") {
+        Some(&"yes") => Some(true),
+        Some(&"no") => Some(false),
+        Some(val) => {
+            return parse_err!(
+                iter,
+                ErrorKind::InvalidDeclaration,
+                "standalone must be 'yes' or 'no', found '{}'",
+                val
+            );
+        }
+        None => None,
+    };
+    declaration.version = match map.get("version") {
+        Some(&"1.0") => Version::V10,
+        Some(&"1.1") => Version::V11,
+        Some(val) => {
+            return parse_err!(
+                iter,
+                ErrorKind::InvalidDeclaration,
+                "unknown or unsupported XML version number '{}'",
+                val
+            );
+        }
+        None => {
+            return parse_err!(
+                iter,
+                ErrorKind::InvalidDeclaration,
+                "the XML declaration must have a version"
+            );
+        }
+    };
     if let Some(&val) = map.get("encoding") {
-        match val.to_ascii_lowercase().as_str() {
-            "utf-8" => {
-                declaration.encoding = Some(Encoding::Utf8);
+        let declared = match Encoding::from_label(val) {
+            Some(encoding) => encoding,
+            None => {
+                return parse_err!(
+                    iter,
+                    ErrorKind::Encoding,
+                    "unknown or unsupported encoding string '{}'",
+                    val
+                )
             }
-            _ => {
-                return parse_err!(iter, "unknown or unsupported encoding string '{}'", val);
+        };
+        // when the document was decoded from bytes, the declaration must agree with the encoding
+        // that was detected. a plain 'UTF-16' is satisfied by either byte order.
+        declaration.encoding = match (declared, iter.it.encoding()) {
+            (Encoding::Utf16Be, Some(detected @ (Encoding::Utf16Le | Encoding::Utf16Be)))
+                if val.eq_ignore_ascii_case("utf-16") =>
+            {
+                Some(detected)
             }
-        }
+            (declared, Some(detected)) if declared != detected => {
+                return parse_err!(
+                    iter,
+                    ErrorKind::Encoding,
+                    "the declared encoding '{}' does not match the detected encoding '{}'",
+                    val,
+                    detected.label()
+                );
+            }
+            (declared, _) => Some(declared),
+        };
+    }
+    iter.st.version = declaration.version;
+    if iter.it.depth() == 0 {
+        iter.st.c = iter.normalize_line_end(iter.st.c);
     }
     Ok(declaration)
 }
 
-/// Splits each string on '=', then for the value on the right, expects it to be a quoted string
-/// starting with either `"` or `'`. `iter` is only passed to ease error construction.
-fn parse_as_map<'a, S: AsRef<str>>(
-    iter: &Iter<'_>,
-    data: &'a [S],
-) -> Result<HashMap<&'a str, &'a str>> {
-    let mut result = HashMap::new();
-    for item in data {
-        let s = item.as_ref();
-        let split = s.split('=').collect::<Vec<&str>>();
-        match split.len() {
-            0 => continue,
-            1 => {
-                result.insert(*split.first().unwrap(), "");
+/// Tokenizes the pseudo-attributes of the XML declaration, which follow the attribute grammar
+/// `S Name S? '=' S? ("'" value "'" | '"' value '"')`. The pairs are returned in source order.
+/// `iter` is only passed to ease error construction.
+fn parse_pseudo_attributes<'a>(iter: &Iter<'_>, data: &'a str) -> Result<Vec<(&'a str, &'a str)>> {
+    let mut result: Vec<(&str, &str)> = Vec::new();
+    let mut rest = data.trim_start();
+    while !rest.is_empty() {
+        let key_len = rest
+            .find(|c: char| c.is_whitespace() || c == '=')
+            .unwrap_or(rest.len());
+        let (key, after_key) = rest.split_at(key_len);
+        let after_eq = match after_key.trim_start().strip_prefix('=') {
+            Some(after_eq) if !key.is_empty() => after_eq.trim_start(),
+            _ => {
+                return parse_err!(
+                    iter,
+                    ErrorKind::InvalidDeclaration,
+                    "unparseable string encountered in XML declaration: '{}'",
+                    rest
+                );
             }
+        };
+        let open = match after_eq.chars().next() {
+            Some(q @ ('\'' | '"')) => q,
             _ => {
-                let quoted_value = *split.get(1).unwrap();
-                let len = quoted_value.len();
-                if len < 2 {
-                    return parse_err!(
-                        iter,
-                        "unparseable string encountered in XML declaration: '{}'",
-                        quoted_value
-                    );
-                }
-                let open = &quoted_value[..1];
-                let middle = &quoted_value[1..len - 1];
-                let end = &quoted_value[len - 1..];
-                if (open != "'" && open != "\"") || open != end {
-                    return parse_err!(
-                        iter,
-                        "bad quotation marks encountered in XML declaration: '{}' and '{}'",
-                        open,
-                        end
-                    );
-                }
-                result.insert(*split.first().unwrap(), middle);
+                return parse_err!(
+                    iter,
+                    ErrorKind::InvalidDeclaration,
+                    "expected a quoted value for '{}' in the XML declaration",
+                    key
+                );
+            }
+        };
+        let (value, after_value) = match after_eq[1..].split_once(open) {
+            Some(split) => split,
+            None => {
+                return parse_err!(
+                    iter,
+                    ErrorKind::InvalidDeclaration,
+                    "bad quotation marks encountered in XML declaration: unclosed {} for '{}'",
+                    open,
+                    key
+                );
             }
+        };
+        if result.iter().any(|(k, _)| *k == key) {
+            return parse_err!(
+                iter,
+                ErrorKind::InvalidDeclaration,
+                "'{}' appears more than once in the XML declaration",
+                key
+            );
         }
+        result.push((key, value));
+        // pseudo-attributes must be separated by whitespace.
+        if !after_value.is_empty() && !after_value.starts_with(char::is_whitespace) {
+            return parse_err!(
+                iter,
+                ErrorKind::InvalidDeclaration,
+                "unparseable string encountered in XML declaration: '{}'",
+                after_value
+            );
+        }
+        rest = after_value.trim_start();
     }
     Ok(result)
 }
 
-fn state_must_be_before_declaration(iter: &Iter<'_>) -> Result<()> {
-    if iter.st.doc_status != DocStatus::Declaration {
-        parse_err!(iter, "state_must_be_before_declaration")
-    } else {
-        Ok(())
-    }
-}
-
-fn parse_name(iter: &mut Iter<'_>) -> Result<String> {
+fn parse_name<'a>(iter: &mut Iter<'a>) -> Result<Cow<'a, str>> {
     iter.expect_name_start_char()?;
-    let mut name = String::default();
-    name.push(iter.st.c);
+    let mut name = Scratch::new(iter);
+    name.push_current(iter);
     iter.advance_or_die()?;
     loop {
         if iter.is_after_name_char() {
             break;
         }
         iter.expect_name_char()?;
-        name.push(iter.st.c);
+        name.push_current(iter);
+        iter.check_text_limit(Limit::NameLength, name.as_str())?;
         if !iter.advance() {
             break;
         }
     }
-    Ok(name)
+    Ok(name.into_cow())
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -499,9 +1091,8 @@ fn parse_name(iter: &mut Iter<'_>) -> Result<String> {
 #[test]
 fn xml_00() {
     let xml = "<doc/>";
-    let doc = document_from_string(xml).unwrap();
-    assert!(doc.declaration().version.is_none());
-    assert!(doc.declaration().encoding.is_none());
+    let doc = document_from_string(xml, &ParseOptions::default()).unwrap();
+    assert!(doc.declaration().is_none());
     assert_eq!(0, doc.root().nodes_len());
     assert_eq!("doc", doc.root().fullname());
 }
@@ -509,33 +1100,116 @@ fn xml_00() {
 #[test]
 fn xml_01() {
     let xml = r#"<?xml version="1.0" encoding="UTF-8"?><doc/>"#;
-    let doc = document_from_string(xml).unwrap();
-    assert_eq!(Version::V10, doc.declaration().version.unwrap());
-    assert_eq!(Encoding::Utf8, doc.declaration().encoding.unwrap());
+    let doc = document_from_string(xml, &ParseOptions::default()).unwrap();
+    assert_eq!(Version::V10, doc.declaration().unwrap().version);
+    assert_eq!(Encoding::Utf8, doc.declaration().unwrap().encoding.unwrap());
     assert_eq!(0, doc.root().nodes_len());
     assert_eq!("doc", doc.root().fullname());
 }
 
 #[test]
 fn xml_02() {
+    // the version is required.
     let xml = r#"<?xml encoding="UTF-8"?><doc/>"#;
-    let doc = document_from_string(xml).unwrap();
-    assert!(doc.declaration().version.is_none());
-    assert_eq!(Encoding::Utf8, doc.declaration().encoding.unwrap());
-    assert_eq!(0, doc.root().nodes_len());
-    assert_eq!("doc", doc.root().fullname());
+    let e = document_from_string(xml, &ParseOptions::default()).unwrap_err();
+    assert_eq!(ErrorKind::InvalidDeclaration, e.kind());
 }
 
 #[test]
 fn xml_03() {
     let xml = r#"<?xml version="1.0"?><doc/>"#;
-    let doc = document_from_string(xml).unwrap();
-    assert_eq!(Version::V10, doc.declaration().version.unwrap());
-    assert!(doc.declaration().encoding.is_none());
+    let doc = document_from_string(xml, &ParseOptions::default()).unwrap();
+    assert_eq!(Version::V10, doc.declaration().unwrap().version);
+    assert!(doc.declaration().unwrap().encoding.is_none());
     assert_eq!(0, doc.root().nodes_len());
     assert_eq!("doc", doc.root().fullname());
 }
 
+#[test]
+fn xml_from_reader() {
+    let xml = r#"<?xml version="1.0"?><doc a="🍺">text</doc>"#;
+    let doc = document_from_reader(xml.as_bytes(), &ParseOptions::default()).unwrap();
+    assert_eq!(
+        doc,
+        document_from_string(xml, &ParseOptions::default()).unwrap()
+    );
+}
+
+#[test]
+fn xml_from_reader_bad_utf8() {
+    let xml = b"<doc>\xC3\x28</doc>";
+    let err = document_from_reader(&xml[..], &ParseOptions::default())
+        .err()
+        .unwrap();
+    match err {
+        crate::error::Error::Parse(pe) => assert_eq!(6, pe.xml_site.unwrap().position),
+        _ => panic!("expected a parse error"),
+    }
+}
+
+#[test]
+fn xml_from_reader_io_error() {
+    struct Broken(usize);
+    impl Read for Broken {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.0 == 0 {
+                return Err(std::io::Error::other("broken"));
+            }
+            self.0 -= 1;
+            buf[0] = b'<';
+            Ok(1)
+        }
+    }
+    let err = document_from_reader(Broken(1), &ParseOptions::default())
+        .err()
+        .unwrap();
+    assert!(matches!(err, crate::error::Error::Other(_)));
+}
+
+#[test]
+fn xml_from_reader_utf16() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-16"?><doc a="🍺">text</doc>"#;
+    let mut bytes = vec![0xFE, 0xFF];
+    bytes.extend(xml.encode_utf16().flat_map(|u| u.to_be_bytes()));
+    let doc = document_from_reader(&bytes[..], &ParseOptions::default()).unwrap();
+    assert_eq!(
+        Encoding::Utf16Be,
+        doc.declaration().unwrap().encoding.unwrap()
+    );
+    assert_eq!("🍺", doc.root().attribute("a").unwrap());
+}
+
+#[test]
+fn xml_from_reader_latin1() {
+    let bytes = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><doc>caf\xE9</doc>";
+    let doc = document_from_reader(&bytes[..], &ParseOptions::default()).unwrap();
+    assert_eq!(
+        Encoding::Iso8859_1,
+        doc.declaration().unwrap().encoding.unwrap()
+    );
+    assert_eq!("café", doc.root().text().unwrap());
+}
+
+#[test]
+fn xml_from_reader_spaced_encoding() {
+    // the same bytes that the source detects as windows-1252 parse into a document.
+    let bytes = b"<?xml version='1.0' encoding = 'windows-1252'?><a>\x80\xE9</a>";
+    let doc = crate::from_reader(&bytes[..]).unwrap();
+    assert_eq!(
+        Encoding::Windows1252,
+        doc.declaration().unwrap().encoding.unwrap()
+    );
+    assert_eq!("€é", doc.root().text().unwrap());
+}
+
+#[test]
+fn xml_from_reader_encoding_mismatch() {
+    let xml = r#"<?xml version="1.0" encoding="ISO-8859-1"?><doc/>"#;
+    let mut bytes = vec![0xFF, 0xFE];
+    bytes.extend(xml.encode_utf16().flat_map(|u| u.to_le_bytes()));
+    assert!(document_from_reader(&bytes[..], &ParseOptions::default()).is_err());
+}
+
 #[test]
 fn consume_test() {
     let s = "bones and bish(";
@@ -552,12 +1226,791 @@ fn consume_test_err() {
     assert!(result.is_err());
 }
 
+#[cfg(test)]
+fn parse_whitespace(xml: &str, whitespace: WhitespacePolicy) -> Document {
+    let opts = ParseOptions {
+        whitespace,
+        ..ParseOptions::default()
+    };
+    document_from_string(xml, &opts).unwrap()
+}
+
+#[test]
+fn whitespace_policies() {
+    use crate::Node;
+    let xml = "<doc>\n  <p>  some <b>bold</b>  text\n </p>\n</doc>";
+    let texts = |doc: &Document| -> Vec<String> {
+        let p = doc.root().child("p").unwrap();
+        p.nodes()
+            .filter_map(|n| match n {
+                Node::Text(t) => Some(t.clone()),
+                _ => None,
+            })
+            .collect()
+    };
+    let collapsed = parse_whitespace(xml, WhitespacePolicy::Collapse);
+    assert_eq!(1, collapsed.root().nodes_len());
+    assert_eq!(vec!["some", "text"], texts(&collapsed));
+    let trimmed = parse_whitespace(xml, WhitespacePolicy::TrimOnly);
+    assert_eq!(1, trimmed.root().nodes_len());
+    assert_eq!(vec!["some", "text"], texts(&trimmed));
+    let preserved = parse_whitespace(xml, WhitespacePolicy::Preserve);
+    assert_eq!(3, preserved.root().nodes_len());
+    assert_eq!(vec!["  some ", "  text\n "], texts(&preserved));
+    let trimmed = parse_whitespace("<doc> a \n\t b </doc>", WhitespacePolicy::TrimOnly);
+    assert_eq!("a \n\t b", trimmed.root().text().unwrap());
+}
+
+#[test]
+fn whitespace_honor_xml_space() {
+    let xml = r#"<doc> a  b <pre xml:space="preserve"> a  b <x xml:space="default"> a  b </x><y> a  b </y></pre></doc>"#;
+    let doc = parse_whitespace(xml, WhitespacePolicy::HonorXmlSpace);
+    let pre = doc.root().child("pre").unwrap();
+    assert_eq!("a b", doc.root().text().unwrap());
+    assert_eq!(" a  b ", pre.text().unwrap());
+    assert_eq!("a b", pre.child("x").unwrap().text().unwrap());
+    assert_eq!(" a  b ", pre.child("y").unwrap().text().unwrap());
+}
+
+#[test]
+fn whitespace_preserve_round_trip() {
+    let xml = "<doc>\n  <p>some <b>bold</b>  text</p>\n  <!--c-->\n</doc>";
+    let doc = parse_whitespace(xml, WhitespacePolicy::Preserve);
+    let opts = crate::WriteOpts {
+        mixed_content: crate::MixedContent::Preserve,
+        ..crate::WriteOpts::default()
+    };
+    assert_eq!(format!("{}\n", xml), doc.to_string_opts(&opts).unwrap());
+}
+
+#[test]
+fn namespaces_resolved_when_parsing() {
+    let opts = ParseOptions {
+        namespaces: true,
+        ..ParseOptions::default()
+    };
+    let xml =
+        r#"<doc xmlns="http://d" xmlns:a="http://a"><a:x a:id="1" id="2"/><y xmlns=""/></doc>"#;
+    let doc = document_from_string(xml, &opts).unwrap();
+    assert_eq!(Some("http://d"), doc.root().namespace_uri());
+    let x = doc.root().child("a:x").unwrap();
+    assert_eq!("{http://a}x", x.expanded_name().to_string());
+    assert_eq!("1", x.attribute_ns("http://a", "id").unwrap());
+    assert_eq!("2", x.attribute_ns("", "id").unwrap());
+    assert_eq!(None, doc.root().child("y").unwrap().namespace_uri());
+
+    let err = document_from_string("<doc><a:x/></doc>", &opts).unwrap_err();
+    assert!(format!("{}", err).contains("the prefix 'a' is not bound"));
+    assert!(document_from_string("<doc><a:x/></doc>", &ParseOptions::default()).is_ok());
+}
+
+#[test]
+fn parse_options_retention() {
+    let xml = "<?xml version=\"1.0\"?><?pi before?><!--before--><doc>a<!--c--><?pi?>b<![CDATA[<c>]]>d</doc><!--after-->";
+    let doc = document_from_string(xml, &ParseOptions::default()).unwrap();
+    assert_eq!(6, doc.root().nodes_len());
+    assert_eq!(2, doc.prolog_misc().count());
+
+    let opts = ParseOptions::new()
+        .comments(false)
+        .processing_instructions(false);
+    let doc = document_from_string(xml, &opts).unwrap();
+    // the text around a discarded comment or processing instruction is merged.
+    assert_eq!(3, doc.root().nodes_len());
+    assert_eq!("ab", doc.root().text().unwrap());
+    assert_eq!(0, doc.prolog_misc().count());
+    assert_eq!(0, doc.epilog_misc().count());
+    let doc = document_from_string("<a>x<!--c-->y</a>", &ParseOptions::new().comments(false));
+    let doc = doc.unwrap();
+    assert_eq!(1, doc.root().nodes_len());
+    assert_eq!("xy", doc.root().text().unwrap());
+    // text is not merged with the text before an element.
+    let doc = document_from_string(
+        "<a>x<b/><!--c-->y</a>",
+        &ParseOptions::new().comments(false),
+    );
+    assert_eq!(3, doc.unwrap().root().nodes_len());
+
+    let doc = document_from_string(xml, &opts.coalesce_cdata(true)).unwrap();
+    assert_eq!(1, doc.root().nodes_len());
+    assert_eq!("ab<c>d", doc.root().text().unwrap());
+}
+
+#[cfg(test)]
+fn limit_exceeded(xml: &str, limits: Limits) -> Option<Limit> {
+    let opts = ParseOptions::new().limits(limits);
+    match document_from_string(xml, &opts) {
+        Err(crate::error::Error::Parse(ParseError {
+            kind: ErrorKind::LimitExceeded(limit),
+            ..
+        })) => Some(limit),
+        _ => None,
+    }
+}
+
+#[test]
+fn limits_enforced() {
+    let nested = |depth: usize| format!("{}{}", "<a>".repeat(depth), "</a>".repeat(depth));
+    let default = Limits::default;
+    // depth is unbounded by default.
+    assert!(document_from_string(nested(100_000), &ParseOptions::default()).is_ok());
+    let shallow = || Limits {
+        max_depth: 256,
+        ..default()
+    };
+    let opts = ParseOptions::new().limits(shallow());
+    assert!(document_from_string(nested(256), &opts).is_ok());
+    assert_eq!(Some(Limit::Depth), limit_exceeded(&nested(257), shallow()));
+    let xml = nested(3);
+    let opts = ParseOptions::new().limits(Limits {
+        max_depth: 2,
+        ..default()
+    });
+    let mut reader = Reader::new_with(&xml, &opts).unwrap();
+    assert!(reader.next_event().is_ok());
+    assert!(reader.next_event().is_ok());
+    assert!(reader.next_event().is_err());
+
+    let xml = r#"<doc a="1" b="2"><text>hello</text><!--12345--><![CDATA[123456]]></doc>"#;
+    assert_eq!(None, limit_exceeded(xml, default()));
+    let limits = Limits {
+        max_input_size: xml.len() - 1,
+        ..default()
+    };
+    assert_eq!(Some(Limit::InputSize), limit_exceeded(xml, limits));
+    let limits = Limits {
+        max_attributes: 1,
+        ..default()
+    };
+    assert_eq!(Some(Limit::Attributes), limit_exceeded(xml, limits));
+    let limits = Limits {
+        max_name_length: 3,
+        ..default()
+    };
+    assert_eq!(Some(Limit::NameLength), limit_exceeded(xml, limits));
+    for max_text_length in &[4, 5] {
+        let limits = Limits {
+            max_text_length: *max_text_length,
+            ..default()
+        };
+        assert_eq!(Some(Limit::TextLength), limit_exceeded(xml, limits));
+    }
+    // a text node or attribute value of exactly the maximum length is allowed, one more is not.
+    let text_limit = |max_text_length| Limits {
+        max_text_length,
+        ..default()
+    };
+    for xml in &["<doc>hell</doc>", "<doc a='hell'/>"] {
+        assert_eq!(None, limit_exceeded(xml, text_limit(4)));
+    }
+    for xml in &["<doc>hello</doc>", "<doc a='hello'/>"] {
+        assert_eq!(Some(Limit::TextLength), limit_exceeded(xml, text_limit(4)));
+    }
+}
+
+#[test]
+fn spans_recorded() {
+    let xml = "<r>\n  <é a='1'  b=\"2\"/><!--c--><![CDATA[d]]>\n<?p q?>text</r>";
+    let opts = ParseOptions::new().spans(true);
+    let doc = crate::parse_with(xml, &opts).unwrap();
+    let root = doc.root();
+    let span = root.span().unwrap();
+    assert_eq!(0..xml.len(), span.range());
+    assert_eq!(
+        (1, 1, 0),
+        (span.start.line, span.start.column, span.start.offset)
+    );
+    let e = root.child("é").unwrap();
+    let span = e.span().unwrap();
+    assert_eq!("<é a='1'  b=\"2\"/>", &xml[span.range()]);
+    assert_eq!((2, 3), (span.start.line, span.start.column));
+    assert_eq!("a='1'", &xml[e.attribute_span("a").unwrap().range()]);
+    assert_eq!("b=\"2\"", &xml[e.attribute_span("b").unwrap().range()]);
+    let nodes: Vec<&str> = (0..root.nodes_len())
+        .map(|i| &xml[root.node_span(i).unwrap().range()])
+        .collect();
+    let expected = vec![
+        "<é a='1'  b=\"2\"/>",
+        "<!--c-->",
+        "<![CDATA[d]]>",
+        "<?p q?>",
+        "text",
+    ];
+    assert_eq!(expected, nodes);
+    let unrecorded = crate::parse(xml).unwrap();
+    assert!(unrecorded.root().span().is_none());
+    assert_eq!(doc, unrecorded);
+}
+
+#[test]
+fn recover_from_errors() {
+    let recover = |xml: &str| document_from_string_recovering(xml, &ParseOptions::default());
+    let xml = "<r><!-- -- --><a x=1 y='2'>t</b></a><c><d>&bad; &amp;</c><e z=\"<f/></r>";
+    let (doc, errors) = recover(xml);
+    let messages: Vec<String> = errors.iter().map(|e| e.message.clone().unwrap()).collect();
+    assert_eq!(7, errors.len(), "{:#?}", messages);
+    assert!(messages[1].contains("attribute value"));
+    assert!(messages[2].contains("'b' does not match"));
+    assert!(messages[3].contains("'&bad;'"));
+    assert!(messages[4].contains("'c' does not match"));
+    assert!(messages[6].contains("'r' does not match"));
+    let root = doc.root();
+    let names: Vec<&str> = root.children().map(|e| e.name()).collect();
+    assert_eq!(vec!["a", "c", "e"], names);
+    let a = root.child("a").unwrap();
+    assert_eq!("t", a.text().unwrap());
+    assert!(a.attribute("x").is_none());
+    assert_eq!(
+        "&",
+        root.child("c").unwrap().child("d").unwrap().text().unwrap()
+    );
+    assert!(root.child("e").unwrap().child("f").is_some());
+
+    // elements that are still open at the end of the document are closed.
+    let (doc, errors) = recover("<?xml version='1.0'?><!-- -- --><r><a>text");
+    assert_eq!(2, errors.len());
+    assert_eq!("text", doc.root().child("a").unwrap().text().unwrap());
+    let (doc, errors) = recover("<r><a>");
+    let messages: Vec<&str> = errors
+        .iter()
+        .map(|e| e.message.as_deref().unwrap_or(""))
+        .collect();
+    assert_eq!(
+        vec!["the document ended before the end tag of 'a'"],
+        messages
+    );
+    assert_eq!(ErrorKind::UnexpectedEof, errors[0].kind);
+    assert!(doc.root().child("a").is_some());
+
+    // an error at a `<` is reported once, not again after resynchronizing.
+    let (doc, errors) = recover("<a/><b/>");
+    let messages: Vec<&str> = errors
+        .iter()
+        .map(|e| e.message.as_deref().unwrap_or(""))
+        .collect();
+    assert_eq!(vec!["a document can only have one root element"], messages);
+    assert_eq!(5, errors[0].xml_site.as_ref().unwrap().column);
+    assert_eq!("a", doc.root().name());
+
+    // a well-formed document has no errors.
+    let xml = "<r><a x='1'>&lt;</a><!--c--></r>";
+    let (doc, errors) = recover(xml);
+    assert!(errors.is_empty());
+    assert_eq!(crate::parse(xml).unwrap(), doc);
+
+    // a limit stops the parse.
+    let opts = ParseOptions::new().limits(Limits {
+        max_depth: 2,
+        ..Limits::default()
+    });
+    let (doc, errors) =
+        document_from_string_recovering("<r><a><b><c/></b></a><d>&x;</d></r>", &opts);
+    assert_eq!(1, errors.len());
+    assert_eq!(ErrorKind::LimitExceeded(Limit::Depth), errors[0].kind);
+    assert!(doc.root().child("a").is_some());
+    assert!(doc.root().child("d").is_none());
+}
+
+#[test]
+fn errors_rendered() {
+    let parse_error = |xml: &str, opts: &ParseOptions| match document_from_string(xml, opts) {
+        Err(crate::error::Error::Parse(e)) => e,
+        _ => panic!("expected a parse error"),
+    };
+    let xml = "<catalog><cd/><x/><cd><t/></cd><cd>\n\t<title a='1' a2 ='2' b/>";
+    let e = parse_error(xml, &ParseOptions::default());
+    assert_eq!(Some("/catalog/cd[3]/title"), e.element_path.as_deref());
+    let rendered = e.render(xml);
+    let lines: Vec<&str> = rendered.lines().collect();
+    assert_eq!(" --> 2:24", lines[1]);
+    assert_eq!("2 | \t<title a='1' a2 ='2' b/>", lines[4]);
+    assert_eq!(format!("  | \t{}^", " ".repeat(22)), lines[5]);
+    assert_eq!("  = element: /catalog/cd[3]/title", lines[6]);
+
+    let xml = "<a b='&x;'/>";
+    let e = parse_error(xml, &ParseOptions::default());
+    let expected = "error[E0006]: reference to undeclared entity '&x;'\n --> 1:9\n  |\n1 | <a b='&x;'/>\n  |         ^\n  = element: /a\n";
+    assert_eq!(expected, e.render(xml));
+
+    // XML 1.1 line ends are lines in the excerpt, as they are for the line and column.
+    let xml = "<?xml version='1.1'?>\u{85}<a>\u{2028}  <b></a>";
+    let e = parse_error(xml, &ParseOptions::default());
+    let rendered = e.render(xml);
+    let lines: Vec<&str> = rendered.lines().collect();
+    assert_eq!(" --> 3:9", lines[1], "{}", rendered);
+    assert_eq!("2 | <a>", lines[3]);
+    assert_eq!("3 |   <b></a>", lines[4]);
+    assert_eq!(format!("  | {}^", " ".repeat(8)), lines[5]);
+    // but not in XML 1.0.
+    let xml = "<a>\u{85}<b></a>";
+    let e = parse_error(xml, &ParseOptions::default());
+    let rendered = e.render(xml);
+    assert!(rendered.contains("1 | <a>\u{85}<b></a>\n"), "{}", rendered);
+
+    let opts = ParseOptions::new().limits(Limits {
+        max_depth: 1,
+        ..Limits::default()
+    });
+    let e = parse_error("<a><b/></a>", &opts);
+    assert_eq!("E0101", e.code());
+    assert!(e
+        .render("")
+        .starts_with("error[E0101]: elements are nested more than 1 deep\n"));
+}
+
+#[test]
+fn error_kinds() {
+    let kind = |xml: &str| match crate::parse(xml) {
+        Err(e) => e.kind(),
+        Ok(_) => panic!("expected an error for {}", xml),
+    };
+    let cases = [
+        ("<a><b></a>", ErrorKind::MismatchedEndTag),
+        ("<a><b>", ErrorKind::UnexpectedEof),
+        ("<a x='1'", ErrorKind::UnexpectedEof),
+        ("<a></a!>", ErrorKind::InvalidName),
+        ("<?1 x?><a/>", ErrorKind::InvalidName),
+        ("<a x='<'/>", ErrorKind::InvalidCharacter),
+        ("<a>&x;</a>", ErrorKind::InvalidReference),
+        ("<a>&#xD800;</a>", ErrorKind::InvalidReference),
+        ("<?xml version='2.0'?><a/>", ErrorKind::InvalidDeclaration),
+        (
+            "<?xml version='1.0' encoding='x'?><a/>",
+            ErrorKind::Encoding,
+        ),
+        ("<a/><!DOCTYPE a>", ErrorKind::InvalidDoctype),
+        (
+            "<!DOCTYPE a [<!ENTITY e SYSTEM 'e'>]><a>&e;</a>",
+            ErrorKind::Unsupported,
+        ),
+        ("<a><!-- -- --></a>", ErrorKind::Syntax),
+    ];
+    for (xml, expected) in cases.iter() {
+        assert_eq!(*expected, kind(xml), "{}", xml);
+    }
+    let opts = ParseOptions::new().namespaces(true);
+    let e = crate::parse_with("<a:b/>", &opts).unwrap_err();
+    assert_eq!(ErrorKind::Namespace, e.kind());
+    let e = crate::load("/this/file/does/not/exist.xml").unwrap_err();
+    assert_eq!(ErrorKind::Io, e.kind());
+    assert_eq!("E0201", e.kind().code());
+}
+
+#[test]
+fn xml_11() {
+    let opts = ParseOptions::new().whitespace(WhitespacePolicy::Preserve);
+    let xml = "<?xml version='1.1'?>\u{85}<doc a='x\u{2028}y'>a\r\u{85}b\rc\u{2028}d&#x1;</doc>";
+    let doc = crate::parse_with(xml, &opts).unwrap();
+    assert_eq!(Version::V11, doc.declaration().unwrap().version);
+    assert_eq!("x y", doc.root().attribute("a").unwrap());
+    let text = "a\nb\nc\nd\u{1}";
+    assert_eq!(text, doc.root().text().unwrap());
+
+    // restricted characters must be references, and each line end counts as one line.
+    let e = match crate::parse("<?xml version='1.1'?>\r\n<a>\u{85}\u{2028}\u{1}</a>") {
+        Err(crate::error::Error::Parse(e)) => e,
+        _ => panic!("expected a parse error"),
+    };
+    assert_eq!(ErrorKind::InvalidCharacter, e.kind);
+    let site = e.xml_site.unwrap();
+    assert_eq!((4, 1), (site.line, site.column));
+
+    // in XML 1.0 the same references and line ends have no special meaning.
+    assert!(crate::parse("<a>&#x1;</a>").is_err());
+    let doc = crate::parse_with("<a>\u{85}\u{2028}</a>", &opts).unwrap();
+    assert_eq!("\u{85}\u{2028}", doc.root().text().unwrap());
+
+    // the writer escapes the characters that would be lost or rejected when parsed as 1.1.
+    let mut doc = Document::new();
+    doc.set_declaration(Declaration {
+        version: Version::V11,
+        encoding: None,
+        standalone: None,
+    });
+    let root = doc.root_mut();
+    root.set_name("doc");
+    root.add_attribute("a", "\u{85}");
+    root.add_text("a\u{1}b\u{2028}");
+    let written = doc.to_string();
+    assert!(written.contains("<doc a=\"&#x85;\">a&#x1;b&#x2028;</doc>"));
+    let doc = crate::parse_with(written, &opts).unwrap();
+    assert_eq!("\u{85}", doc.root().attribute("a").unwrap());
+    assert_eq!("a\u{1}b\u{2028}", doc.root().text().unwrap());
+}
+
+#[test]
+fn line_ends_normalized() {
+    let opts = ParseOptions::new().whitespace(WhitespacePolicy::Preserve);
+    let doc = crate::parse_with("<a b='x\r\ny'>1\r\n2\r3&#xD;</a>", &opts).unwrap();
+    assert_eq!("x y", doc.root().attribute("b").unwrap());
+    assert_eq!("1\n2\n3\r", doc.root().text().unwrap());
+    let e = match crate::parse("<a>\r\n\r<b>\u{0}</b></a>") {
+        Err(crate::error::Error::Parse(e)) => e,
+        _ => panic!("expected a parse error"),
+    };
+    let site = e.xml_site.unwrap();
+    assert_eq!((3, 4), (site.line, site.column));
+}
+
+#[test]
+fn illegal_chars_rejected() {
+    let cases = [
+        ("<a>\u{0}</a>", ErrorKind::InvalidCharacter),
+        ("<a>x\u{1}</a>", ErrorKind::InvalidCharacter),
+        ("<a b='\u{1F}'/>", ErrorKind::InvalidCharacter),
+        ("<a><!--\u{FFFE}--></a>", ErrorKind::InvalidCharacter),
+        ("<a><?p \u{FFFF}?></a>", ErrorKind::InvalidCharacter),
+        ("<a><![CDATA[\u{8}]]></a>", ErrorKind::InvalidCharacter),
+        (
+            "<!DOCTYPE a [<!ENTITY e '\u{2}'>]><a/>",
+            ErrorKind::InvalidCharacter,
+        ),
+        ("<a>&#0;</a>", ErrorKind::InvalidReference),
+        ("<a>&#xFFFE;</a>", ErrorKind::InvalidReference),
+    ];
+    for (xml, expected) in cases.iter() {
+        match crate::parse(xml) {
+            Err(e) => assert_eq!(*expected, e.kind(), "{:?}", xml),
+            Ok(_) => panic!("expected an error for {:?}", xml),
+        }
+    }
+}
+
+#[test]
+fn not_well_formed() {
+    let cases = [
+        ("<a x='1' x='2'/>", ErrorKind::DuplicateAttribute),
+        ("<a x='1'y='2'/>", ErrorKind::Syntax),
+        ("<a>x]]>y</a>", ErrorKind::Syntax),
+        ("<a><!-- a -- b --></a>", ErrorKind::Syntax),
+        ("<a></ a>", ErrorKind::InvalidName),
+        ("<a></a><b/>", ErrorKind::Syntax),
+        ("<a><b></a>", ErrorKind::MismatchedEndTag),
+        ("<!DOCTYPE a><!DOCTYPE a><a/>", ErrorKind::InvalidDoctype),
+        (" <?xml version='1.0'?><a/>", ErrorKind::InvalidDeclaration),
+        (
+            "<!--c--><?xml version='1.0'?><a/>",
+            ErrorKind::InvalidDeclaration,
+        ),
+        (
+            "<a><?xml version='1.0'?></a>",
+            ErrorKind::InvalidDeclaration,
+        ),
+        ("<a/><?xml version='1.0'?>", ErrorKind::InvalidDeclaration),
+        ("<a><?XmL x?></a>", ErrorKind::InvalidName),
+        (
+            "<?xml encoding='UTF-8'?><a/>",
+            ErrorKind::InvalidDeclaration,
+        ),
+        (
+            "<?xml version='1.0' v='1'?><a/>",
+            ErrorKind::InvalidDeclaration,
+        ),
+        (
+            "<?xml version='1.0' version='1.0'?><a/>",
+            ErrorKind::InvalidDeclaration,
+        ),
+        (
+            "<?xml encoding='UTF-8' version='1.0'?><a/>",
+            ErrorKind::InvalidDeclaration,
+        ),
+        (
+            "<?xml version='1.0' standalone='YES'?><a/>",
+            ErrorKind::InvalidDeclaration,
+        ),
+        ("<?xml version=1.0?><a/>", ErrorKind::InvalidDeclaration),
+        ("<?xml version='1.0\"?><a/>", ErrorKind::InvalidDeclaration),
+        (
+            "<?xml version='1.0'encoding='UTF-8'?><a/>",
+            ErrorKind::InvalidDeclaration,
+        ),
+        ("<?xml version ?><a/>", ErrorKind::InvalidDeclaration),
+        ("<a>&#RE;</a>", ErrorKind::InvalidReference),
+        (
+            "<!DOCTYPE a [<!ENTITY e '<![CDATA['>]><a>&e;]]></a>",
+            ErrorKind::Syntax,
+        ),
+    ];
+    for (xml, expected) in cases.iter() {
+        match crate::parse(xml) {
+            Err(e) => assert_eq!(*expected, e.kind(), "{:?}", xml),
+            Ok(_) => panic!("expected an error for {:?}", xml),
+        }
+        // the reader rejects the same documents.
+        let events = crate::Reader::new(xml)
+            .and_then(|reader| reader.collect::<crate::error::Result<Vec<_>>>());
+        assert!(events.is_err(), "expected a reader error for {:?}", xml);
+    }
+}
+
+#[test]
+fn not_well_formed_standalone() {
+    // documents in the style of the standalone not-well-formed cases of the W3C conformance suite,
+    // grouped by the kind of mistake. the suite's own files are run as generated tests.
+    let cases = [
+        // empty and misplaced content
+        "",
+        "<doc></doc>\nText not allowed at end",
+        "<doc></doc>\n&#32;",
+        "<doc/>\n</doc>",
+        "<doc/>\n<doc></doc>",
+        "<doc>\n</doc>\n<![CDATA[]]>",
+        "<!-- a comment -->\n<![CDATA[]]>\n<doc></doc>",
+        "<!-- a comment -->\n&#32;\n<doc></doc>",
+        "text<doc/>",
+        // tags
+        "<doc></>",
+        "<doc></DOC>",
+        "<doc><a></aa></doc>",
+        "<doc>\n<a/\n</doc>",
+        "<doc>\n<a/</a>\n</doc>",
+        "<doc>\n<a / >\n</doc>",
+        "<doc>\n<123></123>\n</doc>",
+        "<doc\x0C>A form feed is not white space</doc\x0C>",
+        "< doc/>",
+        "<doc></doc x='1'>",
+        "<doc>",
+        "<doc><a>",
+        // attributes
+        "<doc a1></doc>",
+        "<doc a1=v1></doc>",
+        "<doc a1=\"v1'></doc>",
+        "<doc a1=\"<foo>\"></doc>",
+        "<doc a1=></doc>",
+        "<doc a1=\"v1\" \"v2\"></doc>",
+        "<doc a1=\"A & B\"></doc>",
+        "<doc a1=\"a&b\"></doc>",
+        "<doc a1=\"&#123:\"></doc>",
+        "<doc 12=\"34\"></doc>",
+        "<doc x=\"foo\" y=\"bar\" x=\"baz\"></doc>",
+        "<doc a=\"1\"b=\"2\"/>",
+        "<doc a=\"&undefined;\"/>",
+        // references
+        "<doc>&amp no refc</doc>",
+        "<doc>&.entity;</doc>",
+        "<doc>&#RE;</doc>",
+        "<doc>A & B</doc>",
+        "<doc>&#x0;</doc>",
+        "<doc>&#xD800;</doc>",
+        "<doc>&#-1;</doc>",
+        "<doc>&undefined;</doc>",
+        "<doc>& amp;</doc>",
+        // character data and characters
+        "<doc>]]></doc>",
+        "<doc>]]]></doc>",
+        "<doc>abc]]]>def</doc>",
+        "<doc>1 < 2 but not in XML</doc>",
+        "<doc>A form feed (\x0C) is not legal in data</doc>",
+        "<doc>abc\x1Edef</doc>",
+        "<doc>\u{FFFE}</doc>",
+        // comments
+        "<doc>\n<!-- abc\n</doc>",
+        "<doc><!-- a comment -- another --></doc>",
+        "<doc><!-- ends with a dash ---></doc>",
+        "<doc><!- not a comment --></doc>",
+        "<doc><!-- a form feed (\x0C) is not allowed in a comment --></doc>",
+        // processing instructions
+        "<doc>\n<?a pi that is not closed\n</doc>",
+        "<doc><?pi a form feed (\x0C) is not allowed in a pi?></doc>",
+        "<doc><?xml version='1.0'?></doc>",
+        "<doc><?XML version='1.0'?></doc>",
+        "<?pi-before-declaration?><?xml version='1.0'?><doc/>",
+        // CDATA sections
+        "<doc><![CDATA[</doc>",
+        "<doc><![CDATA [ stuff]]></doc>",
+        "<doc><![cdata[data]]></doc>",
+        "<doc>\n<a><![CDATA[xyz]]]></a>\n<![CDATA[]]></a>\n</doc>",
+        // the XML declaration
+        "<?xml version='1.0' ?><?xml version='1.0'?><doc/>",
+        "<?xml encoding='UTF-8'?><doc/>",
+        "<?xml version='1.0' standalone='maybe'?><doc/>",
+        "<?xml version='1.0' encoding='UTF-8' encoding='UTF-8'?><doc/>",
+        "<?xml version=\"1.0\" standalone=\"yes\" encoding=\"UTF-8\"?><doc/>",
+        "<?xml version=\"1.0\"encoding=\"UTF-8\"?><doc/>",
+        "<?xml VERSION=\"1.0\"?><doc/>",
+        "\n<?xml version='1.0'?><doc/>",
+        // the document type declaration
+        "<!DOCTYPE doc>\n<doc></doc>\n<!DOCTYPE doc>",
+        "<doc/><!DOCTYPE doc>",
+        "<!DOCTYPE doc [\n<!ELEMENT doc (a,|b)>\n]>\n<doc/>",
+        "<!DOCTYPE doc [\n<!ENTITY e \"<foo>\">\n]>\n<doc>&e;</doc>",
+        "<!DOCTYPE doc [\n<!ENTITY e \"&e;\">\n]>\n<doc>&e;</doc>",
+        "<!DOCTYPE doc [\n<!ENTITY a \"&b;\">\n<!ENTITY b \"&a;\">\n]>\n<doc>&a;</doc>",
+        "<!DOCTYPE doc [\n<!ENTITY e \"</doc><doc>\">\n]>\n<doc>&e;</doc>",
+        "<!DOCTYPE doc [\n<!ATTLIST doc a CDATA \"<\">\n]>\n<doc/>",
+        "<!DOCTYPE doc [\n<!ELEMENT doc EMPTY>\n<!-- unclosed\n]>\n<doc/>",
+        "<!DOCTYPE doc [\n<!ELEMENT doc (#PCDATA)>\n",
+        "<!DOCTYPE doc [\n<!ENTITY foo PUBLIC \"some public id\">\n]>\n<doc/>",
+        "<!DOCTYPE doc [\n<!ELEMENT doc ANY>\n]>\n<doc/>\n<!ELEMENT x ANY>",
+        "<!doctype doc>\n<doc/>",
+        "<!DOCTYPE doc [<!ELEMENTdoc ANY>]><doc/>",
+    ];
+    for xml in cases.iter() {
+        match crate::parse(xml) {
+            // the error must say what is wrong with the document, not that the parser failed.
+            Err(e) => assert_ne!(ErrorKind::Internal, e.kind(), "{:?}: {}", xml, e),
+            Ok(_) => panic!("expected an error for {:?}", xml),
+        }
+        assert!(crate::parse_borrowed(xml).is_err(), "{:?}", xml);
+        assert!(crate::from_reader(xml.as_bytes()).is_err(), "{:?}", xml);
+        let events = crate::Reader::new(xml)
+            .and_then(|reader| reader.collect::<crate::error::Result<Vec<_>>>());
+        assert!(events.is_err(), "expected a reader error for {:?}", xml);
+    }
+}
+
+#[test]
+fn no_root_element() {
+    for xml in &["   ", "<!-- c -->", "<?pi x?>", "<?xml version='1.0'?>"] {
+        let kind = |result: crate::error::Result<()>| result.unwrap_err().kind();
+        assert_eq!(
+            ErrorKind::UnexpectedEof,
+            kind(crate::parse(xml).map(|_| ())),
+            "{:?}",
+            xml
+        );
+        assert_eq!(
+            ErrorKind::UnexpectedEof,
+            kind(crate::parse_borrowed(xml).map(|_| ())),
+            "{:?}",
+            xml
+        );
+        assert_eq!(
+            ErrorKind::UnexpectedEof,
+            kind(crate::from_reader(xml.as_bytes()).map(|_| ())),
+            "{:?}",
+            xml
+        );
+        let (_, errors) = crate::parse_recovering(xml, &ParseOptions::default());
+        assert_eq!(1, errors.len(), "{:?}: {:#?}", xml, errors);
+        assert_eq!(ErrorKind::UnexpectedEof, errors[0].kind, "{:?}", xml);
+    }
+}
+
+#[test]
+fn declaration_round_trip() {
+    let declared = |xml: &str| crate::parse(xml).unwrap().declaration().copied();
+    assert_eq!(None, declared("<a/>"));
+    let expected = Declaration {
+        version: Version::V10,
+        encoding: None,
+        standalone: None,
+    };
+    assert_eq!(Some(expected), declared("<?xml version='1.0'?><a/>"));
+    let expected = Declaration {
+        version: Version::V11,
+        encoding: Some(Encoding::Utf8),
+        standalone: Some(true),
+    };
+    let xml = r#"<?xml version="1.1" encoding="UTF-8" standalone="yes"?><a/>"#;
+    assert_eq!(Some(expected), declared(xml));
+    // whitespace is allowed around '=' and the quote styles can be mixed.
+    let xml = "<?xml version = \"1.1\"\n  encoding=\t'UTF-8' standalone ='yes' ?><a/>";
+    assert_eq!(Some(expected), declared(xml));
+
+    // the declaration is written only if there is one, and as it was parsed.
+    for xml in &[
+        "<a/>\n",
+        "<?xml version=\"1.0\"?>\n<a/>\n",
+        "<?xml version=\"1.0\" standalone=\"no\"?>\n<a/>\n",
+        "<?xml version=\"1.1\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<a/>\n",
+    ] {
+        assert_eq!(*xml, crate::parse(xml).unwrap().to_string());
+    }
+    let mut doc = crate::parse("<?xml version='1.0'?><a/>").unwrap();
+    doc.set_declaration(expected);
+    assert_eq!(Some(&expected), doc.declaration());
+    assert_eq!(Some(expected), doc.remove_declaration());
+    assert_eq!("<a/>\n", doc.to_string());
+}
+
+#[test]
+fn borrowed_documents() {
+    use crate::borrowed::{Misc, Node};
+    let is_borrowed = |s: &Cow<'_, str>| matches!(s, Cow::Borrowed(_));
+    let xml = "<?xml version='1.0'?><!--before--><?pi data?>
+<!DOCTYPE doc [<!ATTLIST doc fixed CDATA #FIXED 'yes' list NMTOKENS #IMPLIED>]>
+<doc a='plain' b='tab\there' list=' x  y ' c='&lt;'>plain text<![CDATA[ c ]]><!--c--><?t d?>\
+<e>  one space  </e><h>two  spaces</h><f>line\r\nend</f><g>&amp;</g></doc><!--after-->";
+    let doc = crate::parse_borrowed(xml).unwrap();
+    let root = doc.root();
+    assert_eq!("doc", root.fullname());
+    assert_eq!(Some("plain"), root.attribute("a"));
+    assert_eq!(Some("tab here"), root.attribute("b"));
+    assert_eq!(Some("x y"), root.attribute("list"));
+    assert_eq!(Some("<"), root.attribute("c"));
+    assert_eq!(Some("yes"), root.attribute("fixed"));
+    assert!(root.is_attribute_defaulted("fixed"));
+    let keys: Vec<&str> = root.attributes().map(|(key, _)| key).collect();
+    assert_eq!(vec!["a", "b", "list", "c", "fixed"], keys);
+
+    // text that parsing does not change is borrowed, everything else is owned.
+    match (root.node(0), root.node(1), root.node(2), root.node(3)) {
+        (
+            Some(Node::Text(text)),
+            Some(Node::CData(cdata)),
+            Some(Node::Comment(comment)),
+            Some(Node::Pi(pi)),
+        ) => {
+            assert!(is_borrowed(text) && is_borrowed(cdata) && is_borrowed(comment));
+            assert_eq!(("plain text", " c ", "c"), (&**text, &**cdata, &**comment));
+            assert_eq!(("t", "d"), (pi.target(), pi.data()));
+        }
+        nodes => panic!("unexpected nodes {:?}", nodes),
+    }
+    let text = |name: &str| match root.child(name).unwrap().node(0) {
+        Some(Node::Text(text)) => text.clone(),
+        node => panic!("unexpected node {:?}", node),
+    };
+    assert!(matches!(text("e"), Cow::Borrowed("one space")));
+    assert!(matches!(text("h"), Cow::Owned(s) if s == "two spaces"));
+    assert!(matches!(text("f"), Cow::Owned(s) if s == "line end"));
+    assert!(matches!(text("g"), Cow::Owned(s) if s == "&"));
+    assert!(matches!(
+        doc.prolog_misc().next(),
+        Some(Misc::Comment(Cow::Borrowed("before")))
+    ));
+    assert_eq!(1, doc.epilog_misc().len());
+
+    // like owned elements, equality does not depend on the order of the attributes.
+    let reordered = crate::parse_borrowed("<doc b='2' a='1'><e/></doc>").unwrap();
+    assert_eq!(
+        crate::parse_borrowed("<doc a='1' b='2'><e/></doc>").unwrap(),
+        reordered
+    );
+    assert_ne!(
+        crate::parse_borrowed("<doc a='1' b='3'><e/></doc>").unwrap(),
+        reordered
+    );
+
+    // the same parser builds both kinds of document.
+    assert_eq!(crate::parse(xml).unwrap(), doc.into_owned());
+    let opts = ParseOptions::new()
+        .whitespace(WhitespacePolicy::Preserve)
+        .coalesce_cdata(true)
+        .comments(false);
+    let borrowed = crate::parse_borrowed_with(xml, &opts).unwrap();
+    assert_eq!(
+        crate::parse_with(xml, &opts).unwrap(),
+        borrowed.into_owned()
+    );
+
+    for opts in &[
+        ParseOptions::new().spans(true),
+        ParseOptions::new().namespaces(true),
+    ] {
+        match crate::parse_borrowed_with(xml, opts) {
+            Err(e) => assert_eq!(ErrorKind::Unsupported, e.kind()),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    const XML1: &str = r#"
-<?xml version="1.0" encoding="utf-8" standalone="no"?>
+    const XML1: &str = r#"<?xml version="1.0" encoding="utf-8" standalone="no"?>
 <!DOCTYPE something PUBLIC "-//Some//Path//EN" "http://www.example.org/dtds/partwise.dtd">
 <cats>
   <cat id="b1">
@@ -578,6 +2031,7 @@ mod tests {
         let mut iter = Iter::new(XML1).unwrap();
         let mut doc = Document::new();
         parse_document(&mut iter, &mut doc).unwrap();
-        assert_eq!(Encoding::Utf8, doc.declaration().encoding.unwrap());
+        assert_eq!(Encoding::Utf8, doc.declaration().unwrap().encoding.unwrap());
+        assert_eq!(Some(false), doc.declaration().unwrap().standalone);
     }
 }
@@ -0,0 +1,198 @@
+/// Options for controlling how an XML document is parsed. The fields can be set directly, or
+/// with the builder-style methods of the same names.
+///
+/// ```
+/// use exile::{ParseOptions, WhitespacePolicy};
+/// let opts = ParseOptions::new()
+///     .whitespace(WhitespacePolicy::Preserve)
+///     .comments(false)
+///     .coalesce_cdata(true);
+/// let xml = "<doc> a <!--comment--><![CDATA[<b>]]> c </doc>";
+/// let doc = exile::parse_with(xml, &opts).unwrap();
+/// assert_eq!(1, doc.root().nodes_len());
+/// assert_eq!(" a <b> c ", doc.root().text().unwrap());
+/// ```
+#[derive(Debug, Clone, Eq, PartialOrd, PartialEq, Hash)]
+pub struct ParseOptions {
+    /// Limits that guard against documents that would consume excessive resources.
+    pub limits: Limits,
+    /// How whitespace in the text of elements is treated.
+    pub whitespace: WhitespacePolicy,
+    /// Resolve the namespace prefixes of elements and attributes as they are parsed, so that a
+    /// prefix that is not bound is an error. See [`crate::Element::namespace_uri`].
+    pub namespaces: bool,
+    /// Keep comments. When `false`, comments are checked for well-formedness and then discarded,
+    /// and the text on either side of a comment becomes a single text node.
+    pub comments: bool,
+    /// Keep processing instructions, other than the XML declaration. When `false`, they are
+    /// checked for well-formedness and then discarded, and the text on either side of one becomes
+    /// a single text node.
+    pub processing_instructions: bool,
+    /// Treat `CDATA` sections as text, adding their contents to any text that they are adjacent to
+    /// instead of keeping them as separate `CDATA` nodes.
+    pub coalesce_cdata: bool,
+    /// Record where each element, attribute and node was parsed from. See
+    /// [`crate::Element::span`].
+    pub spans: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            limits: Limits::default(),
+            whitespace: WhitespacePolicy::default(),
+            namespaces: false,
+            comments: true,
+            processing_instructions: true,
+            coalesce_cdata: false,
+            spans: false,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Create the default options, to be modified with the builder-style methods.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the resource [`ParseOptions::limits`].
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Set the [`ParseOptions::whitespace`] policy.
+    pub fn whitespace(mut self, whitespace: WhitespacePolicy) -> Self {
+        self.whitespace = whitespace;
+        self
+    }
+
+    /// Set whether [`ParseOptions::namespaces`] are resolved.
+    pub fn namespaces(mut self, namespaces: bool) -> Self {
+        self.namespaces = namespaces;
+        self
+    }
+
+    /// Set whether [`ParseOptions::comments`] are kept.
+    pub fn comments(mut self, comments: bool) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    /// Set whether [`ParseOptions::processing_instructions`] are kept.
+    pub fn processing_instructions(mut self, processing_instructions: bool) -> Self {
+        self.processing_instructions = processing_instructions;
+        self
+    }
+
+    /// Set whether `CDATA` sections are coalesced into text, see
+    /// [`ParseOptions::coalesce_cdata`].
+    pub fn coalesce_cdata(mut self, coalesce_cdata: bool) -> Self {
+        self.coalesce_cdata = coalesce_cdata;
+        self
+    }
+
+    /// Set whether [`ParseOptions::spans`] are recorded.
+    pub fn spans(mut self, spans: bool) -> Self {
+        self.spans = spans;
+        self
+    }
+}
+
+/// How whitespace in the text of elements is treated. Whitespace outside of the root element is
+/// always discarded.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialOrd, PartialEq, Hash)]
+pub enum WhitespacePolicy {
+    /// Text is kept exactly as it is written, including text that is nothing but whitespace, such
+    /// as the indentation between elements.
+    Preserve,
+    /// Leading and trailing whitespace is removed and each run of whitespace within the text is
+    /// replaced with a single space. Text that is nothing but whitespace is discarded.
+    #[default]
+    Collapse,
+    /// Leading and trailing whitespace is removed, but whitespace within the text is kept. Text
+    /// that is nothing but whitespace is discarded.
+    TrimOnly,
+    /// Text is treated as `Preserve` inside of an element with `xml:space="preserve"` and as
+    /// `Collapse` otherwise. An `xml:space="default"` attribute switches back to `Collapse` for an
+    /// element that is inside of a preserved one.
+    HonorXmlSpace,
+}
+
+/// Limits on the resources that parsing a document may consume, so that untrusted documents can be
+/// parsed safely. A document that exceeds a limit fails to parse with an error of kind
+/// [`crate::error::ErrorKind::LimitExceeded`].
+#[derive(Debug, Clone, Eq, PartialOrd, PartialEq, Hash)]
+pub struct Limits {
+    /// The maximum number of elements that may be nested inside of one another, including the root
+    /// element. This bounds the memory that is used to track the elements that are open. There is
+    /// no limit by default, because elements are parsed without recursion.
+    pub max_depth: usize,
+    /// The maximum number of characters that may be read from the document.
+    pub max_input_size: usize,
+    /// The maximum number of attributes that may be written in a single start tag.
+    pub max_attributes: usize,
+    /// The maximum length, in bytes of UTF-8, of the name of an element, attribute, processing
+    /// instruction or entity.
+    pub max_name_length: usize,
+    /// The maximum length, in bytes of UTF-8, of a text node, CDATA section, comment or attribute
+    /// value.
+    pub max_text_length: usize,
+    /// The maximum number of entity references that may be nested inside of one another's
+    /// replacement text.
+    pub max_entity_depth: usize,
+    /// The maximum number of characters that entity expansion may add to the document, in total.
+    /// This is what stops a small document from expanding into gigabytes of text, as in the
+    /// 'billion laughs' attack.
+    pub max_entity_expansion: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_depth: usize::MAX,
+            max_input_size: usize::MAX,
+            max_attributes: 10_000,
+            max_name_length: 50_000,
+            max_text_length: 10_000_000,
+            max_entity_depth: 16,
+            max_entity_expansion: 1_000_000,
+        }
+    }
+}
+
+/// Identifies one of the [`Limits`], e.g. in an error of kind
+/// [`crate::error::ErrorKind::LimitExceeded`].
+#[derive(Debug, Clone, Copy, Eq, PartialOrd, Ord, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Limit {
+    /// [`Limits::max_depth`]
+    Depth,
+    /// [`Limits::max_input_size`]
+    InputSize,
+    /// [`Limits::max_attributes`]
+    Attributes,
+    /// [`Limits::max_name_length`]
+    NameLength,
+    /// [`Limits::max_text_length`]
+    TextLength,
+    /// [`Limits::max_entity_depth`]
+    EntityDepth,
+    /// [`Limits::max_entity_expansion`]
+    EntityExpansion,
+}
+
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Limit::Depth => "element depth",
+            Limit::InputSize => "input size",
+            Limit::Attributes => "attribute count",
+            Limit::NameLength => "name length",
+            Limit::TextLength => "text length",
+            Limit::EntityDepth => "entity depth",
+            Limit::EntityExpansion => "entity expansion",
+        })
+    }
+}
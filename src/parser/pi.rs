@@ -1,9 +1,14 @@
+use std::borrow::Cow;
+
+use crate::error::ErrorKind;
 use crate::parser::error::Result;
-use crate::parser::Iter;
+use crate::parser::scratch::Scratch;
+use crate::parser::{DocStatus, Iter};
 use crate::Pi;
 
 /// The iter should be pointing to the opening `<` of a processing instruction.
-pub(crate) fn parse_pi_logic(iter: &mut Iter<'_>) -> Result<(String, String)> {
+pub(crate) fn parse_pi_logic<'a>(iter: &mut Iter<'a>) -> Result<(Cow<'a, str>, Cow<'a, str>)> {
+    let depth = iter.it.depth();
     expect!(iter, '<')?;
     iter.advance_or_die()?;
     expect!(iter, '?')?;
@@ -14,30 +19,38 @@ pub(crate) fn parse_pi_logic(iter: &mut Iter<'_>) -> Result<(String, String)> {
         iter.advance_or_die()?;
         expect!(iter, '>')?;
         iter.advance();
-        return Ok(("".into(), "".into()));
+        return Ok((Cow::Borrowed(""), Cow::Borrowed("")));
     }
 
     let target = parse_pi_target(iter)?;
-    let mut data = String::new();
+    check_pi_target(iter, &target)?;
+    let mut data = Scratch::new(iter);
     loop {
         if iter.is('?') && iter.peek_is('>') {
             iter.advance_or_die()?;
+            iter.expect_depth(depth)?;
             iter.advance();
             break;
         }
-        data.push(iter.st.c);
+        iter.expect_literal_char()?;
+        data.push_current(iter);
         iter.advance_or_die()?;
     }
-    Ok((target, data))
+    Ok((target, data.into_cow()))
 }
 
 /// Must be a valid name terminated by whitespace.
-fn parse_pi_target(iter: &mut Iter<'_>) -> Result<String> {
+fn parse_pi_target<'a>(iter: &mut Iter<'a>) -> Result<Cow<'a, str>> {
     if !iter.is_name_start_char() {
-        return parse_err!(iter, "expected name start char, found '{}'", iter.st.c);
+        return parse_err!(
+            iter,
+            ErrorKind::InvalidName,
+            "expected name start char, found '{}'",
+            iter.st.c
+        );
     }
-    let mut name = String::new();
-    name.push(iter.st.c);
+    let mut name = Scratch::new(iter);
+    name.push_current(iter);
     iter.advance_or_die()?;
     loop {
         if iter.is_whitespace() {
@@ -47,19 +60,46 @@ fn parse_pi_target(iter: &mut Iter<'_>) -> Result<String> {
             // e.g. <?target??
             break;
         } else if !iter.is_name_char() {
-            return parse_err!(iter, "expected name char, found '{}'", iter.st.c);
+            return parse_err!(
+                iter,
+                ErrorKind::InvalidName,
+                "expected name char, found '{}'",
+                iter.st.c
+            );
         } else {
-            name.push(iter.st.c);
+            name.push_current(iter);
         }
         iter.advance_or_die()?;
     }
-    Ok(name)
+    Ok(name.into_cow())
+}
+
+/// The target `xml` is only allowed for the XML declaration at the start of the document, and the
+/// other targets that match `xml` case-insensitively are reserved.
+fn check_pi_target(iter: &Iter<'_>, target: &str) -> Result<()> {
+    if target == "xml" {
+        if iter.st.doc_status != DocStatus::Declaration {
+            return parse_err!(
+                iter,
+                ErrorKind::InvalidDeclaration,
+                "the XML declaration is only allowed at the start of the document"
+            );
+        }
+    } else if target.eq_ignore_ascii_case("xml") {
+        return parse_err!(
+            iter,
+            ErrorKind::InvalidName,
+            "the processing instruction target '{}' is reserved",
+            target
+        );
+    }
+    Ok(())
 }
 
 /// The iter should be pointing to the opening `<` of a processing instruction.
 pub(crate) fn parse_pi(iter: &mut Iter<'_>) -> Result<Pi> {
     let (target, data) = parse_pi_logic(iter)?;
-    Ok(Pi::new_unchecked(target, data))
+    Ok(Pi::new_unchecked(target.into_owned(), data.into_owned()))
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
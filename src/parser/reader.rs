@@ -0,0 +1,405 @@
+/*!
+The `reader` module provides a streaming, pull-style alternative to the DOM parser. Instead of
+building a `Document`, the [`Reader`] yields one [`Event`] at a time so that a document can be
+processed without holding all of it in memory.
+!*/
+
+use crate::error::ErrorKind;
+use crate::parser::bang::parse_bang;
+use crate::parser::chars::is_name_start_char;
+use crate::parser::element::{parse_end_tag_name, parse_start_tag, parse_text, LtParse};
+use crate::parser::error::Result;
+use crate::parser::pi::parse_pi_logic;
+use crate::parser::source::Source;
+use crate::parser::tree::Leaf;
+use crate::parser::{parse_declaration, prolog_started, DocStatus, Iter};
+use crate::{Declaration, DocTypeDecl, Element, ParseOptions, Pi};
+use std::io::Read;
+
+/// A piece of an XML document as it is encountered by the [`Reader`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Event {
+    /// The XML declaration, e.g. `<?xml version="1.0"?>`.
+    Declaration(Declaration),
+    /// The `<!DOCTYPE ...>` section.
+    DocType(DocTypeDecl),
+    /// An opening tag, e.g. `<foo a="b">`. The `Element` holds the name and attributes of the tag,
+    /// but never any child nodes.
+    StartElement(Element),
+    /// A closing tag, e.g. `</foo>`, given by its fullname. A self-closing tag such as `<foo/>`
+    /// produces a `StartElement` immediately followed by an `EndElement`.
+    EndElement(String),
+    /// Text data in an element, with escapes resolved and whitespace normalized.
+    Text(String),
+    /// `<![CDATA[text]]>`, which is reported as `Text` if [`ParseOptions::coalesce_cdata`] is set.
+    CData(String),
+    /// Comment, e.g. `<!--some comment-->`
+    Comment(String),
+    /// Processing Instruction, e.g. `<?target data?>`
+    Pi(Pi),
+}
+
+/// A streaming XML parser. The `Reader` pulls one [`Event`] at a time from the XML document and
+/// only holds on to the names of the elements that are currently open.
+///
+/// # Example
+///
+/// ```
+/// use exile::{Event, Reader};
+/// let xml = r#"<root><thing name="foo"/><thing>bar</thing></root>"#;
+/// let mut names = Vec::new();
+/// for event in Reader::new(xml).unwrap() {
+///     if let Event::StartElement(element) = event.unwrap() {
+///         names.push(element.name().to_owned());
+///     }
+/// }
+/// assert_eq!(vec!["root", "thing", "thing"], names);
+/// ```
+pub struct Reader<'a> {
+    iter: Iter<'a>,
+    /// The fullnames of the elements that have been opened but not yet closed.
+    open: Vec<String>,
+    /// Set when a self-closing tag has been returned and its `EndElement` is still owed.
+    pending_end: bool,
+    /// Set when the `DocType` has been returned, a document can only have one.
+    has_doctype: bool,
+    /// Set when the end of the document, or an error, has been reached.
+    done: bool,
+}
+
+impl<'a> Reader<'a> {
+    /// Create a `Reader` over an XML document held in string contents.
+    pub fn new(xml: &'a str) -> crate::error::Result<Self> {
+        Self::new_with(xml, &ParseOptions::default())
+    }
+
+    /// Create a `Reader` over an XML document held in string contents, as controlled by `opts`.
+    pub fn new_with(xml: &'a str, opts: &ParseOptions) -> crate::error::Result<Self> {
+        Self::from_source(Source::from_str(xml), opts)
+    }
+
+    /// Create a `Reader` that decodes UTF-8 from `reader` incrementally, as events are requested.
+    /// The `reader` is buffered internally so it does not need to be wrapped in a `BufReader`.
+    pub fn from_reader<R: Read + 'a>(reader: R) -> crate::error::Result<Self> {
+        Self::from_reader_with(reader, &ParseOptions::default())
+    }
+
+    /// Create a `Reader` that decodes `reader` incrementally, as controlled by `opts`.
+    pub fn from_reader_with<R: Read + 'a>(
+        reader: R,
+        opts: &ParseOptions,
+    ) -> crate::error::Result<Self> {
+        Self::from_source(Source::from_read(Box::new(reader)), opts)
+    }
+
+    fn from_source(source: Source<'a>, opts: &ParseOptions) -> crate::error::Result<Self> {
+        Ok(Self {
+            iter: Iter::from_source(source, opts.clone()).map_err(crate::error::Error::Parse)?,
+            open: Vec::new(),
+            pending_end: false,
+            has_doctype: false,
+            done: false,
+        })
+    }
+
+    /// The number of elements that are currently open. This is `1` after the root element's
+    /// `StartElement` has been returned and `0` after its `EndElement` has been returned.
+    pub fn depth(&self) -> usize {
+        self.open.len()
+    }
+
+    /// Returns the next event, or `None` when the end of the document has been reached. Once an
+    /// error has been returned, all subsequent calls return `None`.
+    pub fn next_event(&mut self) -> crate::error::Result<Option<Event>> {
+        if self.done {
+            return Ok(None);
+        }
+        let result = if self.pending_end {
+            self.pending_end = false;
+            Ok(Some(self.close()))
+        } else if self.open.is_empty() {
+            self.next_outside_root()
+        } else {
+            self.next_inside_root()
+        };
+        match result {
+            Ok(None) | Err(_) => self.done = true,
+            Ok(Some(_)) => {}
+        }
+        self.iter.finish(result)
+    }
+
+    /// Handles the prolog and epilog, i.e. everything before and after the root element.
+    fn next_outside_root(&mut self) -> Result<Option<Event>> {
+        loop {
+            let iter = &mut self.iter;
+            if iter.is_whitespace() {
+                // the declaration must be the very first thing in the document.
+                prolog_started(iter);
+                iter.skip_whitespace()?;
+            }
+            if iter.end() {
+                if iter.st.doc_status != DocStatus::Epilog {
+                    return parse_err!(
+                        iter,
+                        ErrorKind::UnexpectedEof,
+                        "the document does not have a root element"
+                    );
+                }
+                return Ok(None);
+            }
+            expect!(iter, '<')?;
+            match iter.peek_or_die()? {
+                '?' => {
+                    // the target is checked by parse_pi_logic, `xml` is only allowed at the start.
+                    let (target, data) = parse_pi_logic(iter)?;
+                    if target == "xml" {
+                        let declaration = parse_declaration(iter, &target, &data)?;
+                        iter.st.doc_status = DocStatus::Prolog;
+                        return Ok(Some(Event::Declaration(declaration)));
+                    }
+                    prolog_started(iter);
+                    if !iter.st.opts.processing_instructions {
+                        continue;
+                    }
+                    return Ok(Some(Event::Pi(Pi::new_unchecked(target, data))));
+                }
+                '!' => {
+                    prolog_started(iter);
+                    match parse_bang(iter)? {
+                        LtParse::Some(Leaf::Comment(comment)) => {
+                            return Ok(Some(Event::Comment(comment.into_owned())));
+                        }
+                        LtParse::DocType(_) if self.has_doctype => {
+                            return parse_err!(
+                                iter,
+                                ErrorKind::InvalidDoctype,
+                                "a document can only have one DOCTYPE"
+                            )
+                        }
+                        LtParse::DocType(doctype) if iter.st.doc_status != DocStatus::Epilog => {
+                            self.has_doctype = true;
+                            return Ok(Some(Event::DocType(doctype)));
+                        }
+                        LtParse::Skip => continue,
+                        other => {
+                            return parse_err!(
+                                iter,
+                                ErrorKind::Syntax,
+                                "unexpected {:?} outside of root",
+                                other
+                            )
+                        }
+                    }
+                }
+                _ => {
+                    if iter.st.doc_status == DocStatus::Epilog {
+                        return parse_err!(
+                            iter,
+                            ErrorKind::Syntax,
+                            "a document can only have one root element"
+                        );
+                    }
+                    iter.st.doc_status = DocStatus::Root;
+                    return Ok(Some(self.open_element()?));
+                }
+            }
+        }
+    }
+
+    /// Handles the content of the root element.
+    fn next_inside_root(&mut self) -> Result<Option<Event>> {
+        loop {
+            let iter = &mut self.iter;
+            iter.skip_content_whitespace()?;
+            if iter.end() {
+                return parse_err!(
+                    iter,
+                    ErrorKind::UnexpectedEof,
+                    "the document ended before element '{}' was closed",
+                    self.open.last().map(|s| s.as_str()).unwrap_or_default()
+                );
+            }
+            if !iter.is('<') {
+                let text = parse_text(iter)?;
+                if text.is_empty() {
+                    continue;
+                }
+                return Ok(Some(Event::Text(text.into_owned())));
+            }
+            let next = iter.peek_or_die()?;
+            if is_name_start_char(next) {
+                return Ok(Some(self.open_element()?));
+            }
+            match next {
+                '/' => {
+                    let expected = self.open.last().map(|s| s.as_str()).unwrap_or_default();
+                    parse_end_tag_name(iter, expected)?;
+                    iter.advance();
+                    return Ok(Some(self.close()));
+                }
+                '?' => {
+                    let (target, data) = parse_pi_logic(iter)?;
+                    if !iter.st.opts.processing_instructions {
+                        continue;
+                    }
+                    return Ok(Some(Event::Pi(Pi::new_unchecked(target, data))));
+                }
+                '!' => match parse_bang(iter)? {
+                    LtParse::Some(Leaf::Comment(comment)) => {
+                        return Ok(Some(Event::Comment(comment.into_owned())))
+                    }
+                    LtParse::Some(Leaf::CData(cdata)) => {
+                        return Ok(Some(Event::CData(cdata.into_owned())))
+                    }
+                    LtParse::Some(Leaf::Text(text)) => {
+                        return Ok(Some(Event::Text(text.into_owned())))
+                    }
+                    LtParse::Skip => continue,
+                    other => {
+                        return parse_err!(
+                            iter,
+                            ErrorKind::Syntax,
+                            "unexpected {:?} inside of an element",
+                            other
+                        )
+                    }
+                },
+                _ => {
+                    // this error occurred on the peeked char, so to report the correct position of
+                    // the error, we will first advance the iter (if possible).
+                    iter.advance();
+                    return parse_err!(iter, ErrorKind::Syntax, "unexpected char following '<'");
+                }
+            }
+        }
+    }
+
+    /// Parses a start tag and pushes it onto the stack of open elements.
+    fn open_element(&mut self) -> Result<Event> {
+        let (element, is_empty) = parse_start_tag::<Element>(&mut self.iter)?;
+        self.iter.enter_element(&element);
+        self.open.push(element.fullname().to_owned());
+        self.pending_end = is_empty;
+        Ok(Event::StartElement(element))
+    }
+
+    /// Pops the innermost open element and returns its `EndElement` event.
+    fn close(&mut self) -> Event {
+        let name = self.open.pop().unwrap_or_default();
+        self.iter.exit_element();
+        if self.open.is_empty() {
+            self.iter.st.doc_status = DocStatus::Epilog;
+        }
+        Event::EndElement(name)
+    }
+}
+
+impl Iterator for Reader<'_> {
+    type Item = crate::error::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+fn events(xml: &str) -> crate::error::Result<Vec<Event>> {
+    Reader::new(xml)?.collect()
+}
+
+#[test]
+fn reader_simple() {
+    let xml = r#"<?xml version="1.0"?>
+<!-- hi -->
+<r a="b">
+  <x/>
+  text &amp; more<![CDATA[<&>]]><?pi data?>
+</r>
+<?after?>"#;
+    let mut x = Element::from_name("r");
+    x.add_attribute("a", "b");
+    let expected = vec![
+        Event::Declaration(Declaration {
+            version: crate::Version::V10,
+            encoding: None,
+            standalone: None,
+        }),
+        Event::Comment(" hi ".into()),
+        Event::StartElement(x),
+        Event::StartElement(Element::from_name("x")),
+        Event::EndElement("x".into()),
+        Event::Text("text & more".into()),
+        Event::CData("<&>".into()),
+        Event::Pi(Pi::new("pi", "data").unwrap()),
+        Event::EndElement("r".into()),
+        Event::Pi(Pi::new("after", "").unwrap()),
+    ];
+    assert_eq!(expected, events(xml).unwrap());
+}
+
+#[test]
+fn reader_from_reader() {
+    let xml = "<a><b>text</b><c/></a>";
+    let from_reader: Vec<Event> = Reader::from_reader(xml.as_bytes())
+        .unwrap()
+        .collect::<crate::error::Result<_>>()
+        .unwrap();
+    assert_eq!(events(xml).unwrap(), from_reader);
+}
+
+#[test]
+fn reader_with_options() {
+    let xml = "<!--c--><?pi?><a>x<!--c--><?pi?><![CDATA[y]]></a><?pi?>";
+    let opts = ParseOptions::new()
+        .comments(false)
+        .processing_instructions(false)
+        .coalesce_cdata(true);
+    let events: Vec<Event> = Reader::new_with(xml, &opts)
+        .unwrap()
+        .collect::<crate::error::Result<_>>()
+        .unwrap();
+    let expected = vec![
+        Event::StartElement(Element::from_name("a")),
+        Event::Text("x".into()),
+        Event::Text("y".into()),
+        Event::EndElement("a".into()),
+    ];
+    assert_eq!(expected, events);
+}
+
+#[test]
+fn reader_depth() {
+    let mut reader = Reader::new("<a><b/></a>").unwrap();
+    let mut depths = Vec::new();
+    while reader.next_event().unwrap().is_some() {
+        depths.push(reader.depth());
+    }
+    assert_eq!(vec![1, 2, 1, 0], depths);
+}
+
+#[test]
+fn reader_mismatched_end_tag() {
+    let mut reader = Reader::new("<a><b></a></b>").unwrap();
+    assert!(reader.next_event().is_ok());
+    assert!(reader.next_event().is_ok());
+    assert!(reader.next_event().is_err());
+    assert!(reader.next_event().unwrap().is_none());
+}
+
+#[test]
+fn reader_unclosed() {
+    assert!(events("<a><b></b>").is_err());
+}
+
+#[test]
+fn reader_two_roots() {
+    assert!(events("<a/><b/>").is_err());
+}
+
+#[test]
+fn reader_late_declaration() {
+    assert!(events(r#"<!-- x --><?xml version="1.0"?><a/>"#).is_err());
+}
@@ -0,0 +1,150 @@
+/*!
+The `scratch` module provides the buffer that the parser collects names and text in. As long as the
+characters that are pushed are the same, contiguous characters that are in the string being parsed,
+the buffer only records where they are, so that the result can borrow from the document. Once a
+character differs from the document, for example because an escape was replaced or a line end was
+normalized, the buffer switches to an owned `String`.
+!*/
+
+use std::borrow::Cow;
+
+use crate::parser::Iter;
+
+/// Text that is borrowed from the document for as long as possible.
+#[derive(Debug, Clone)]
+pub(crate) struct Scratch<'a> {
+    /// The string being parsed, or `None` if it is being decoded from bytes.
+    source: Option<&'a str>,
+    /// The bytes of `source` that the text is made of, while it is borrowed.
+    start: usize,
+    end: usize,
+    /// The text, once it can no longer be borrowed.
+    owned: Option<String>,
+}
+
+impl<'a> Scratch<'a> {
+    /// Creates an empty buffer for the document that `iter` is parsing.
+    pub(crate) fn new(iter: &Iter<'a>) -> Self {
+        Self {
+            source: iter.it.as_str(),
+            start: 0,
+            end: 0,
+            owned: None,
+        }
+    }
+
+    /// Pushes the character that `iter` is pointing at.
+    pub(crate) fn push_current(&mut self, iter: &Iter<'a>) {
+        self.push_at(iter.st.c, iter.offset());
+    }
+
+    /// Pushes `c` in place of the character that `iter` is pointing at. The text can still be
+    /// borrowed if they are the same character.
+    pub(crate) fn push_instead(&mut self, c: char, iter: &Iter<'a>) {
+        self.push_at(c, iter.offset());
+    }
+
+    /// Pushes a character that is not in the document, e.g. the replacement of an escape.
+    pub(crate) fn push(&mut self, c: char) {
+        self.push_at(c, None);
+    }
+
+    /// Pushes `c`, which is at `offset` in the document if it is `Some`.
+    pub(crate) fn push_at(&mut self, c: char, offset: Option<usize>) {
+        if let Some(owned) = &mut self.owned {
+            owned.push(c);
+            return;
+        }
+        if let (Some(source), Some(offset)) = (self.source, offset) {
+            let is_same = source.get(offset..).is_some_and(|rest| rest.starts_with(c));
+            if is_same && self.start == self.end {
+                self.start = offset;
+                self.end = offset + c.len_utf8();
+                return;
+            } else if is_same && offset == self.end {
+                self.end += c.len_utf8();
+                return;
+            }
+        }
+        let mut owned = self.as_str().to_owned();
+        owned.push(c);
+        self.owned = Some(owned);
+    }
+
+    /// The text that has been pushed so far.
+    pub(crate) fn as_str(&self) -> &str {
+        match (&self.owned, self.source) {
+            (Some(owned), _) => owned.as_str(),
+            (None, Some(source)) => &source[self.start..self.end],
+            (None, None) => "",
+        }
+    }
+
+    /// Returns the text, borrowed from the document if possible.
+    pub(crate) fn into_cow(self) -> Cow<'a, str> {
+        match (self.owned, self.source) {
+            (Some(owned), _) => Cow::Owned(owned),
+            (None, Some(source)) => Cow::Borrowed(&source[self.start..self.end]),
+            (None, None) => Cow::Borrowed(""),
+        }
+    }
+}
+
+/// Applies `trim` to `s`, without allocating if `s` is borrowed or nothing is trimmed.
+pub(crate) fn trim_cow<'a, F>(s: Cow<'a, str>, trim: F) -> Cow<'a, str>
+where
+    F: Fn(&str) -> &str,
+{
+    match s {
+        Cow::Borrowed(s) => Cow::Borrowed(trim(s)),
+        Cow::Owned(s) => {
+            let trimmed = trim(&s);
+            if trimmed.len() == s.len() {
+                Cow::Owned(s)
+            } else {
+                Cow::Owned(trimmed.to_owned())
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// tests
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn scratch_borrows_contiguous_chars() {
+    let mut iter = Iter::new("abc").unwrap();
+    let mut scratch = Scratch::new(&iter);
+    scratch.push_current(&iter);
+    iter.advance();
+    scratch.push_current(&iter);
+    assert!(matches!(scratch.clone().into_cow(), Cow::Borrowed("ab")));
+    // the same character in place of the document's character is still borrowed.
+    iter.advance();
+    scratch.push_instead('c', &iter);
+    assert!(matches!(scratch.into_cow(), Cow::Borrowed("abc")));
+}
+
+#[test]
+fn scratch_owns_changed_chars() {
+    let mut iter = Iter::new("a\tb").unwrap();
+    let mut scratch = Scratch::new(&iter);
+    scratch.push_current(&iter);
+    iter.advance();
+    scratch.push_instead(' ', &iter);
+    iter.advance();
+    scratch.push_current(&iter);
+    assert!(matches!(scratch.into_cow(), Cow::Owned(s) if s == "a b"));
+}
+
+#[test]
+fn scratch_owns_normalized_line_ends() {
+    let mut iter = Iter::new("a\r\nb").unwrap();
+    let mut scratch = Scratch::new(&iter);
+    while !iter.end() {
+        scratch.push_current(&iter);
+        iter.advance();
+    }
+    assert!(matches!(scratch.into_cow(), Cow::Owned(s) if s == "a\nb"));
+}
@@ -0,0 +1,449 @@
+/*!
+The `source` module provides the characters that the parser consumes. Characters are either taken
+from a string that is already in memory or decoded incrementally from a `std::io::Read` object.
+The replacement text of entities is pushed onto the `Source` so that it is read before the rest of
+the document.
+!*/
+
+use std::io::{ErrorKind, Read};
+
+use crate::Encoding;
+
+/// The number of bytes that are read from a `Read` object at a time.
+const BUFFER_SIZE: usize = 8 * 1024;
+
+/// A peekable stream of characters that the `Iter` pulls from.
+pub(crate) struct Source<'a> {
+    inner: Inner<'a>,
+    /// The replacement text of the entities that are being expanded, innermost last.
+    entities: Vec<EntityText>,
+    /// The next character and its entity depth, if it has been peeked.
+    peeked: Option<Option<(char, usize)>>,
+    /// The entity depth of the character most recently returned by `next`.
+    depth: usize,
+}
+
+/// The replacement text of an entity that is being read.
+struct EntityText {
+    name: String,
+    chars: Vec<char>,
+    pos: usize,
+}
+
+enum Inner<'a> {
+    /// The whole string, and the characters that have not been read yet.
+    Str(&'a str, std::str::Chars<'a>),
+    Read(Decoder<'a>),
+}
+
+impl<'a> Source<'a> {
+    /// Create a `Source` over a string held in memory.
+    pub(crate) fn from_str(s: &'a str) -> Self {
+        Self {
+            inner: Inner::Str(s, s.chars()),
+            entities: Vec::new(),
+            peeked: None,
+            depth: 0,
+        }
+    }
+
+    /// Create a `Source` that decodes bytes from `reader` as they are needed. The encoding is
+    /// detected from the first bytes that are read.
+    pub(crate) fn from_read(reader: Box<dyn Read + 'a>) -> Self {
+        Self {
+            inner: Inner::Read(Decoder::new(reader)),
+            entities: Vec::new(),
+            peeked: None,
+            depth: 0,
+        }
+    }
+
+    /// Returns the next character, or `None` if the source is exhausted or has failed.
+    pub(crate) fn next(&mut self) -> Option<char> {
+        let next = match self.peeked.take() {
+            Some(peeked) => peeked,
+            None => self.pull(),
+        };
+        let (c, depth) = next?;
+        self.depth = depth;
+        Some(c)
+    }
+
+    /// Returns the next character without consuming it.
+    pub(crate) fn peek(&mut self) -> Option<&char> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.pull());
+        }
+        self.peeked
+            .as_ref()
+            .and_then(|peeked| peeked.as_ref().map(|(c, _)| c))
+    }
+
+    /// Pushes the replacement text of the entity `name` so that it is read before any characters
+    /// that follow the current one. Must not be called while a character is peeked.
+    pub(crate) fn push_entity(&mut self, name: &str, text: &str) {
+        debug_assert!(self.peeked.is_none());
+        self.entities.push(EntityText {
+            name: name.to_owned(),
+            chars: text.chars().collect(),
+            pos: 0,
+        });
+    }
+
+    /// The number of entities that the most recently returned character is nested in. Characters
+    /// of the document itself have a depth of zero.
+    pub(crate) fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns `true` if the most recently returned character is part of the replacement text of
+    /// the entity `name`, directly or through other entities.
+    pub(crate) fn is_expanding(&self, name: &str) -> bool {
+        let depth = self.depth.min(self.entities.len());
+        self.entities[..depth].iter().any(|e| e.name == name)
+    }
+
+    /// The string that is being read, or `None` if the source is decoding bytes.
+    pub(crate) fn as_str(&self) -> Option<&'a str> {
+        match &self.inner {
+            Inner::Str(s, _) => Some(s),
+            Inner::Read(_) => None,
+        }
+    }
+
+    /// The encoding that was detected when reading bytes, or `None` if the source is a string.
+    pub(crate) fn encoding(&self) -> Option<Encoding> {
+        match &self.inner {
+            Inner::Str(..) => None,
+            Inner::Read(decoder) => Some(decoder.encoding),
+        }
+    }
+
+    /// If the source stopped producing characters because of an I/O or decoding error, returns that
+    /// error. Subsequent calls return `None`.
+    pub(crate) fn take_error(&mut self) -> Option<std::io::Error> {
+        match &mut self.inner {
+            Inner::Str(..) => None,
+            Inner::Read(decoder) => decoder.error.take(),
+        }
+    }
+
+    fn pull(&mut self) -> Option<(char, usize)> {
+        while let Some(entity) = self.entities.last_mut() {
+            if let Some(&c) = entity.chars.get(entity.pos) {
+                entity.pos += 1;
+                return Some((c, self.entities.len()));
+            }
+            self.entities.pop();
+        }
+        let c = match &mut self.inner {
+            Inner::Str(_, chars) => chars.next(),
+            Inner::Read(decoder) => decoder.next_char(),
+        };
+        c.map(|c| (c, 0))
+    }
+}
+
+/// Decodes characters from a `Read` object using a fixed-size buffer. The encoding is detected from
+/// the first bytes of the document as described in Appendix F of the XML specification.
+struct Decoder<'a> {
+    reader: Box<dyn Read + 'a>,
+    buf: Vec<u8>,
+    pos: usize,
+    len: usize,
+    encoding: Encoding,
+    error: Option<std::io::Error>,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(reader: Box<dyn Read + 'a>) -> Self {
+        let mut decoder = Self {
+            reader,
+            buf: vec![0; BUFFER_SIZE],
+            pos: 0,
+            len: 0,
+            encoding: Encoding::Utf8,
+            error: None,
+        };
+        decoder.detect_encoding();
+        decoder
+    }
+
+    fn available(&self) -> &[u8] {
+        &self.buf[self.pos..self.len]
+    }
+
+    /// Looks at the byte order mark, or the first characters of the document if there is no byte
+    /// order mark, to choose an encoding. If the document appears to be in an ASCII-compatible
+    /// encoding, then the `encoding` of the XML declaration is used to choose among them.
+    fn detect_encoding(&mut self) {
+        while self.available().len() < 4 && self.fill() {}
+        let (encoding, bom_len) = match self.available() {
+            [0xEF, 0xBB, 0xBF, ..] => (Encoding::Utf8, 3),
+            [0xFF, 0xFE, ..] => (Encoding::Utf16Le, 2),
+            [0xFE, 0xFF, ..] => (Encoding::Utf16Be, 2),
+            [b'<', 0x00, b'?', 0x00, ..] => (Encoding::Utf16Le, 0),
+            [0x00, b'<', 0x00, b'?', ..] => (Encoding::Utf16Be, 0),
+            _ => (self.declared_encoding().unwrap_or(Encoding::Utf8), 0),
+        };
+        self.encoding = encoding;
+        self.pos += bom_len;
+    }
+
+    /// Finds the `encoding` named in the XML declaration, if any. The declaration is not consumed,
+    /// and it is left to the parser to check it for correctness.
+    fn declared_encoding(&mut self) -> Option<Encoding> {
+        while !self.available().contains(&b'>') && self.len < self.buf.len() && self.fill() {}
+        let bytes = self.available();
+        let end = bytes.iter().position(|&b| b == b'>')?;
+        let declaration = std::str::from_utf8(bytes.get(..end)?.strip_prefix(b"<?xml")?).ok()?;
+        let after = declaration.split_once("encoding")?.1.trim_start();
+        let after = after.strip_prefix('=')?.trim_start();
+        let quote = after.chars().next().filter(|&q| q == '"' || q == '\'')?;
+        let label = after[1..].split(quote).next()?;
+        Encoding::from_label(label).filter(|e| e.is_single_byte())
+    }
+
+    fn next_byte(&mut self) -> Option<u8> {
+        if self.pos == self.len && !self.fill() {
+            return None;
+        }
+        let b = self.buf[self.pos];
+        self.pos += 1;
+        Some(b)
+    }
+
+    /// Reads more bytes into the buffer after moving any unread bytes to the front. Returns `false`
+    /// at the end of the stream or if an error occurred, in which case the error is held in
+    /// `self.error`.
+    fn fill(&mut self) -> bool {
+        if self.error.is_some() {
+            return false;
+        }
+        self.buf.copy_within(self.pos..self.len, 0);
+        self.len -= self.pos;
+        self.pos = 0;
+        if self.len == self.buf.len() {
+            return true;
+        }
+        loop {
+            match self.reader.read(&mut self.buf[self.len..]) {
+                Ok(0) => return false,
+                Ok(n) => {
+                    self.len += n;
+                    return true;
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.error = Some(e);
+                    return false;
+                }
+            }
+        }
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        match self.encoding {
+            Encoding::Utf8 => self.next_utf8(),
+            Encoding::Utf16Le | Encoding::Utf16Be => self.next_utf16(),
+            Encoding::Iso8859_1 | Encoding::Windows1252 => {
+                let b = self.next_byte()?;
+                Some(self.encoding.decode_byte(b))
+            }
+        }
+    }
+
+    fn next_utf8(&mut self) -> Option<char> {
+        let first = self.next_byte()?;
+        let (width, min, mut value) = match first {
+            0x00..=0x7F => return Some(first as char),
+            0xC2..=0xDF => (2, 0x80, u32::from(first & 0x1F)),
+            0xE0..=0xEF => (3, 0x800, u32::from(first & 0x0F)),
+            0xF0..=0xF4 => (4, 0x10000, u32::from(first & 0x07)),
+            _ => return self.invalid(first),
+        };
+        for _ in 1..width {
+            match self.next_byte() {
+                Some(b) if b & 0xC0 == 0x80 => value = (value << 6) | u32::from(b & 0x3F),
+                // the sequence is reported by the byte that started it.
+                Some(_) => return self.invalid(first),
+                None if self.error.is_some() => return None,
+                None => return self.invalid(first),
+            }
+        }
+        match std::char::from_u32(value) {
+            Some(c) if value >= min => Some(c),
+            _ => self.invalid(first),
+        }
+    }
+
+    fn next_utf16_unit(&mut self) -> Option<u16> {
+        let a = self.next_byte()?;
+        let b = match self.next_byte() {
+            Some(b) => b,
+            None if self.error.is_some() => return None,
+            None => {
+                self.invalid(a);
+                return None;
+            }
+        };
+        match self.encoding {
+            Encoding::Utf16Le => Some(u16::from_le_bytes([a, b])),
+            _ => Some(u16::from_be_bytes([a, b])),
+        }
+    }
+
+    fn next_utf16(&mut self) -> Option<char> {
+        let first = self.next_utf16_unit()?;
+        let units = match first {
+            0xD800..=0xDBFF => match self.next_utf16_unit() {
+                Some(second) => vec![first, second],
+                None if self.error.is_some() => return None,
+                None => vec![first],
+            },
+            _ => vec![first],
+        };
+        match std::char::decode_utf16(units).next() {
+            Some(Ok(c)) => Some(c),
+            _ => {
+                self.error = Some(std::io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid UTF-16 sequence at code unit 0x{first:04X}"),
+                ));
+                None
+            }
+        }
+    }
+
+    fn invalid(&mut self, byte: u8) -> Option<char> {
+        self.error = Some(std::io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "invalid {} sequence at byte 0x{byte:02X}",
+                self.encoding.label()
+            ),
+        ));
+        None
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+fn decode_all(bytes: &[u8]) -> (String, Option<std::io::Error>) {
+    let mut source = Source::from_read(Box::new(bytes));
+    let mut s = String::new();
+    while let Some(c) = source.next() {
+        s.push(c);
+    }
+    (s, source.take_error())
+}
+
+#[test]
+fn source_decodes_utf8() {
+    let want = "a🍺 é ∑ z";
+    let (got, err) = decode_all(want.as_bytes());
+    assert_eq!(want, got);
+    assert!(err.is_none());
+}
+
+#[test]
+fn source_decodes_across_buffer_boundary() {
+    let want = format!("{}🍺🍺", "x".repeat(BUFFER_SIZE - 2));
+    let (got, err) = decode_all(want.as_bytes());
+    assert_eq!(want, got);
+    assert!(err.is_none());
+}
+
+#[test]
+fn source_rejects_bad_utf8() {
+    let (got, err) = decode_all(b"ab\xFFcd");
+    assert_eq!("ab", got);
+    assert_eq!(ErrorKind::InvalidData, err.unwrap().kind());
+}
+
+#[test]
+fn source_rejects_truncated_utf8() {
+    let (got, err) = decode_all(b"<a>\xE9</a>");
+    assert_eq!("<a>", got);
+    assert_eq!(
+        "invalid UTF-8 sequence at byte 0xE9",
+        err.unwrap().to_string()
+    );
+}
+
+#[test]
+fn source_rejects_overlong_utf8() {
+    let (_, err) = decode_all(b"\xE0\x80\xAF");
+    assert!(err.is_some());
+}
+
+#[test]
+fn source_peek() {
+    let mut source = Source::from_read(Box::new("xy".as_bytes()));
+    assert_eq!(Some(&'x'), source.peek());
+    assert_eq!(Some('x'), source.next());
+    assert_eq!(Some('y'), source.next());
+    assert!(source.peek().is_none());
+    assert!(source.next().is_none());
+}
+
+#[test]
+fn source_detects_utf16_bom() {
+    let mut bytes = vec![0xFF, 0xFE];
+    for unit in "<a>🍺</a>".encode_utf16() {
+        bytes.extend_from_slice(&unit.to_le_bytes());
+    }
+    let mut source = Source::from_read(Box::new(&bytes[..]));
+    assert_eq!(Some(Encoding::Utf16Le), source.encoding());
+    let mut s = String::new();
+    while let Some(c) = source.next() {
+        s.push(c);
+    }
+    assert_eq!("<a>🍺</a>", s);
+}
+
+#[test]
+fn source_detects_utf16_without_bom() {
+    let bytes: Vec<u8> = "<?xml?>"
+        .encode_utf16()
+        .flat_map(|u| u.to_be_bytes())
+        .collect();
+    let source = Source::from_read(Box::new(&bytes[..]));
+    assert_eq!(Some(Encoding::Utf16Be), source.encoding());
+}
+
+#[test]
+fn source_skips_utf8_bom() {
+    let (got, err) = decode_all(b"\xEF\xBB\xBF<a/>");
+    assert_eq!("<a/>", got);
+    assert!(err.is_none());
+}
+
+#[test]
+fn source_detects_declared_encoding() {
+    let bytes = b"<?xml version='1.0' encoding = 'windows-1252'?><a>\x80\xE9</a>";
+    let mut source = Source::from_read(Box::new(&bytes[..]));
+    assert_eq!(Some(Encoding::Windows1252), source.encoding());
+    let mut s = String::new();
+    while let Some(c) = source.next() {
+        s.push(c);
+    }
+    assert!(s.ends_with("<a>€é</a>"));
+}
+
+#[test]
+fn source_entities() {
+    let mut source = Source::from_str("a;b");
+    assert_eq!(Some('a'), source.next());
+    assert_eq!(Some(';'), source.next());
+    source.push_entity("x", "1");
+    assert_eq!(Some('1'), source.next());
+    assert_eq!(1, source.depth());
+    assert!(source.is_expanding("x"));
+    source.push_entity("y", "");
+    assert_eq!(Some('b'), source.next());
+    assert_eq!(0, source.depth());
+    assert!(!source.is_expanding("x"));
+    assert!(source.next().is_none());
+}
@@ -1,6 +1,13 @@
+use std::borrow::Cow;
+
+use crate::error::ErrorKind;
+use crate::parser::chars::is_char;
+use crate::parser::entity::expand_entity;
 use crate::parser::error::Result;
-use crate::parser::Iter;
+use crate::parser::scratch::{trim_cow, Scratch};
+use crate::parser::{Iter, WhitespacePolicy};
 use crate::xdoc::is_whitespace;
+use crate::Limit;
 
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
 pub(crate) enum StringType {
@@ -13,59 +20,109 @@ pub(crate) enum StringType {
 }
 
 /// Parse a string that terminates based on some character(s) determined by `string_type`.
-pub(crate) fn parse_string(iter: &mut Iter<'_>, string_type: StringType) -> Result<String> {
-    let mut space_buffer = None;
+/// References to entities declared in the DTD are expanded as the string is parsed. Whitespace in
+/// element text is treated according to the iter's `WhitespacePolicy`. In attribute values each
+/// whitespace character becomes a space, except those given by character references, as described
+/// in https://www.w3.org/TR/xml/#AVNormalize. The string is borrowed from the document unless
+/// references, normalization or collapsed whitespace change it.
+pub(crate) fn parse_string<'a>(
+    iter: &mut Iter<'a>,
+    string_type: StringType,
+) -> Result<Cow<'a, str>> {
+    // when collapsing whitespace, a run of whitespace is held back until non-whitespace follows it.
+    // it is the offset of the run if it is a single space in the document, which can be borrowed.
+    let mut space_buffer: Option<Option<usize>> = None;
     let mut is_non_white_reached = false;
-    let mut result = String::new();
-    while !is_end_char(iter, string_type) {
+    let mut result = Scratch::new(iter);
+    let is_attribute = string_type != StringType::Element;
+    let is_verbatim = !is_attribute && iter.whitespace() != WhitespacePolicy::Collapse;
+    // quotes that come from the replacement text of an entity do not end an attribute value.
+    let depth = iter.it.depth();
+    // the number of `]` characters that came just before the current one, `]]>` is not allowed in
+    // element text.
+    let mut brackets = 0;
+    while !is_end_char(iter, string_type, depth) {
+        iter.expect_literal_char()?;
         if iter.st.c == '&' {
-            let c = parse_escape(iter)?;
-            if is_whitespace(c) {
-                if is_non_white_reached {
-                    space_buffer = Some(' ');
+            // when an entity is expanded there is nothing to push, its replacement text is next.
+            let escaped = match parse_escape(iter) {
+                Ok(escaped) => escaped,
+                Err(e) => {
+                    // when recovering, the reference is dropped. parsing resumes with the character
+                    // that could not be parsed, unless it is the `;` that ended the reference.
+                    iter.recover(e)?;
+                    if !iter.is(';') {
+                        continue;
+                    }
+                    None
                 }
-            } else {
-                is_non_white_reached = true;
-                if let Some(space) = space_buffer {
-                    result.push(space);
-                    space_buffer = None;
+            };
+            if let Some(c) = escaped {
+                if is_attribute || is_verbatim {
+                    result.push(c);
+                } else if is_whitespace(c) {
+                    if is_non_white_reached {
+                        space_buffer = Some(None);
+                    }
+                } else {
+                    is_non_white_reached = true;
+                    if let Some(offset) = space_buffer.take() {
+                        result.push_at(' ', offset);
+                    }
+                    result.push(c);
                 }
-                result.push(c);
             }
-        } else if is_forbidden(iter, string_type) {
-            return parse_err!(iter, "forbidden character in {:?} string", string_type);
+        } else if !is_attribute && iter.is('>') && brackets >= 2 {
+            return parse_err!(iter, ErrorKind::Syntax, "']]>' is not allowed in text");
+        } else if is_forbidden(iter) {
+            return parse_err!(
+                iter,
+                ErrorKind::InvalidCharacter,
+                "forbidden character in {:?} string",
+                string_type
+            );
+        } else if is_attribute && iter.is_whitespace() {
+            result.push_instead(' ', iter);
+        } else if is_attribute || is_verbatim {
+            result.push_current(iter);
         } else if iter.is_whitespace() {
             if is_non_white_reached {
-                space_buffer = Some(' ');
+                space_buffer = match space_buffer {
+                    None => Some(iter.offset()),
+                    Some(_) => Some(None),
+                };
             }
         } else {
             is_non_white_reached = true;
-            if let Some(space) = space_buffer {
-                result.push(space);
-                space_buffer = None;
+            if let Some(offset) = space_buffer.take() {
+                result.push_at(' ', offset);
             }
-            result.push(iter.st.c);
+            result.push_current(iter);
         }
+        iter.check_text_limit(Limit::TextLength, result.as_str())?;
 
-        if !iter.advance() {
+        brackets = if iter.is(']') { brackets + 1 } else { 0 };
+        // text in an element ends at the end of the document, the caller reports the missing tag.
+        if !iter.advance() && is_attribute {
             return parse_err!(
                 iter,
+                ErrorKind::UnexpectedEof,
                 "input ended before termination character '{}' was reached",
                 end_char(string_type)
             );
         }
     }
-    Ok(result)
+    if is_verbatim && iter.whitespace() == WhitespacePolicy::TrimOnly {
+        return Ok(trim_cow(result.into_cow(), |s| {
+            s.trim_matches(is_whitespace)
+        }));
+    }
+    Ok(result.into_cow())
 }
 
-fn is_forbidden(iter: &Iter<'_>, string_type: StringType) -> bool {
-    // & and < are illegal (as well as " or ' in attributes).
-    match iter.st.c {
-        '&' | '<' => true,
-        '"' => string_type == StringType::AttributeDouble,
-        '\'' => string_type == StringType::AttributeSingle,
-        _ => false,
-    }
+fn is_forbidden(iter: &Iter<'_>) -> bool {
+    // & and < are illegal. in attributes, this includes a < that comes from an entity.
+    matches!(iter.st.c, '&' | '<')
 }
 
 fn end_char(string_type: StringType) -> char {
@@ -76,77 +133,49 @@ fn end_char(string_type: StringType) -> char {
     }
 }
 
-fn is_end_char(iter: &Iter<'_>, string_type: StringType) -> bool {
-    iter.is(end_char(string_type))
-}
-
-fn parse_escape(iter: &mut Iter<'_>) -> Result<char> {
-    iter.advance_or_die()?;
-    match iter.st.c {
-        'a' => parse_amp_or_apos(iter),
-        'g' => parse_gt(iter),
-        'l' => parse_lt(iter),
-        'q' => parse_quot(iter),
-        '#' => parse_codepoint(iter),
-        _ => parse_err!(iter, "unexpected character in escape sequence"),
+fn is_end_char(iter: &Iter<'_>, string_type: StringType, depth: usize) -> bool {
+    match string_type {
+        StringType::Element => iter.is('<') || iter.end(),
+        _ => iter.is(end_char(string_type)) && iter.it.depth() == depth,
     }
 }
 
-fn parse_amp_or_apos(iter: &mut Iter<'_>) -> Result<char> {
+/// Parses a reference, expecting the iter to be pointing at `&`. Character references and the
+/// predefined entities are returned as the character that they stand for. References to other
+/// entities are expanded onto the iter, in which case `None` is returned. The iter is left pointing
+/// at the `;` that ends the reference.
+fn parse_escape(iter: &mut Iter<'_>) -> Result<Option<char>> {
     iter.advance_or_die()?;
-    if iter.is('m') {
-        parse_amp(iter)
-    } else if iter.is('p') {
-        parse_apos(iter)
-    } else {
-        parse_err!(iter, "expected either &amp; or &apos;")
+    if iter.is('#') {
+        return parse_codepoint(iter).map(Some);
+    }
+    let name = parse_reference_name(iter)?;
+    match name.as_str() {
+        "amp" => Ok(Some('&')),
+        "apos" => Ok(Some('\'')),
+        "gt" => Ok(Some('>')),
+        "lt" => Ok(Some('<')),
+        "quot" => Ok(Some('"')),
+        _ => {
+            expand_entity(iter, &name)?;
+            Ok(None)
+        }
     }
 }
 
-fn parse_amp(iter: &mut Iter<'_>) -> Result<char> {
-    iter.advance_or_die()?;
-    expect!(iter, 'p')?;
-    iter.advance_or_die()?;
-    expect!(iter, ';')?;
-    Ok('&')
-}
-
-fn parse_apos(iter: &mut Iter<'_>) -> Result<char> {
-    iter.advance_or_die()?;
-    expect!(iter, 'o')?;
-    iter.advance_or_die()?;
-    expect!(iter, 's')?;
-    iter.advance_or_die()?;
-    expect!(iter, ';')?;
-    Ok('\'')
-}
-
-fn parse_gt(iter: &mut Iter<'_>) -> Result<char> {
-    iter.advance_or_die()?;
-    expect!(iter, 't')?;
-    iter.advance_or_die()?;
-    expect!(iter, ';')?;
-    Ok('>')
-}
-
-fn parse_lt(iter: &mut Iter<'_>) -> Result<char> {
-    iter.advance_or_die()?;
-    expect!(iter, 't')?;
-    iter.advance_or_die()?;
-    expect!(iter, ';')?;
-    Ok('<')
-}
-
-fn parse_quot(iter: &mut Iter<'_>) -> Result<char> {
-    iter.advance_or_die()?;
-    expect!(iter, 'u')?;
-    iter.advance_or_die()?;
-    expect!(iter, 'o')?;
-    iter.advance_or_die()?;
-    expect!(iter, 't')?;
-    iter.advance_or_die()?;
-    expect!(iter, ';')?;
-    Ok('"')
+/// Parses the name in an entity reference, leaving the iter pointing at the `;` that follows it.
+fn parse_reference_name(iter: &mut Iter<'_>) -> Result<String> {
+    iter.expect_name_start_char()?;
+    let mut name = String::default();
+    name.push(iter.st.c);
+    loop {
+        iter.advance_or_die()?;
+        if iter.is(';') {
+            return Ok(name);
+        }
+        iter.expect_name_char()?;
+        name.push(iter.st.c);
+    }
 }
 
 fn parse_codepoint(iter: &mut Iter<'_>) -> Result<char> {
@@ -164,17 +193,25 @@ fn parse_hexidecimal_codepoint(iter: &mut Iter<'_>) -> Result<char> {
     let mut data = String::new();
     while !iter.is(';') {
         if !iter.is_hex() {
-            return parse_err!(iter, "non-hex-digit in hexidecimal unicode escape");
+            return parse_err!(
+                iter,
+                ErrorKind::InvalidReference,
+                "non-hex-digit in hexidecimal unicode escape"
+            );
         }
         data.push(iter.st.c);
         iter.advance_or_die()?;
     }
     let codepoint = u32::from_str_radix(data.as_str(), 16)
-        .map_err(|e| create_parser_error!(&iter.st, "{}", e))?;
-    let maybe_char = std::char::from_u32(codepoint);
-    match maybe_char {
-        Some(c) => Ok(c),
-        None => parse_err!(iter, "illegal unicode codepoint '{}'", codepoint),
+        .map_err(|e| create_parser_error!(&iter.st, ErrorKind::InvalidReference, "{}", e))?;
+    match std::char::from_u32(codepoint) {
+        Some(c) if is_char(c, iter.st.version) => Ok(c),
+        _ => parse_err!(
+            iter,
+            ErrorKind::InvalidReference,
+            "illegal unicode codepoint '{}'",
+            codepoint
+        ),
     }
 }
 
@@ -183,18 +220,26 @@ fn parse_decimal_codepoint(iter: &mut Iter<'_>) -> Result<char> {
     let mut data = String::new();
     while !iter.is(';') {
         if !iter.is_digit() {
-            return parse_err!(iter, "non-digit in decimal unicode escape");
+            return parse_err!(
+                iter,
+                ErrorKind::InvalidReference,
+                "non-digit in decimal unicode escape"
+            );
         }
         data.push(iter.st.c);
         iter.advance_or_die()?;
     }
     let codepoint = data
         .parse::<u32>()
-        .map_err(|e| create_parser_error!(&iter.st, "{}", e))?;
-    let maybe_char = std::char::from_u32(codepoint);
-    match maybe_char {
-        Some(c) => Ok(c),
-        None => parse_err!(iter, "illegal unicode codepoint '{}'", codepoint),
+        .map_err(|e| create_parser_error!(&iter.st, ErrorKind::InvalidReference, "{}", e))?;
+    match std::char::from_u32(codepoint) {
+        Some(c) if is_char(c, iter.st.version) => Ok(c),
+        _ => parse_err!(
+            iter,
+            ErrorKind::InvalidReference,
+            "illegal unicode codepoint '{}'",
+            codepoint
+        ),
     }
 }
 
@@ -205,85 +250,85 @@ fn parse_decimal_codepoint(iter: &mut Iter<'_>) -> Result<char> {
 #[test]
 fn test_parse_amp_ok() {
     use crate::parser::Iter;
-    let mut iter = Iter::new("mp;").unwrap();
-    let c = parse_amp(&mut iter).unwrap();
-    assert_eq!('&', c);
+    let mut iter = Iter::new("&amp;").unwrap();
+    let c = parse_escape(&mut iter).unwrap();
+    assert_eq!(Some('&'), c);
     assert_eq!(';', iter.st.c);
 }
 
 #[test]
 fn test_parse_amp_err() {
     use crate::parser::Iter;
-    let mut iter = Iter::new("mp").unwrap();
-    let result = parse_amp(&mut iter);
+    let mut iter = Iter::new("&amp").unwrap();
+    let result = parse_escape(&mut iter);
     assert!(result.is_err());
 }
 
 #[test]
 fn test_parse_apos_ok() {
     use crate::parser::Iter;
-    let mut iter = Iter::new("pos;").unwrap();
-    let c = parse_apos(&mut iter).unwrap();
-    assert_eq!('\'', c);
+    let mut iter = Iter::new("&apos;").unwrap();
+    let c = parse_escape(&mut iter).unwrap();
+    assert_eq!(Some('\''), c);
     assert_eq!(';', iter.st.c);
 }
 
 #[test]
 fn test_parse_apos_err() {
     use crate::parser::Iter;
-    let mut iter = Iter::new("pox;").unwrap();
-    let result = parse_apos(&mut iter);
+    let mut iter = Iter::new("&apox;").unwrap();
+    let result = parse_escape(&mut iter);
     assert!(result.is_err());
 }
 
 #[test]
 fn test_parse_gt_ok() {
     use crate::parser::Iter;
-    let mut iter = Iter::new("_t;;").unwrap();
-    let c = parse_gt(&mut iter).unwrap();
-    assert_eq!('>', c);
+    let mut iter = Iter::new("&gt;;").unwrap();
+    let c = parse_escape(&mut iter).unwrap();
+    assert_eq!(Some('>'), c);
     assert_eq!(';', iter.st.c);
 }
 
 #[test]
 fn test_parse_gt_err() {
     use crate::parser::Iter;
-    let mut iter = Iter::new("gt:").unwrap();
-    let result = parse_gt(&mut iter);
+    let mut iter = Iter::new("&gt:").unwrap();
+    let result = parse_escape(&mut iter);
     assert!(result.is_err());
 }
 
 #[test]
 fn test_parse_lt_ok() {
     use crate::parser::Iter;
-    let mut iter = Iter::new("_t;").unwrap();
-    let c = parse_lt(&mut iter).unwrap();
-    assert_eq!('<', c);
+    let mut iter = Iter::new("&lt;").unwrap();
+    let c = parse_escape(&mut iter).unwrap();
+    assert_eq!(Some('<'), c);
     assert_eq!(';', iter.st.c);
 }
 
 #[test]
 fn test_parse_lt_err() {
     use crate::parser::Iter;
-    let mut iter = Iter::new("_t:").unwrap();
-    let result = parse_lt(&mut iter);
+    let mut iter = Iter::new("&l t;").unwrap();
+    let result = parse_escape(&mut iter);
     assert!(result.is_err());
 }
 
 #[test]
 fn test_parse_quot_ok() {
     use crate::parser::Iter;
-    let mut iter = Iter::new("_uot;").unwrap();
-    let c = parse_quot(&mut iter).unwrap();
-    assert_eq!('"', c);
+    let mut iter = Iter::new("&quot;").unwrap();
+    let c = parse_escape(&mut iter).unwrap();
+    assert_eq!(Some('"'), c);
     assert_eq!(';', iter.st.c);
 }
 
 #[test]
 fn test_parse_quot_err() {
     use crate::parser::Iter;
-    let mut iter = Iter::new("_uot:").unwrap();
-    let result = parse_quot(&mut iter);
+    let mut iter = Iter::new("&quot:").unwrap();
+    let result = parse_escape(&mut iter);
     assert!(result.is_err());
 }
 
@@ -0,0 +1,308 @@
+/*!
+The `tree` module abstracts over the documents that the parser builds, so that the same parser can
+build an owned `Document`, or a `borrowed::Document` whose strings borrow from the string that is
+being parsed.
+!*/
+
+use std::borrow::Cow;
+
+use crate::xdoc::borrowed;
+use crate::xmlns::{resolve, Bindings};
+use crate::{Declaration, DocTypeDecl, Document, Element, Misc, Node, NsErr, Pi, Span};
+
+/// A node that is not an element, as it was parsed.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum Leaf<'a> {
+    Text(Cow<'a, str>),
+    CData(Cow<'a, str>),
+    Comment(Cow<'a, str>),
+    /// The target and data of a processing instruction.
+    Pi(Cow<'a, str>, Cow<'a, str>),
+}
+
+impl<'a> Leaf<'a> {
+    fn into_node(self) -> Node {
+        match self {
+            Leaf::Text(text) => Node::Text(text.into_owned()),
+            Leaf::CData(cdata) => Node::CData(cdata.into_owned()),
+            Leaf::Comment(comment) => Node::Comment(comment.into_owned()),
+            Leaf::Pi(target, data) => Node::Pi(Pi::new_unchecked(target, data)),
+        }
+    }
+
+    fn into_borrowed_node(self) -> borrowed::Node<'a> {
+        match self {
+            Leaf::Text(text) => borrowed::Node::Text(text),
+            Leaf::CData(cdata) => borrowed::Node::CData(cdata),
+            Leaf::Comment(comment) => borrowed::Node::Comment(comment),
+            Leaf::Pi(target, data) => borrowed::Node::Pi(borrowed::Pi::new(target, data)),
+        }
+    }
+}
+
+/// An element that the parser can build.
+pub(crate) trait Tree<'a>: Sized {
+    fn from_name(name: Cow<'a, str>) -> Self;
+
+    fn fullname(&self) -> &str;
+
+    fn attribute(&self, key: &str) -> Option<&str>;
+
+    fn attributes_len(&self) -> usize;
+
+    /// Adds an attribute that was specified in the document, and where it was parsed from if spans
+    /// are being recorded.
+    fn add_attribute(&mut self, key: Cow<'a, str>, value: Cow<'a, str>, span: Option<Span>);
+
+    /// Adds an attribute that was given its default value by the DTD.
+    fn add_defaulted_attribute(&mut self, key: &str, value: &str);
+
+    /// Adds a completed child element, along with its span if it has one.
+    fn add_child(&mut self, child: Self);
+
+    /// Adds a node other than an element, and where it was parsed from if spans are being recorded.
+    /// If `coalesce` is `true`, text is appended to the last node if that is text, in which case the
+    /// last node's span is extended.
+    fn add_leaf(&mut self, leaf: Leaf<'a>, span: Option<Span>, coalesce: bool);
+
+    fn span(&self) -> Option<Span>;
+
+    fn set_span(&mut self, span: Span);
+
+    fn namespaces(&self) -> Option<&Bindings>;
+
+    /// Returns the namespace bindings that are in scope for this element, given those of its parent.
+    fn resolve(&self, parent: &Bindings) -> Result<Bindings, NsErr>;
+
+    fn set_namespaces(&mut self, bindings: Bindings);
+}
+
+/// A document that the parser can build.
+pub(crate) trait DocumentTree<'a>: Default {
+    type Element: Tree<'a>;
+
+    fn set_declaration(&mut self, declaration: Declaration);
+
+    fn has_doctype(&self) -> bool;
+
+    fn set_doctype(&mut self, doctype: DocTypeDecl);
+
+    /// Adds a comment to the prolog, or to the epilog if `is_epilog` is `true`.
+    fn add_comment(&mut self, comment: Cow<'a, str>, is_epilog: bool);
+
+    /// Adds a processing instruction to the prolog, or to the epilog if `is_epilog` is `true`.
+    fn add_pi(&mut self, target: Cow<'a, str>, data: Cow<'a, str>, is_epilog: bool);
+
+    fn set_root(&mut self, root: Self::Element);
+}
+
+impl<'a> Tree<'a> for Element {
+    fn from_name(name: Cow<'a, str>) -> Self {
+        Element::from_name(name)
+    }
+
+    fn fullname(&self) -> &str {
+        Element::fullname(self)
+    }
+
+    fn attribute(&self, key: &str) -> Option<&str> {
+        Element::attribute(self, key).map(|value| value.as_str())
+    }
+
+    fn attributes_len(&self) -> usize {
+        Element::attributes_len(self)
+    }
+
+    fn add_attribute(&mut self, key: Cow<'a, str>, value: Cow<'a, str>, span: Option<Span>) {
+        Element::add_attribute(self, &key, value);
+        if let Some(span) = span {
+            self.set_attribute_span(key, span);
+        }
+    }
+
+    fn add_defaulted_attribute(&mut self, key: &str, value: &str) {
+        Element::add_defaulted_attribute(self, key, value);
+    }
+
+    fn add_child(&mut self, child: Self) {
+        let span = child.span();
+        Element::add_child(self, child);
+        if let Some(span) = span {
+            self.set_last_node_span(span);
+        }
+    }
+
+    fn add_leaf(&mut self, leaf: Leaf<'a>, span: Option<Span>, coalesce: bool) {
+        if let (Leaf::Text(text), true) = (&leaf, coalesce) {
+            if let Some(Node::Text(last)) = self.last_node_mut() {
+                last.push_str(text);
+                if let Some(span) = span {
+                    let last = self.node_span(self.nodes_len() - 1);
+                    let start = last.map_or(span.start, |last| last.start);
+                    self.set_last_node_span(Span {
+                        start,
+                        end: span.end,
+                    });
+                }
+                return;
+            }
+        }
+        self.add_node(leaf.into_node());
+        if let Some(span) = span {
+            self.set_last_node_span(span);
+        }
+    }
+
+    fn span(&self) -> Option<Span> {
+        Element::span(self)
+    }
+
+    fn set_span(&mut self, span: Span) {
+        Element::set_span(self, span);
+    }
+
+    fn namespaces(&self) -> Option<&Bindings> {
+        Element::namespaces(self)
+    }
+
+    fn resolve(&self, parent: &Bindings) -> Result<Bindings, NsErr> {
+        resolve(self, parent)
+    }
+
+    fn set_namespaces(&mut self, bindings: Bindings) {
+        Element::set_namespaces(self, bindings);
+    }
+}
+
+impl<'a> DocumentTree<'a> for Document {
+    type Element = Element;
+
+    fn set_declaration(&mut self, declaration: Declaration) {
+        Document::set_declaration(self, declaration);
+    }
+
+    fn has_doctype(&self) -> bool {
+        self.doctype().is_some()
+    }
+
+    fn set_doctype(&mut self, doctype: DocTypeDecl) {
+        self.set_doctype_decl(doctype);
+    }
+
+    fn add_comment(&mut self, comment: Cow<'a, str>, is_epilog: bool) {
+        let misc = Misc::Comment(comment.into_owned());
+        if is_epilog {
+            self.add_epilog_misc(misc);
+        } else {
+            self.add_prolog_misc(misc);
+        }
+    }
+
+    fn add_pi(&mut self, target: Cow<'a, str>, data: Cow<'a, str>, is_epilog: bool) {
+        let misc = Misc::Pi(Pi::new_unchecked(target, data));
+        if is_epilog {
+            self.add_epilog_misc(misc);
+        } else {
+            self.add_prolog_misc(misc);
+        }
+    }
+
+    fn set_root(&mut self, root: Element) {
+        Document::set_root(self, root);
+    }
+}
+
+// spans and namespaces are not supported by borrowed documents, which `parse_borrowed` checks.
+impl<'a> Tree<'a> for borrowed::Element<'a> {
+    fn from_name(name: Cow<'a, str>) -> Self {
+        borrowed::Element::from_name(name)
+    }
+
+    fn fullname(&self) -> &str {
+        borrowed::Element::fullname(self)
+    }
+
+    fn attribute(&self, key: &str) -> Option<&str> {
+        borrowed::Element::attribute(self, key)
+    }
+
+    fn attributes_len(&self) -> usize {
+        borrowed::Element::attributes_len(self)
+    }
+
+    fn add_attribute(&mut self, key: Cow<'a, str>, value: Cow<'a, str>, _: Option<Span>) {
+        borrowed::Element::add_attribute(self, key, value);
+    }
+
+    fn add_defaulted_attribute(&mut self, key: &str, value: &str) {
+        borrowed::Element::add_defaulted_attribute(self, key, value);
+    }
+
+    fn add_child(&mut self, child: Self) {
+        self.add_node(borrowed::Node::Element(child));
+    }
+
+    fn add_leaf(&mut self, leaf: Leaf<'a>, _: Option<Span>, coalesce: bool) {
+        if let (Leaf::Text(text), true) = (&leaf, coalesce) {
+            if let Some(borrowed::Node::Text(last)) = self.last_node_mut() {
+                last.to_mut().push_str(text);
+                return;
+            }
+        }
+        self.add_node(leaf.into_borrowed_node());
+    }
+
+    fn span(&self) -> Option<Span> {
+        None
+    }
+
+    fn set_span(&mut self, _: Span) {}
+
+    fn namespaces(&self) -> Option<&Bindings> {
+        None
+    }
+
+    fn resolve(&self, parent: &Bindings) -> Result<Bindings, NsErr> {
+        Ok(parent.clone())
+    }
+
+    fn set_namespaces(&mut self, _: Bindings) {}
+}
+
+impl<'a> DocumentTree<'a> for borrowed::Document<'a> {
+    type Element = borrowed::Element<'a>;
+
+    fn set_declaration(&mut self, declaration: Declaration) {
+        borrowed::Document::set_declaration(self, declaration);
+    }
+
+    fn has_doctype(&self) -> bool {
+        self.doctype().is_some()
+    }
+
+    fn set_doctype(&mut self, doctype: DocTypeDecl) {
+        borrowed::Document::set_doctype(self, doctype);
+    }
+
+    fn add_comment(&mut self, comment: Cow<'a, str>, is_epilog: bool) {
+        let misc = borrowed::Misc::Comment(comment);
+        if is_epilog {
+            self.add_epilog_misc(misc);
+        } else {
+            self.add_prolog_misc(misc);
+        }
+    }
+
+    fn add_pi(&mut self, target: Cow<'a, str>, data: Cow<'a, str>, is_epilog: bool) {
+        let misc = borrowed::Misc::Pi(borrowed::Pi::new(target, data));
+        if is_epilog {
+            self.add_epilog_misc(misc);
+        } else {
+            self.add_prolog_misc(misc);
+        }
+    }
+
+    fn set_root(&mut self, root: borrowed::Element<'a>) {
+        borrowed::Document::set_root(self, root);
+    }
+}
@@ -0,0 +1,858 @@
+/*!
+The `arena` module provides [`Arena`], a form of a document in which every node is kept in a single
+vector and identified by a [`NodeId`]. Each node links to its parent, its first and last children
+and its siblings, so that its relatives are found in constant time. The tree can be changed through
+the arena, and the ids of the nodes that remain stay valid.
+!*/
+
+use std::ops::{Index, IndexMut};
+
+use crate::error::ErrorKind;
+use crate::xdoc::error::Result;
+use crate::{Declaration, DocTypeDecl, Document, Edge, Element, Misc, Node, Pi, Span};
+
+/// Identifies a node in an [`Arena`]. Ids are never reused, so the id of a removed node does not
+/// refer to any other node later on.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct NodeId(usize);
+
+/// The content of a node in an [`Arena`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum NodeData {
+    /// The document itself. Its children are the root element, the doctype, and the comments and
+    /// processing instructions of the prolog and epilog.
+    Document,
+
+    /// `<!DOCTYPE doc>`, which can only be a child of the document.
+    DocType(DocTypeDecl),
+
+    /// `<element/>`. Its child nodes are nodes of the arena, the `Element` itself has no nodes
+    /// unless they are added to it directly.
+    Element(Element),
+
+    /// Text data in an element, i.e. `<x>hello &lt;</x>` where the `Text` is `hello <`.
+    Text(String),
+
+    /// `<![CDATA[text]]>`
+    CData(String),
+
+    /// Comment, e.g. `<!--some comment-->`
+    Comment(String),
+
+    /// Processing Instruction, e.g. `<?target data?>`
+    Pi(Pi),
+}
+
+impl NodeData {
+    fn from_node(node: Node) -> Self {
+        match node {
+            Node::CData(cdata) => NodeData::CData(cdata),
+            Node::Comment(comment) => NodeData::Comment(comment),
+            Node::Element(element) => NodeData::Element(element),
+            Node::Pi(pi) => NodeData::Pi(pi),
+            Node::Text(text) => NodeData::Text(text),
+        }
+    }
+
+    fn from_misc(misc: Misc) -> Self {
+        match misc {
+            Misc::Comment(comment) => NodeData::Comment(comment),
+            Misc::Pi(pi) => NodeData::Pi(pi),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            NodeData::Document => "document",
+            NodeData::DocType(_) => "doctype",
+            NodeData::Element(_) => "element",
+            NodeData::Text(_) => "text",
+            NodeData::CData(_) => "CDATA",
+            NodeData::Comment(_) => "comment",
+            NodeData::Pi(_) => "processing instruction",
+        }
+    }
+}
+
+/// A node and its links to the nodes around it.
+#[derive(Debug, Clone)]
+struct Slot {
+    data: NodeData,
+    /// Where the node was parsed from, if spans were recorded.
+    span: Option<Span>,
+    parent: Option<NodeId>,
+    first_child: Option<NodeId>,
+    last_child: Option<NodeId>,
+    previous_sibling: Option<NodeId>,
+    next_sibling: Option<NodeId>,
+}
+
+impl Slot {
+    fn new(data: NodeData, span: Option<Span>) -> Self {
+        Self {
+            data,
+            span,
+            parent: None,
+            first_child: None,
+            last_child: None,
+            previous_sibling: None,
+            next_sibling: None,
+        }
+    }
+}
+
+/// A document in which every node, of any kind, has a [`NodeId`] and links to its parent, children
+/// and siblings. Nodes are added, moved and removed through the arena, which checks that the tree
+/// stays a well-formed document.
+///
+/// # Example
+///
+/// ```
+/// use exile::NodeData;
+/// let xml = "<a><b/><c><d>x</d><e/></c></a>";
+/// let mut arena = exile::parse(xml).unwrap().into_arena();
+/// let a = arena.root().unwrap();
+///
+/// // find <d> by following the links
+/// let c = arena.last_child(a).unwrap();
+/// let d = arena.first_child(c).unwrap();
+/// assert_eq!("d", arena.element(d).unwrap().name());
+/// assert_eq!(Some(c), arena.parent(d));
+///
+/// // every node has an id, including text
+/// let x = arena.first_child(d).unwrap();
+/// assert_eq!(NodeData::Text("x".into()), arena[x]);
+///
+/// // move <d> to the end of <a>
+/// arena.append(a, d).unwrap();
+/// let expected = exile::parse("<a><b/><c><e/></c><d>x</d></a>").unwrap();
+/// assert_eq!(expected, arena.into_document());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Arena {
+    declaration: Option<Declaration>,
+    /// Indexed by `NodeId`. Removed nodes leave `None` behind, so that ids are not reused.
+    slots: Vec<Option<Slot>>,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self {
+            declaration: None,
+            slots: vec![Some(Slot::new(NodeData::Document, None))],
+        }
+    }
+}
+
+impl Arena {
+    /// Create an arena holding only the document node.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The id of the document node, which is the parent of the root element.
+    pub fn document(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    /// The id of the root element. `None` if it has been removed.
+    pub fn root(&self) -> Option<NodeId> {
+        self.children(self.document())
+            .find(|&id| self.element(id).is_some())
+    }
+
+    /// Get the `Declaration` object, or `None` if the document does not have an XML declaration.
+    pub fn declaration(&self) -> Option<&Declaration> {
+        self.declaration.as_ref()
+    }
+
+    /// Set the `Declaration` object for the document.
+    pub fn set_declaration(&mut self, declaration: Declaration) {
+        self.declaration = Some(declaration);
+    }
+
+    /// Remove the `Declaration`. Returns the `Declaration` that was removed, if there was one.
+    pub fn remove_declaration(&mut self) -> Option<Declaration> {
+        self.declaration.take()
+    }
+
+    /// Returns `true` if `id` refers to a node that has not been removed.
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.slot(id).is_some()
+    }
+
+    /// Get the content of the node `id`. `None` if it has been removed.
+    pub fn get(&self, id: NodeId) -> Option<&NodeData> {
+        self.slot(id).map(|slot| &slot.data)
+    }
+
+    /// Get the content of the node `id` for modification. `None` if it has been removed.
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut NodeData> {
+        self.slot_mut(id).map(|slot| &mut slot.data)
+    }
+
+    /// Get the node `id` if it is an element.
+    pub fn element(&self, id: NodeId) -> Option<&Element> {
+        match self.get(id) {
+            Some(NodeData::Element(element)) => Some(element),
+            _ => None,
+        }
+    }
+
+    /// Get the node `id` for modification if it is an element.
+    pub fn element_mut(&mut self, id: NodeId) -> Option<&mut Element> {
+        match self.get_mut(id) {
+            Some(NodeData::Element(element)) => Some(element),
+            _ => None,
+        }
+    }
+
+    /// Where the node `id` was parsed from, if the document was parsed with
+    /// [`crate::ParseOptions::spans`].
+    pub fn span(&self, id: NodeId) -> Option<Span> {
+        self.slot(id)?.span
+    }
+
+    /// The parent of the node `id`. `None` for the document node and for nodes that are detached.
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.slot(id)?.parent
+    }
+
+    /// The first child of the node `id`.
+    pub fn first_child(&self, id: NodeId) -> Option<NodeId> {
+        self.slot(id)?.first_child
+    }
+
+    /// The last child of the node `id`.
+    pub fn last_child(&self, id: NodeId) -> Option<NodeId> {
+        self.slot(id)?.last_child
+    }
+
+    /// The sibling that comes before the node `id`.
+    pub fn previous_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.slot(id)?.previous_sibling
+    }
+
+    /// The sibling that comes after the node `id`.
+    pub fn next_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.slot(id)?.next_sibling
+    }
+
+    /// Iterate over the children (of any kind) of the node `id`.
+    pub fn children(&self, id: NodeId) -> Children<'_> {
+        Children {
+            arena: self,
+            next: self.first_child(id),
+        }
+    }
+
+    /// Iterate over the ancestors of the node `id`, starting with its parent and ending with the
+    /// document node.
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.parent(id), move |&id| self.parent(id))
+    }
+
+    /// Iterate over the siblings that come after the node `id`, nearest first.
+    pub fn following_siblings(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.next_sibling(id), move |&id| self.next_sibling(id))
+    }
+
+    /// Iterate over the siblings that come before the node `id`, nearest first.
+    pub fn preceding_siblings(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.previous_sibling(id), move |&id| {
+            self.previous_sibling(id)
+        })
+    }
+
+    /// Iterate over the descendants of the node `id`, in document order. The node itself is not
+    /// included.
+    pub fn descendants(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.traverse(id).filter_map(|edge| match edge {
+            Edge::Enter(id) => Some(id),
+            Edge::Exit(_) => None,
+        })
+    }
+
+    /// Walk the descendants of the node `id` depth-first, entering each node before its children
+    /// and exiting it after them. Like [`Element::traverse`], the node itself is not included, so
+    /// the first edge enters its first child and the last exits its last child.
+    ///
+    /// ```
+    /// use exile::Edge;
+    /// let arena = exile::parse("<a><b><c/></b>text</a>").unwrap().into_arena();
+    /// let a = arena.root().unwrap();
+    /// let b = arena.first_child(a).unwrap();
+    /// let c = arena.first_child(b).unwrap();
+    /// let text = arena.last_child(a).unwrap();
+    /// let edges: Vec<Edge<_>> = arena.traverse(a).collect();
+    /// assert_eq!(
+    ///     vec![
+    ///         Edge::Enter(b),
+    ///         Edge::Enter(c),
+    ///         Edge::Exit(c),
+    ///         Edge::Exit(b),
+    ///         Edge::Enter(text),
+    ///         Edge::Exit(text),
+    ///     ],
+    ///     edges
+    /// );
+    /// ```
+    pub fn traverse(&self, id: NodeId) -> impl Iterator<Item = Edge<NodeId>> + '_ {
+        let mut next = self.first_child(id).map(Edge::Enter);
+        std::iter::from_fn(move || {
+            let edge = next?;
+            next = match edge {
+                Edge::Enter(node) => match self.first_child(node) {
+                    Some(child) => Some(Edge::Enter(child)),
+                    None => Some(Edge::Exit(node)),
+                },
+                Edge::Exit(node) => match self.next_sibling(node) {
+                    Some(sibling) => Some(Edge::Enter(sibling)),
+                    None => self.parent(node).filter(|&p| p != id).map(Edge::Exit),
+                },
+            };
+            Some(edge)
+        })
+    }
+
+    /// Add a node holding `data` to the arena. It is not part of the document until it is given a
+    /// parent with [`Arena::append`] or [`Arena::insert_before`].
+    pub fn new_node(&mut self, data: NodeData) -> NodeId {
+        let id = NodeId(self.slots.len());
+        self.slots.push(Some(Slot::new(data, None)));
+        id
+    }
+
+    /// Make `child` the last child of `parent`, moving it from where it was. Returns an error if
+    /// the document would not be well-formed, e.g. if `parent` is not an element or the document,
+    /// if `child` is an ancestor of `parent`, or if the document would have two root elements.
+    pub fn append(&mut self, parent: NodeId, child: NodeId) -> Result<()> {
+        self.check_insert(parent, child)?;
+        self.detach(child);
+        let previous = self.last_child(parent);
+        self.link(parent, child, previous, None);
+        Ok(())
+    }
+
+    /// Make `child` the sibling that comes before `sibling`, moving it from where it was. Returns an
+    /// error if `sibling` does not have a parent, or for the reasons given in [`Arena::append`].
+    pub fn insert_before(&mut self, sibling: NodeId, child: NodeId) -> Result<()> {
+        let parent = match self.parent(sibling) {
+            Some(parent) if sibling != child => parent,
+            _ => {
+                return raise!(
+                    ErrorKind::InvalidDocument,
+                    "the node {:?} cannot be inserted before {:?}",
+                    child,
+                    sibling
+                )
+            }
+        };
+        self.check_insert(parent, child)?;
+        self.detach(child);
+        let previous = self.previous_sibling(sibling);
+        self.link(parent, child, previous, Some(sibling));
+        Ok(())
+    }
+
+    /// Remove the node `id` from its parent. The node and its descendants stay in the arena, and
+    /// can be given a new parent.
+    pub fn detach(&mut self, id: NodeId) {
+        let (parent, previous, next) = match self.slot(id) {
+            Some(slot) => (slot.parent, slot.previous_sibling, slot.next_sibling),
+            None => return,
+        };
+        let parent = match parent {
+            Some(parent) => parent,
+            None => return,
+        };
+        match previous.and_then(|previous| self.slot_mut(previous)) {
+            Some(slot) => slot.next_sibling = next,
+            None => self.set_first_child(parent, next),
+        }
+        match next.and_then(|next| self.slot_mut(next)) {
+            Some(slot) => slot.previous_sibling = previous,
+            None => self.set_last_child(parent, previous),
+        }
+        if let Some(slot) = self.slot_mut(id) {
+            slot.parent = None;
+            slot.previous_sibling = None;
+            slot.next_sibling = None;
+        }
+    }
+
+    /// Remove the node `id` and all of its descendants from the arena. Returns an error if `id` is
+    /// the document node or has already been removed.
+    pub fn remove(&mut self, id: NodeId) -> Result<()> {
+        if id == self.document() || !self.contains(id) {
+            return raise!(
+                ErrorKind::InvalidDocument,
+                "the node {:?} cannot be removed",
+                id
+            );
+        }
+        self.detach(id);
+        let mut pending = vec![id];
+        while let Some(id) = pending.pop() {
+            pending.extend(self.children(id));
+            self.slots[id.0] = None;
+        }
+        Ok(())
+    }
+
+    /// Convert the arena back into a [`Document`]. Nodes that are detached are dropped. If the
+    /// root element has been removed, the document has a default root element.
+    pub fn into_document(mut self) -> Document {
+        let mut document = Document::new();
+        if let Some(declaration) = self.declaration.take() {
+            document.set_declaration(declaration);
+        }
+        let mut is_epilog = false;
+        let mut next = self.first_child(self.document());
+        while let Some(id) = next {
+            next = self.next_sibling(id);
+            let misc = match self.take(id) {
+                NodeData::DocType(doctype) => {
+                    document.set_doctype_decl(doctype);
+                    continue;
+                }
+                NodeData::Element(element) => {
+                    let root = self.take_element(id, element);
+                    document.set_root(root);
+                    is_epilog = true;
+                    continue;
+                }
+                NodeData::Comment(comment) => Misc::Comment(comment),
+                NodeData::Pi(pi) => Misc::Pi(pi),
+                // the document node cannot have other children.
+                _ => continue,
+            };
+            if is_epilog {
+                document.add_epilog_misc(misc);
+            } else {
+                document.add_prolog_misc(misc);
+            }
+        }
+        document
+    }
+
+    fn slot(&self, id: NodeId) -> Option<&Slot> {
+        self.slots.get(id.0).and_then(Option::as_ref)
+    }
+
+    fn slot_mut(&mut self, id: NodeId) -> Option<&mut Slot> {
+        self.slots.get_mut(id.0).and_then(Option::as_mut)
+    }
+
+    fn set_first_child(&mut self, parent: NodeId, child: Option<NodeId>) {
+        if let Some(slot) = self.slot_mut(parent) {
+            slot.first_child = child;
+        }
+    }
+
+    fn set_last_child(&mut self, parent: NodeId, child: Option<NodeId>) {
+        if let Some(slot) = self.slot_mut(parent) {
+            slot.last_child = child;
+        }
+    }
+
+    /// Returns an error if `child` cannot become a child of `parent`.
+    fn check_insert(&self, parent: NodeId, child: NodeId) -> Result<()> {
+        let (parent_data, child_data) = match (self.get(parent), self.get(child)) {
+            (Some(parent_data), Some(child_data)) => (parent_data, child_data),
+            _ => {
+                return raise!(
+                    ErrorKind::InvalidDocument,
+                    "the node {:?} or {:?} does not exist",
+                    parent,
+                    child
+                )
+            }
+        };
+        let is_allowed = match (parent_data, child_data) {
+            (_, NodeData::Document) => false,
+            (NodeData::Document, NodeData::Text(_) | NodeData::CData(_)) => false,
+            (NodeData::Document, NodeData::Element(_)) => self.root().is_none_or(|r| r == child),
+            (NodeData::Document, NodeData::DocType(_)) => self
+                .children(parent)
+                .all(|id| id == child || !matches!(self[id], NodeData::DocType(_))),
+            (NodeData::Document, _) => true,
+            (NodeData::Element(_), NodeData::DocType(_)) => false,
+            (NodeData::Element(_), _) => true,
+            _ => false,
+        };
+        if !is_allowed {
+            return raise!(
+                ErrorKind::InvalidDocument,
+                "a {} cannot be added to the {} {:?}",
+                child_data.name(),
+                parent_data.name(),
+                parent
+            );
+        }
+        let mut ancestor = Some(parent);
+        while let Some(id) = ancestor {
+            if id == child {
+                return raise!(
+                    ErrorKind::InvalidDocument,
+                    "the node {:?} cannot be added to its own descendant {:?}",
+                    child,
+                    parent
+                );
+            }
+            ancestor = self.parent(id);
+        }
+        Ok(())
+    }
+
+    /// Links the detached node `child` into the children of `parent`, between `previous` and `next`.
+    fn link(
+        &mut self,
+        parent: NodeId,
+        child: NodeId,
+        previous: Option<NodeId>,
+        next: Option<NodeId>,
+    ) {
+        if let Some(slot) = self.slot_mut(child) {
+            slot.parent = Some(parent);
+            slot.previous_sibling = previous;
+            slot.next_sibling = next;
+        }
+        match previous.and_then(|previous| self.slot_mut(previous)) {
+            Some(slot) => slot.next_sibling = Some(child),
+            None => self.set_first_child(parent, Some(child)),
+        }
+        match next.and_then(|next| self.slot_mut(next)) {
+            Some(slot) => slot.previous_sibling = Some(child),
+            None => self.set_last_child(parent, Some(child)),
+        }
+    }
+
+    /// Adds a node holding `data` as the last child of `parent`.
+    fn push(&mut self, parent: NodeId, data: NodeData, span: Option<Span>) -> NodeId {
+        let id = self.new_node(data);
+        if let Some(slot) = self.slot_mut(id) {
+            slot.span = span;
+        }
+        let previous = self.last_child(parent);
+        self.link(parent, id, previous, None);
+        id
+    }
+
+    /// Adds `element` and its descendants as the last child of `parent`. Child elements are not
+    /// added recursively, so that the nesting depth is not limited by the size of the call stack.
+    fn push_element(&mut self, parent: NodeId, mut element: Element) {
+        let span = element.span();
+        let nodes = element.take_nodes();
+        let id = self.push(parent, NodeData::Element(element), span);
+        let mut pending = vec![(id, nodes)];
+        while let Some((parent, nodes)) = pending.pop() {
+            for (node, span) in nodes {
+                let data = match node {
+                    Node::Element(mut element) => {
+                        let nodes = element.take_nodes();
+                        let id = self.push(parent, NodeData::Element(element), span);
+                        pending.push((id, nodes));
+                        continue;
+                    }
+                    node => NodeData::from_node(node),
+                };
+                self.push(parent, data, span);
+            }
+        }
+    }
+
+    /// Takes the content of the node `id`, leaving the document node's content in its place.
+    fn take(&mut self, id: NodeId) -> NodeData {
+        match self.slot_mut(id) {
+            Some(slot) => std::mem::replace(&mut slot.data, NodeData::Document),
+            None => NodeData::Document,
+        }
+    }
+
+    /// Rebuilds `element`, whose content has been taken from the node `id`, from its descendants.
+    /// Child elements are not rebuilt recursively, for the same reason as in `push_element`.
+    fn take_element(&mut self, id: NodeId, mut element: Element) -> Element {
+        // the elements that are being rebuilt, innermost last, with the span of the child that is
+        // being rebuilt and the next child to add to each.
+        let mut open: Vec<(Element, Option<Span>, Option<NodeId>)> = Vec::new();
+        let mut next = self.first_child(id);
+        loop {
+            let child = match next {
+                Some(child) => child,
+                None => match open.pop() {
+                    Some((mut parent, span, parent_next)) => {
+                        parent.add_child(element);
+                        if let Some(span) = span {
+                            parent.set_last_node_span(span);
+                        }
+                        element = parent;
+                        next = parent_next;
+                        continue;
+                    }
+                    None => return element,
+                },
+            };
+            next = self.next_sibling(child);
+            let span = self.span(child);
+            let node = match self.take(child) {
+                NodeData::Element(child_element) => {
+                    open.push((element, span, next));
+                    element = child_element;
+                    next = self.first_child(child);
+                    continue;
+                }
+                NodeData::Text(text) => Node::Text(text),
+                NodeData::CData(cdata) => Node::CData(cdata),
+                NodeData::Comment(comment) => Node::Comment(comment),
+                NodeData::Pi(pi) => Node::Pi(pi),
+                // elements cannot have other children.
+                NodeData::Document | NodeData::DocType(_) => continue,
+            };
+            element.add_node(node);
+            if let Some(span) = span {
+                element.set_last_node_span(span);
+            }
+        }
+    }
+}
+
+impl From<Document> for Arena {
+    fn from(document: Document) -> Self {
+        let mut arena = Arena::new();
+        let parent = arena.document();
+        let prolog = document.prolog;
+        arena.declaration = prolog.xml_decl;
+        for misc in prolog.misc_before_doctype {
+            arena.push(parent, NodeData::from_misc(misc), None);
+        }
+        if let Some(doctype) = prolog.doctypedecl {
+            arena.push(parent, NodeData::DocType(doctype), None);
+        }
+        for misc in prolog.misc_after_doctype {
+            arena.push(parent, NodeData::from_misc(misc), None);
+        }
+        arena.push_element(parent, document.root);
+        for misc in document.epilog_misc {
+            arena.push(parent, NodeData::from_misc(misc), None);
+        }
+        arena
+    }
+}
+
+impl From<Arena> for Document {
+    fn from(arena: Arena) -> Self {
+        arena.into_document()
+    }
+}
+
+impl Index<NodeId> for Arena {
+    type Output = NodeData;
+
+    /// Panics if the node has been removed, see [`Arena::get`].
+    fn index(&self, id: NodeId) -> &NodeData {
+        match self.get(id) {
+            Some(data) => data,
+            None => panic!("the node {:?} has been removed", id),
+        }
+    }
+}
+
+impl IndexMut<NodeId> for Arena {
+    /// Panics if the node has been removed, see [`Arena::get_mut`].
+    fn index_mut(&mut self, id: NodeId) -> &mut NodeData {
+        match self.get_mut(id) {
+            Some(data) => data,
+            None => panic!("the node {:?} has been removed", id),
+        }
+    }
+}
+
+/// An iterator over the children of a node in an [`Arena`], see [`Arena::children`].
+#[derive(Debug, Clone)]
+pub struct Children<'a> {
+    arena: &'a Arena,
+    next: Option<NodeId>,
+}
+
+impl Iterator for Children<'_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.next?;
+        self.next = self.arena.next_sibling(id);
+        Some(id)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// tests
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn arena_links() {
+    let xml = r#"<!--c--><a><b/>text<c><d/><?pi?></c><!--x--></a><?after?>"#;
+    let arena = crate::parse(xml).unwrap().into_arena();
+    let document = arena.document();
+    assert_eq!(NodeData::Document, arena[document]);
+    assert_eq!(None, arena.parent(document));
+    let a = arena.root().unwrap();
+    assert_eq!(Some(document), arena.parent(a));
+    assert_eq!(3, arena.children(document).count());
+    let kinds: Vec<&str> = arena.children(a).map(|id| arena[id].name()).collect();
+    assert_eq!(vec!["element", "text", "element", "comment"], kinds);
+    let b = arena.first_child(a).unwrap();
+    let text = arena.next_sibling(b).unwrap();
+    let c = arena.next_sibling(text).unwrap();
+    assert_eq!(Some(b), arena.previous_sibling(text));
+    assert_eq!(None, arena.previous_sibling(b));
+    assert_eq!(NodeData::Text("text".into()), arena[text]);
+    assert_eq!(Some(a), arena.parent(text));
+    let pi = arena.last_child(c).unwrap();
+    assert_eq!(Some(c), arena.parent(pi));
+    assert_eq!(None, arena.next_sibling(pi));
+    assert_eq!(None, arena.first_child(b));
+    for id in arena.children(a).chain(arena.children(c)) {
+        let parent = arena.parent(id).unwrap();
+        assert!(arena.children(parent).any(|child| child == id));
+    }
+}
+
+#[test]
+fn arena_round_trip() {
+    let xml = r#"<?xml version="1.0"?>
+<!--before-->
+<!DOCTYPE a [<!ELEMENT a ANY>]>
+<?after-doctype?>
+<a x="1"><b>text<![CDATA[cdata]]></b><!--c--><c><d><e/></d></c></a>
+<!--epilog-->"#;
+    let opts = crate::ParseOptions::new().spans(true);
+    let doc = crate::parse_with(xml, &opts).unwrap();
+    let arena = doc.clone().into_arena();
+    let b = arena.first_child(arena.root().unwrap()).unwrap();
+    let text = arena.first_child(b).unwrap();
+    assert_eq!("text", &xml[arena.span(text).unwrap().range()]);
+    let round_tripped = arena.into_document();
+    assert_eq!(doc, round_tripped);
+    assert_eq!(doc.to_string(), round_tripped.to_string());
+    let b = round_tripped.root().child("b").unwrap();
+    assert_eq!("<![CDATA[cdata]]>", &xml[b.node_span(1).unwrap().range()]);
+}
+
+#[test]
+fn arena_mutation() {
+    let mut arena = crate::parse("<a><b/><c>text</c></a>").unwrap().into_arena();
+    let a = arena.root().unwrap();
+    let b = arena.first_child(a).unwrap();
+    let c = arena.last_child(a).unwrap();
+    let text = arena.first_child(c).unwrap();
+
+    // move the text in front of <b>, then add a comment to <b>.
+    arena.insert_before(b, text).unwrap();
+    assert_eq!(None, arena.first_child(c));
+    assert_eq!(Some(text), arena.first_child(a));
+    let comment = arena.new_node(NodeData::Comment("new".into()));
+    assert_eq!(None, arena.parent(comment));
+    arena.append(b, comment).unwrap();
+    if let Some(NodeData::Text(text)) = arena.get_mut(text) {
+        text.push_str(" here");
+    }
+    arena.element_mut(c).unwrap().add_attribute("x", "1");
+    let doc = arena.clone().into_document();
+    let expected = crate::parse(r#"<a>text here<b><!--new--></b><c x="1"/></a>"#).unwrap();
+    assert_eq!(expected.root(), doc.root());
+
+    // removing a node removes its descendants, the other ids stay valid.
+    arena.remove(b).unwrap();
+    assert!(!arena.contains(b));
+    assert!(!arena.contains(comment));
+    assert_eq!(None, arena.get(comment));
+    assert_eq!(Some(c), arena.next_sibling(text));
+    assert_eq!(Some(text), arena.previous_sibling(c));
+    assert_eq!(vec![text, c], arena.children(a).collect::<Vec<_>>());
+    let expected = crate::parse(r#"<a>text here<c x="1"/></a>"#).unwrap();
+    assert_eq!(expected.root(), arena.into_document().root());
+}
+
+#[test]
+fn arena_mutation_errors() {
+    let mut arena = crate::parse("<a><b/></a>").unwrap().into_arena();
+    let document = arena.document();
+    let a = arena.root().unwrap();
+    let b = arena.first_child(a).unwrap();
+    let text = arena.new_node(NodeData::Text("t".into()));
+    let doctype = arena.new_node(NodeData::DocType("<!DOCTYPE a>".parse().unwrap()));
+    let kind = |result: Result<()>| result.unwrap_err().kind;
+    assert_eq!(ErrorKind::InvalidDocument, kind(arena.append(b, a)));
+    assert_eq!(ErrorKind::InvalidDocument, kind(arena.append(b, b)));
+    assert_eq!(ErrorKind::InvalidDocument, kind(arena.append(text, b)));
+    assert_eq!(
+        ErrorKind::InvalidDocument,
+        kind(arena.append(document, text))
+    );
+    assert_eq!(ErrorKind::InvalidDocument, kind(arena.append(document, b)));
+    assert_eq!(ErrorKind::InvalidDocument, kind(arena.append(a, doctype)));
+    assert_eq!(ErrorKind::InvalidDocument, kind(arena.append(a, document)));
+    assert_eq!(ErrorKind::InvalidDocument, kind(arena.insert_before(a, a)));
+    assert_eq!(
+        ErrorKind::InvalidDocument,
+        kind(arena.insert_before(text, b))
+    );
+    assert_eq!(ErrorKind::InvalidDocument, kind(arena.remove(document)));
+    arena.insert_before(a, doctype).unwrap();
+    let second = arena.new_node(NodeData::DocType("<!DOCTYPE a>".parse().unwrap()));
+    assert_eq!(
+        ErrorKind::InvalidDocument,
+        kind(arena.append(document, second))
+    );
+    // the tree is unchanged by the errors.
+    assert_eq!(Some(b), arena.first_child(a));
+    assert_eq!(Some(a), arena.next_sibling(doctype));
+    let doc = arena.into_document();
+    assert!(doc.doctype().is_some());
+    assert_eq!("<a>\n  <b/>\n</a>", doc.root().to_string());
+}
+
+#[test]
+fn arena_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Arena>();
+    assert_send_sync::<NodeId>();
+}
+
+#[test]
+fn arena_iterators() {
+    let xml = "<a><b><c/>text</b><d/><!--e--></a>";
+    let arena = crate::parse(xml).unwrap().into_arena();
+    let document = arena.document();
+    let a = arena.root().unwrap();
+    let b = arena.first_child(a).unwrap();
+    let c = arena.first_child(b).unwrap();
+    let text = arena.next_sibling(c).unwrap();
+    let d = arena.next_sibling(b).unwrap();
+    let e = arena.next_sibling(d).unwrap();
+    assert_eq!(vec![b, a, document], arena.ancestors(c).collect::<Vec<_>>());
+    assert_eq!(0, arena.ancestors(document).count());
+    assert_eq!(vec![d, e], arena.following_siblings(b).collect::<Vec<_>>());
+    assert_eq!(vec![d, b], arena.preceding_siblings(e).collect::<Vec<_>>());
+    assert_eq!(0, arena.preceding_siblings(b).count());
+    assert_eq!(
+        vec![b, c, text, d, e],
+        arena.descendants(a).collect::<Vec<_>>()
+    );
+    assert_eq!(vec![c, text], arena.descendants(b).collect::<Vec<_>>());
+    assert_eq!(0, arena.descendants(e).count());
+    let edges: Vec<Edge<NodeId>> = arena.traverse(b).collect();
+    let expected = vec![
+        Edge::Enter(c),
+        Edge::Exit(c),
+        Edge::Enter(text),
+        Edge::Exit(text),
+    ];
+    assert_eq!(expected, edges);
+    assert_eq!(12, arena.traverse(document).count());
+    assert_eq!(0, arena.traverse(e).count());
+    // the arena and the element walk the same nodes.
+    let element_edges = crate::parse(xml).unwrap().root().traverse().count();
+    assert_eq!(element_edges, arena.traverse(a).count());
+}
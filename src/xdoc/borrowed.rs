@@ -0,0 +1,380 @@
+/*!
+The `borrowed` module provides a read-only document whose names and text borrow from the string
+that it was parsed from. Only the strings that parsing changes, for example because they contain
+escapes or line ends that are normalized, are allocated. The borrowed types mirror their owned
+counterparts and can be converted to them with `into_owned`. See [`crate::parse_borrowed`].
+!*/
+
+use std::borrow::Cow;
+use std::hash::{Hash, Hasher};
+
+use crate::{Declaration, DocTypeDecl};
+
+/// A document that borrows its names and text from the string that it was parsed from. See
+/// [`crate::Document`] for the owned equivalent.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Default)]
+pub struct Document<'a> {
+    declaration: Option<Declaration>,
+    misc_before_doctype: Vec<Misc<'a>>,
+    doctype: Option<DocTypeDecl>,
+    misc_after_doctype: Vec<Misc<'a>>,
+    root: Element<'a>,
+    epilog_misc: Vec<Misc<'a>>,
+}
+
+impl<'a> Document<'a> {
+    /// Get the root `Element`.
+    pub fn root(&self) -> &Element<'a> {
+        &self.root
+    }
+
+    /// Get the `Declaration` object, or `None` if the document does not have an XML declaration.
+    pub fn declaration(&self) -> Option<&Declaration> {
+        self.declaration.as_ref()
+    }
+
+    /// Get the doctype declaration, i.e. `<!DOCTYPE ...>`, if there is one.
+    pub fn doctype(&self) -> Option<&DocTypeDecl> {
+        self.doctype.as_ref()
+    }
+
+    /// Access the `Misc` entries before the root element.
+    pub fn prolog_misc(&self) -> impl Iterator<Item = &Misc<'a>> + '_ {
+        self.misc_before_doctype
+            .iter()
+            .chain(self.misc_after_doctype.iter())
+    }
+
+    /// Access the `Misc` entries after the root element.
+    pub fn epilog_misc(&self) -> std::slice::Iter<'_, Misc<'a>> {
+        self.epilog_misc.iter()
+    }
+
+    /// Copy the borrowed strings into an owned [`crate::Document`].
+    pub fn into_owned(self) -> crate::Document {
+        let mut document = crate::Document::from_root(self.root.into_owned());
+        if let Some(declaration) = self.declaration {
+            document.set_declaration(declaration);
+        }
+        for misc in self.misc_before_doctype {
+            document.add_prolog_misc(misc.into_owned());
+        }
+        if let Some(doctype) = self.doctype {
+            document.set_doctype_decl(doctype);
+        }
+        for misc in self.misc_after_doctype {
+            document.add_prolog_misc(misc.into_owned());
+        }
+        for misc in self.epilog_misc {
+            document.add_epilog_misc(misc.into_owned());
+        }
+        document
+    }
+
+    pub(crate) fn set_declaration(&mut self, declaration: Declaration) {
+        self.declaration = Some(declaration);
+    }
+
+    pub(crate) fn set_doctype(&mut self, doctype: DocTypeDecl) {
+        self.doctype = Some(doctype);
+    }
+
+    pub(crate) fn set_root(&mut self, root: Element<'a>) {
+        self.root = root;
+    }
+
+    pub(crate) fn add_prolog_misc(&mut self, misc: Misc<'a>) {
+        if self.doctype.is_none() {
+            self.misc_before_doctype.push(misc);
+        } else {
+            self.misc_after_doctype.push(misc);
+        }
+    }
+
+    pub(crate) fn add_epilog_misc(&mut self, misc: Misc<'a>) {
+        self.epilog_misc.push(misc);
+    }
+}
+
+/// An element that borrows its names and text from the string that it was parsed from. See
+/// [`crate::Element`] for the owned equivalent. Like the owned element, comparisons and hashing do
+/// not depend on the order of the attributes, or on whether they were given their default value.
+#[derive(Debug, Clone)]
+pub struct Element<'a> {
+    /// The name of this element, which may contain a prefix part, such as `ns` in `ns:foo`.
+    name: Cow<'a, str>,
+    /// Attributes of this element, in the order that they were parsed.
+    attributes: Vec<Attribute<'a>>,
+    /// Children of this element.
+    nodes: Vec<Node<'a>>,
+}
+
+#[derive(Debug, Clone)]
+struct Attribute<'a> {
+    key: Cow<'a, str>,
+    value: Cow<'a, str>,
+    /// Whether the attribute was not specified but was given its default value by the DTD.
+    is_defaulted: bool,
+}
+
+impl PartialEq for Element<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.sorted_attributes() == other.sorted_attributes()
+            && self.nodes == other.nodes
+    }
+}
+
+impl Eq for Element<'_> {}
+
+impl Hash for Element<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.sorted_attributes().hash(state);
+        self.nodes.hash(state);
+    }
+}
+
+impl Default for Element<'_> {
+    fn default() -> Self {
+        Self::from_name(Cow::Borrowed("element"))
+    }
+}
+
+impl<'a> Element<'a> {
+    pub(crate) fn from_name(name: Cow<'a, str>) -> Self {
+        Self {
+            name,
+            attributes: Vec::new(),
+            nodes: Vec::new(),
+        }
+    }
+
+    /// The fullname of the element (including both the namespace alias prefix and the name). For
+    /// example, if the name of this element is `ns:foo`, this function returns `"ns:foo"`.
+    pub fn fullname(&self) -> &str {
+        &self.name
+    }
+
+    /// The name of the element without its prefix. For example, if the name of this element is
+    /// `ns:foo`, `name()` will return `foo`.
+    pub fn name(&self) -> &str {
+        match self.name.split_once(':') {
+            Some((_, name)) => name,
+            None => &self.name,
+        }
+    }
+
+    /// The name of the element's namespace alias prefix. For example, if the name of this element
+    /// is `ns:foo`, `prefix()` will return `Some("ns")`.
+    pub fn prefix(&self) -> Option<&str> {
+        self.name.split_once(':').map(|(prefix, _)| prefix)
+    }
+
+    /// Gets the attribute value at `key`. `None` if an attribute by that name does not exist.
+    pub fn attribute<S: AsRef<str>>(&self, key: S) -> Option<&str> {
+        self.find_attribute(key.as_ref())
+            .map(|attribute| attribute.value.as_ref())
+    }
+
+    /// Gets an iterator over the attribute key/value pairs, in the order that they were parsed.
+    /// Attributes that were given their default value by the DTD come last.
+    pub fn attributes(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.attributes
+            .iter()
+            .map(|attribute| (attribute.key.as_ref(), attribute.value.as_ref()))
+    }
+
+    /// Gets the count of attributes.
+    pub fn attributes_len(&self) -> usize {
+        self.attributes.len()
+    }
+
+    /// Returns `true` if the attribute at `key` was not specified in the document, but was given
+    /// its default value by an `<!ATTLIST>` declaration in the DTD.
+    pub fn is_attribute_defaulted<S: AsRef<str>>(&self, key: S) -> bool {
+        self.find_attribute(key.as_ref())
+            .is_some_and(|attribute| attribute.is_defaulted)
+    }
+
+    /// Iterate over the child nodes (of any kind).
+    pub fn nodes(&self) -> std::slice::Iter<'_, Node<'a>> {
+        self.nodes.iter()
+    }
+
+    /// Get the child node (of any kind) at `index`.
+    pub fn node(&self, index: usize) -> Option<&Node<'a>> {
+        self.nodes.get(index)
+    }
+
+    /// Get the number of nodes (of any kind) that are children of this node.
+    pub fn nodes_len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns the 'child' elements of the current element. Text nodes, processing instructions
+    /// and comments are skipped.
+    pub fn children(&self) -> impl Iterator<Item = &Element<'a>> {
+        self.nodes.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+
+    /// Find the first child element with the fullname `name` (does not recurse to lower levels of
+    /// children).
+    pub fn child<S: AsRef<str>>(&self, name: S) -> Option<&Element<'a>> {
+        let name = name.as_ref();
+        self.children().find(|child| child.fullname() == name)
+    }
+
+    /// Returns the contents of the first `Text` or `CData` node encountered in the element. See
+    /// [`crate::Element::text`].
+    pub fn text(&self) -> Option<&str> {
+        for node in &self.nodes {
+            match node {
+                Node::Text(s) | Node::CData(s) => return Some(s),
+                Node::Element(_) => return None,
+                _ => continue,
+            }
+        }
+        None
+    }
+
+    /// Copy the borrowed strings into an owned [`crate::Element`].
+    pub fn into_owned(self) -> crate::Element {
+        let mut element = crate::Element::from_name(self.name.into_owned());
+        for attribute in self.attributes {
+            if attribute.is_defaulted {
+                element.add_defaulted_attribute(attribute.key, attribute.value);
+            } else {
+                element.add_attribute(attribute.key, attribute.value);
+            }
+        }
+        for node in self.nodes {
+            element.add_node(node.into_owned());
+        }
+        element
+    }
+
+    /// The attribute key/value pairs sorted by key, for comparisons and hashing.
+    fn sorted_attributes(&self) -> Vec<(&str, &str)> {
+        let mut attributes: Vec<(&str, &str)> = self.attributes().collect();
+        attributes.sort_unstable();
+        attributes
+    }
+
+    fn find_attribute(&self, key: &str) -> Option<&Attribute<'a>> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.key == key)
+    }
+
+    pub(crate) fn add_attribute(&mut self, key: Cow<'a, str>, value: Cow<'a, str>) {
+        self.attributes.push(Attribute {
+            key,
+            value,
+            is_defaulted: false,
+        });
+    }
+
+    pub(crate) fn add_defaulted_attribute(&mut self, key: &str, value: &str) {
+        self.attributes.push(Attribute {
+            key: Cow::Owned(key.to_owned()),
+            value: Cow::Owned(value.to_owned()),
+            is_defaulted: true,
+        });
+    }
+
+    pub(crate) fn add_node(&mut self, node: Node<'a>) {
+        self.nodes.push(node);
+    }
+
+    pub(crate) fn last_node_mut(&mut self) -> Option<&mut Node<'a>> {
+        self.nodes.last_mut()
+    }
+}
+
+/// A node that borrows its text from the string that it was parsed from. See [`crate::Node`] for
+/// the owned equivalent.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Node<'a> {
+    /// `<![CDATA[text]]>`
+    CData(Cow<'a, str>),
+
+    /// Comment, e.g. `<!--some comment-->`
+    Comment(Cow<'a, str>),
+
+    /// `<element/>`
+    Element(Element<'a>),
+
+    /// Processing Instruction, e.g. `<?target data?>`
+    Pi(Pi<'a>),
+
+    /// Text data in an element, i.e. `<x>hello &lt;</x>` where the `Text` is `hello <`.
+    Text(Cow<'a, str>),
+}
+
+impl Node<'_> {
+    /// Copy the borrowed strings into an owned [`crate::Node`].
+    pub fn into_owned(self) -> crate::Node {
+        match self {
+            Node::CData(cdata) => crate::Node::CData(cdata.into_owned()),
+            Node::Comment(comment) => crate::Node::Comment(comment.into_owned()),
+            Node::Element(element) => crate::Node::Element(element.into_owned()),
+            Node::Pi(pi) => crate::Node::Pi(pi.into_owned()),
+            Node::Text(text) => crate::Node::Text(text.into_owned()),
+        }
+    }
+}
+
+/// A processing instruction that borrows its target and data from the string that it was parsed
+/// from. See [`crate::Pi`] for the owned equivalent.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Pi<'a> {
+    target: Cow<'a, str>,
+    data: Cow<'a, str>,
+}
+
+impl<'a> Pi<'a> {
+    pub(crate) fn new(target: Cow<'a, str>, data: Cow<'a, str>) -> Self {
+        Self { target, data }
+    }
+
+    /// Return the target from a processing instruction, e.g. in `<?foo bar baz?>`, `foo` is the
+    /// target.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Return the data from a processing instruction, e.g. in `<?foo bar baz?>`, `bar baz` is the
+    /// data.
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+
+    /// Copy the borrowed strings into an owned [`crate::Pi`].
+    pub fn into_owned(self) -> crate::Pi {
+        crate::Pi::new_unchecked(self.target.into_owned(), self.data.into_owned())
+    }
+}
+
+/// A comment or processing instruction in the prolog or epilog of a borrowed document. See
+/// [`crate::Misc`] for the owned equivalent.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Misc<'a> {
+    /// `<!-- comment -->`
+    Comment(Cow<'a, str>),
+    /// ProcessingInstruction, e.g. `<?target whatever?>`
+    Pi(Pi<'a>),
+}
+
+impl Misc<'_> {
+    /// Copy the borrowed strings into an owned [`crate::Misc`].
+    pub fn into_owned(self) -> crate::Misc {
+        match self {
+            Misc::Comment(comment) => crate::Misc::Comment(comment.into_owned()),
+            Misc::Pi(pi) => crate::Misc::Pi(pi.into_owned()),
+        }
+    }
+}
@@ -1,25 +1,30 @@
 use std::io::Write;
 
 use super::error::Result;
-
-#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
-pub struct CData(String);
+use crate::error::ErrorKind;
 
 /// Checks if a CData string is valid.
 pub(super) fn check_cdata<S: AsRef<str>>(cdata: S) -> Result<()> {
     if cdata.as_ref().contains("]]>") {
-        return raise!("CDATA string cannot contain ]]>");
+        return raise!(
+            ErrorKind::InvalidDocument,
+            "CDATA string cannot contain ]]>"
+        );
     }
     Ok(())
 }
 
-// writes a cdata string
+// writes a cdata string, which is checked again because nodes can be changed in place.
 pub(super) fn write_cdata<W, S>(cdata: S, writer: &mut W) -> Result<()>
 where
     W: Write,
     S: AsRef<str>,
 {
-    better_wrap!(write!(writer, "<![CDATA[{}]]>", cdata.as_ref()))
+    check_cdata(cdata.as_ref())?;
+    better_wrap!(
+        ErrorKind::Io,
+        write!(writer, "<![CDATA[{}]]>", cdata.as_ref())
+    )
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
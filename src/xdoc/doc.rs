@@ -4,9 +4,11 @@ use std::fmt::{Display, Formatter};
 use std::io::{Cursor, Write};
 use std::path::Path;
 
-use crate::error::OtherError;
+use crate::error::{ErrorKind, OtherError};
+use crate::xdoc::encoding::EncodingWriter;
 use crate::xdoc::error::Result;
-use crate::{Element, Index, Misc, Pi, WriteOpts};
+use crate::xmlns::initial_bindings;
+use crate::{Arena, DocTypeDecl, DtdViolation, Element, Encoding, Misc, NsErr, Pi, WriteOpts};
 
 #[derive(Debug, Default, Clone, Copy, Eq, Ord, PartialOrd, PartialEq, Hash)]
 /// Represents the XML Version being used.
@@ -18,32 +20,28 @@ pub enum Version {
     V11,
 }
 
-#[derive(Debug, Default, Clone, Copy, Eq, Ord, PartialOrd, PartialEq, Hash)]
-/// The encoding of the XML Document, currently only UTF-8 is supported.
-pub enum Encoding {
-    /// The encoding is UTF-8.
-    #[default]
-    Utf8,
-}
-
 #[derive(Debug, Clone, Copy, Eq, Ord, PartialOrd, PartialEq, Hash, Default)]
-/// The XML declaration at the start of the XML Document.
+/// The XML declaration at the start of the XML Document, e.g.
+/// `<?xml version="1.0" encoding="UTF-8" standalone="yes"?>`. A document without a declaration
+/// has no `Declaration` at all, see [`Document::declaration`].
 pub struct Declaration {
-    /// The version of the XML Document. `None` is the same as `Version::V10` except that it is not
-    /// printed in the XML document. That is, XML defaults to 1.0 in the absence of a declaration.
-    pub version: Option<Version>,
+    /// The version of the XML Document.
+    pub version: Version,
     /// The encoding of the XML Document. `None` is the same as `Encoding::Utf8` except that it is
-    /// not printed in the XML document. That is, XML defaults to `UTF-8` in the absence of a
-    /// declaration.
+    /// not printed in the XML declaration.
     pub encoding: Option<Encoding>,
+    /// The standalone document declaration, i.e. `standalone="yes"` is `Some(true)` and
+    /// `standalone="no"` is `Some(false)`. `None` is the same as `Some(false)` except that it is not
+    /// printed in the XML declaration.
+    pub standalone: Option<bool>,
 }
 
 #[derive(Debug, Clone, Eq, PartialOrd, PartialEq, Hash, Default)]
 /// Represents an XML Document.
 pub struct Document {
-    prolog: Prolog,
-    root: Element,
-    epilog_misc: Vec<Misc>,
+    pub(crate) prolog: Prolog,
+    pub(crate) root: Element,
+    pub(crate) epilog_misc: Vec<Misc>,
 }
 
 impl Unpin for Document {}
@@ -53,12 +51,11 @@ impl Unpin for Document {}
 /// [22] prolog ::= XMLDecl? Misc* (doctypedecl Misc*)?
 /// ```
 #[derive(Debug, Clone, Eq, Ord, PartialOrd, PartialEq, Hash, Default)]
-struct Prolog {
-    // TODO xml_decl should be an Option and inside it, Version should not be optional.
-    xml_decl: Declaration,
-    misc_before_doctype: Vec<Misc>,
-    doctypedecl: Option<String>,
-    misc_after_doctype: Vec<Misc>,
+pub(crate) struct Prolog {
+    pub(crate) xml_decl: Option<Declaration>,
+    pub(crate) misc_before_doctype: Vec<Misc>,
+    pub(crate) doctypedecl: Option<DocTypeDecl>,
+    pub(crate) misc_after_doctype: Vec<Misc>,
 }
 
 impl<'a> From<&'a Element> for Cow<'a, Element> {
@@ -105,32 +102,103 @@ impl Document {
         &mut self.root
     }
 
-    /// Get the `Declaration` object.
-    pub fn declaration(&self) -> &Declaration {
-        &self.prolog.xml_decl
+    /// Find the first element with the fullname `name`, in document order, starting with the root.
+    ///
+    /// ```
+    /// let doc = exile::parse("<a><b><c>x</c></b><c>y</c></a>").unwrap();
+    /// assert_eq!("x", doc.find("c").unwrap().text().unwrap());
+    /// assert_eq!(2, doc.find_all("c").count());
+    /// ```
+    pub fn find<S: AsRef<str>>(&self, name: S) -> Option<&Element> {
+        let name = name.as_ref();
+        std::iter::once(&self.root)
+            .chain(self.root.descendants())
+            .find(|element| element.fullname() == name)
+    }
+
+    /// Returns the elements with the fullname `name`, in document order, starting with the root.
+    pub fn find_all<'a, S>(&'a self, name: S) -> impl Iterator<Item = &'a Element> + 'a
+    where
+        S: AsRef<str> + 'a,
+    {
+        std::iter::once(&self.root)
+            .chain(self.root.descendants())
+            .filter(move |element| element.fullname() == name.as_ref())
+    }
+
+    /// Get the `Declaration` object, or `None` if the document does not have an XML declaration.
+    pub fn declaration(&self) -> Option<&Declaration> {
+        self.prolog.xml_decl.as_ref()
     }
 
     /// Set the `Declaration` object for the `Document`.
     pub fn set_declaration(&mut self, declaration: Declaration) {
-        self.prolog.xml_decl = declaration;
+        self.prolog.xml_decl = Some(declaration);
     }
 
-    /// Set the doctype declaration. This will go before any prolog comments or processing
-    /// instructions you have added with [`add_prolog_comment`] or [`add_prolog_pi`]. Once this has
-    /// been set, using those functions will place additional comments or processing instructions
-    /// *after* the doctype declaration.
-    #[cfg(feature = "doctype_wip")]
-    pub fn set_doctype<S: Into<String>>(&mut self, doctype: S) -> Result<()> {
-        self.prolog.doctypedecl = Some(doctype.into());
-        Ok(())
+    /// Remove the `Declaration` so that the `Document` is written without an XML declaration.
+    /// Returns the `Declaration` that was removed, if there was one.
+    pub fn remove_declaration(&mut self) -> Option<Declaration> {
+        self.prolog.xml_decl.take()
+    }
+
+    /// Get the doctype declaration, i.e. `<!DOCTYPE ...>`, if there is one.
+    pub fn doctype(&self) -> Option<&DocTypeDecl> {
+        self.prolog.doctypedecl.as_ref()
     }
 
-    /// Disabled doctype setter.
-    #[cfg(not(feature = "doctype_wip"))]
-    pub fn set_doctype<S: Into<String>>(&mut self, _: S) -> Result<()> {
+    /// Set the doctype declaration by parsing `doctype`, which must hold the entire declaration
+    /// from `<!DOCTYPE` through the closing `>`. The doctype is written after the comments and
+    /// processing instructions that were added with [`Self::add_prolog_comment`] or
+    /// [`Self::add_prolog_pi`] before it was set, and before those that are added after it was set.
+    pub fn set_doctype<S: AsRef<str>>(&mut self, doctype: S) -> Result<()> {
+        let doctype = better_wrap!(
+            ErrorKind::InvalidDoctype,
+            doctype.as_ref().parse::<DocTypeDecl>()
+        )?;
+        self.set_doctype_decl(doctype);
         Ok(())
     }
 
+    /// Set the doctype declaration. See [`Self::set_doctype`] for where it is placed in the prolog.
+    pub fn set_doctype_decl(&mut self, doctype: DocTypeDecl) {
+        self.prolog.doctypedecl = Some(doctype);
+    }
+
+    /// Check the document against the element and attribute-list declarations of its DTD. Returns
+    /// every violation of the XML specification's validity constraints that was found, so an empty
+    /// list means that the document is valid. A document without a doctype is never valid.
+    ///
+    /// ```
+    /// let xml = r#"<!DOCTYPE doc [ <!ELEMENT doc (a+)> <!ELEMENT a EMPTY> ]><doc><b/></doc>"#;
+    /// let doc = exile::parse(xml).unwrap();
+    /// let violations = doc.validate_dtd();
+    /// assert_eq!(2, violations.len());
+    /// assert_eq!("/doc", violations[0].path());
+    /// assert_eq!("/doc/b", violations[1].path());
+    /// ```
+    pub fn validate_dtd(&self) -> Vec<DtdViolation> {
+        crate::xdoc::validate::validate(self)
+    }
+
+    /// Resolve the namespace prefixes of every element and attribute to the URIs that they are
+    /// bound to by `xmlns` attributes, after which [`Element::namespace_uri`],
+    /// [`Element::expanded_name`] and [`Element::attribute_ns`] can be used. Returns an error if a
+    /// prefix is used where it is not bound, or if a namespace declaration is not allowed.
+    ///
+    /// ```
+    /// let xml = r#"<a:doc xmlns:a="http://a" xmlns="http://default"><b a:id="1"/></a:doc>"#;
+    /// let mut doc = exile::parse(xml).unwrap();
+    /// doc.resolve_namespaces().unwrap();
+    /// assert_eq!(Some("http://a"), doc.root().namespace_uri());
+    /// let b = doc.root().child("b").unwrap();
+    /// assert_eq!("{http://default}b", b.expanded_name().to_string());
+    /// assert_eq!("1", b.attribute_ns("http://a", "id").unwrap());
+    /// ```
+    pub fn resolve_namespaces(&mut self) -> std::result::Result<(), NsErr> {
+        self.root.resolve_namespaces(&initial_bindings())
+    }
+
     /// Add a comment before the document root element.
     pub fn add_prolog_comment<S: Into<String>>(&mut self, comment: S) -> Result<()> {
         // TODO check for --
@@ -155,6 +223,15 @@ impl Document {
         }
     }
 
+    /// Add a comment or processing instruction before the document root element.
+    pub(crate) fn add_prolog_misc(&mut self, misc: Misc) {
+        if self.prolog.doctypedecl.is_none() {
+            self.prolog.misc_before_doctype.push(misc);
+        } else {
+            self.prolog.misc_after_doctype.push(misc);
+        }
+    }
+
     /// Remove all [`PI`] and comment entries before the root element.
     pub fn clear_prolog_misc(&mut self) {
         self.prolog.misc_before_doctype.clear();
@@ -181,6 +258,11 @@ impl Document {
         self.epilog_misc.push(Misc::Pi(pi));
     }
 
+    /// Add a comment or processing instruction after the document root element.
+    pub(crate) fn add_epilog_misc(&mut self, misc: Misc) {
+        self.epilog_misc.push(misc);
+    }
+
     /// Clear all `Misc` entries after the root element.
     pub fn clear_epilog_misc(&mut self) {
         self.epilog_misc.clear()
@@ -200,37 +282,63 @@ impl Document {
             .map_err(crate::error::Error::XdocErr)
     }
 
-    /// Write the `Document` to the `Write` object using the given options.
+    /// Write the `Document` to the `Write` object using the given options. The output is encoded
+    /// in the encoding given by the `Declaration`, and it is an `ErrorKind::Encoding` error if the
+    /// document contains a character that can not be represented in that encoding.
     pub fn write_opts<W>(&self, writer: &mut W, opts: &WriteOpts) -> Result<()>
     where
         W: Write,
     {
-        if self.declaration().encoding.is_some() || self.declaration().version.is_some() {
-            xwrite!(writer, "<?xml ")?;
-            let need_space = true;
-            if let Some(version) = &self.declaration().version {
-                match version {
-                    Version::V10 => {
-                        xwrite!(writer, "version=\"1.0\"")?;
-                    }
-                    Version::V11 => {
-                        xwrite!(writer, "version=\"1.1\"")?;
-                    }
-                }
-            }
-            if let Some(encoding) = &self.declaration().encoding {
-                match encoding {
-                    Encoding::Utf8 => {
-                        if need_space {
-                            xwrite!(writer, " ")?;
+        match self
+            .declaration()
+            .and_then(|declaration| declaration.encoding)
+        {
+            None | Some(Encoding::Utf8) => self.write_utf8(writer, opts),
+            Some(encoding) => {
+                let mut encoding_writer =
+                    better_wrap!(ErrorKind::Encoding, EncodingWriter::new(writer, encoding))?;
+                self.write_utf8(&mut encoding_writer, opts)
+                    .map_err(|mut e| {
+                        if encoding_writer.is_unencodable() {
+                            e.kind = ErrorKind::Encoding;
                         }
-                        xwrite!(writer, "encoding=\"UTF-8\"")?
-                    }
+                        e
+                    })
+            }
+        }
+    }
+
+    /// Write the `Document` as UTF-8, regardless of the encoding given by the `Declaration`.
+    fn write_utf8<W>(&self, writer: &mut W, opts: &WriteOpts) -> Result<()>
+    where
+        W: Write,
+    {
+        let opts = &WriteOpts {
+            version: self
+                .declaration()
+                .map(|declaration| declaration.version)
+                .unwrap_or_default(),
+            ..opts.clone()
+        };
+        if let Some(declaration) = self.declaration() {
+            match declaration.version {
+                Version::V10 => {
+                    xwrite!(writer, "<?xml version=\"1.0\"")?;
                 }
+                Version::V11 => {
+                    xwrite!(writer, "<?xml version=\"1.1\"")?;
+                }
+            }
+            if let Some(encoding) = &declaration.encoding {
+                xwrite!(writer, " encoding=\"{}\"", encoding.label())?
+            }
+            if let Some(standalone) = declaration.standalone {
+                let standalone = if standalone { "yes" } else { "no" };
+                xwrite!(writer, " standalone=\"{}\"", standalone)?
             }
             xwrite!(writer, "?>")?;
             if let Err(e) = opts.newline(writer) {
-                return wrap_err!(e);
+                return wrap_err!(ErrorKind::Io, e);
             }
         }
         for misc in &self.prolog.misc_before_doctype {
@@ -254,21 +362,25 @@ impl Document {
         Ok(())
     }
 
-    /// Write the `Document` to a `String` using the given options.
+    /// Write the `Document` to a `String` using the given options. A `String` is always UTF-8, so
+    /// the encoding given by the `Declaration` is only written in the declaration.
     pub fn to_string_opts(&self, opts: &WriteOpts) -> Result<String> {
         let mut c = Cursor::new(Vec::new());
-        self.write_opts(&mut c, opts)?;
+        self.write_utf8(&mut c, opts)?;
         let data = c.into_inner();
         match std::str::from_utf8(data.as_slice()) {
             Ok(s) => Ok(s.to_owned()),
-            Err(e) => wrap_err!(e),
+            Err(e) => wrap_err!(ErrorKind::Internal, e),
         }
     }
 
-    /// Save a document to a file.
+    /// Save a document to a file, encoded in the encoding given by the `Declaration`.
     pub fn save<P: AsRef<Path>>(&self, path: P) -> crate::error::Result<()> {
-        std::fs::write(path.as_ref(), self.to_string().as_bytes()).map_err(|e| {
+        let mut data = Vec::new();
+        self.write(&mut data)?;
+        std::fs::write(path.as_ref(), data).map_err(|e| {
             crate::error::Error::Other(OtherError {
+                kind: ErrorKind::Io,
                 throw_site: throw_site!(),
                 message: Some(format!("Unable to save file '{}'", path.as_ref().display())),
                 source: Some(Box::new(e)),
@@ -276,9 +388,10 @@ impl Document {
         })
     }
 
-    /// Create an index of the elements in this document. Takes ownership of `document`.
-    pub fn index(self) -> Index {
-        Index::build(self)
+    /// Convert the document into an [`Arena`], in which every node has a [`crate::NodeId`] and
+    /// links to its parent, children and siblings.
+    pub fn into_arena(self) -> Arena {
+        Arena::from(self)
     }
 }
 
@@ -355,8 +468,9 @@ mod tests {
         cats.add_child(bishop);
         let mut doc = Document::from_root(cats);
         doc.set_declaration(Declaration {
-            version: Some(Version::V10),
+            version: Version::V10,
             encoding: Some(Encoding::Utf8),
+            standalone: None,
         });
         doc
     }
@@ -395,4 +509,87 @@ mod tests {
         let data_str = std::str::from_utf8(data.as_slice()).unwrap();
         assert_eq!(expected, data_str);
     }
+
+    #[test]
+    fn test_write_encoded() {
+        let mut doc = create_ezfile();
+        doc.root_mut().add_attribute("name", "café");
+        doc.set_declaration(Declaration {
+            version: Version::V10,
+            encoding: Some(Encoding::Iso8859_1),
+            standalone: None,
+        });
+        let mut data = Vec::new();
+        doc.write(&mut data).unwrap();
+        assert!(data.starts_with(br#"<?xml version="1.0" encoding="ISO-8859-1"?>"#));
+        assert!(data.windows(5).any(|w| w == b"caf\xE9\"".as_slice()));
+        assert!(doc.to_string().contains("café"));
+        assert_eq!(doc, crate::from_reader(data.as_slice()).unwrap());
+    }
+
+    #[test]
+    fn test_write_encoded_utf16() {
+        let mut doc = create_ezfile();
+        doc.set_declaration(Declaration {
+            version: Version::V10,
+            encoding: Some(Encoding::Utf16Le),
+            standalone: None,
+        });
+        let mut data = Vec::new();
+        doc.write(&mut data).unwrap();
+        assert_eq!(&[0xFF, 0xFE, b'<', 0x00], &data[..4]);
+        assert_eq!(doc, crate::from_reader(data.as_slice()).unwrap());
+    }
+
+    #[test]
+    fn test_write_unrepresentable() {
+        let mut doc = create_ezfile();
+        doc.root_mut().add_text("🍔");
+        doc.set_declaration(Declaration {
+            version: Version::V10,
+            encoding: Some(Encoding::Windows1252),
+            standalone: None,
+        });
+        let e = doc.write(&mut Vec::new()).unwrap_err();
+        assert_eq!(ErrorKind::Encoding, e.kind());
+
+        let mut doc = create_ezfile();
+        doc.root_mut().add_attribute("price", "10€");
+        doc.set_declaration(Declaration {
+            version: Version::V10,
+            encoding: Some(Encoding::Iso8859_1),
+            standalone: None,
+        });
+        let e = doc.write(&mut Vec::new()).unwrap_err();
+        assert_eq!(ErrorKind::Encoding, e.kind());
+    }
+
+    #[test]
+    fn test_doctype_round_trip() {
+        let xml = "<!DOCTYPE r [\n<!ELEMENT r (#PCDATA)>\n<!ENTITY e 'x'>\n]>\n<r/>\n";
+        let doc = crate::parse(xml).unwrap();
+        assert_eq!("r", doc.doctype().unwrap().name());
+        assert_eq!(2, doc.doctype().unwrap().markup_decls().count());
+        assert_eq!(xml, doc.to_string());
+    }
+
+    #[test]
+    fn test_set_doctype_order() {
+        let mut doc = Document::from_root(Element::from_name("r"));
+        doc.add_prolog_comment("before").unwrap();
+        doc.set_doctype("<!DOCTYPE r>").unwrap();
+        doc.add_prolog_comment("after").unwrap();
+        let xml = doc.to_string();
+        let before = xml.find("<!--before-->").unwrap();
+        let doctype = xml.find("<!DOCTYPE r>").unwrap();
+        let after = xml.find("<!--after-->").unwrap();
+        assert!(before < doctype && doctype < after, "{}", xml);
+    }
+
+    #[test]
+    fn test_set_doctype_bad() {
+        let mut doc = create_ezfile();
+        assert!(doc.set_doctype("<!DOCTYPE r [").is_err());
+        assert!(doc.doctype().is_none());
+    }
 }
@@ -0,0 +1,1445 @@
+/*!
+The `doctype` module models the document type declaration, i.e. `<!DOCTYPE ...>`, and the markup
+declarations of its internal subset. The model is lossless: whitespace and quotation marks are
+retained so that a parsed declaration is written exactly as it was found.
+!*/
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use crate::constants::{CARRIAGE_RETURN, NEWLINE, SPACE};
+use crate::error::ErrorKind;
+use crate::xdoc::error::Result;
+use crate::Pi;
+
+/// https://www.w3.org/TR/xml/#NT-doctypedecl
+/// ```text
+/// [28] doctypedecl ::= '<!DOCTYPE' S Name (S ExternalID)? S? ('[' intSubset ']' S?)? '>'
+/// ```
+///
+/// # Example
+///
+/// ```
+/// use exile::DocTypeDecl;
+/// let doctype: DocTypeDecl = r#"<!DOCTYPE doc [ <!ELEMENT doc (#PCDATA)> ]>"#.parse().unwrap();
+/// assert_eq!("doc", doctype.name());
+/// assert_eq!(1, doctype.markup_decls().count());
+/// assert_eq!(r#"<!DOCTYPE doc [ <!ELEMENT doc (#PCDATA)> ]>"#, doctype.to_string());
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct DocTypeDecl {
+    pub(crate) space_before_name: Whitespace,
+    pub(crate) name: DocTypeName,
+    pub(crate) external_id: Option<(Whitespace, ExternalID)>,
+    pub(crate) space_before_int_subset: Option<Whitespace>,
+    pub(crate) int_subsets: Option<(IntSubsets, Option<Whitespace>)>,
+}
+
+impl DocTypeDecl {
+    /// The name of the document type, which must match the name of the root element.
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// The `SYSTEM` or `PUBLIC` identifier of the external subset, if there is one.
+    pub fn external_id(&self) -> Option<&ExternalID> {
+        self.external_id.as_ref().map(|(_, id)| id)
+    }
+
+    /// The markup declarations of the internal subset, in document order.
+    pub fn markup_decls(&self) -> impl Iterator<Item = &MarkupDeclValue> + '_ {
+        self.int_subsets
+            .iter()
+            .flat_map(|(subsets, _)| subsets.0.iter())
+            .filter_map(|item| match item {
+                IntSubset::MarkupDecl(decl) => Some(decl.as_ref()),
+                IntSubset::DeclSep(_) => None,
+            })
+    }
+}
+
+impl FromStr for DocTypeDecl {
+    type Err = crate::error::Error;
+
+    fn from_str(s: &str) -> crate::error::Result<Self> {
+        crate::parser::parse_doctype_str(s)
+    }
+}
+
+pub(crate) const STR_DOCTYPE: &str = "DOCTYPE";
+
+/// Same as any name except without namespace alias prefixes.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct DocTypeName {
+    pub(crate) name: String,
+}
+
+impl DocTypeName {
+    /// The name as a string.
+    pub fn as_str(&self) -> &str {
+        self.name.as_str()
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub(crate) struct Whitespace {
+    pub(crate) inner: Vec<Space>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[allow(clippy::enum_variant_names)]
+pub(crate) enum Space {
+    /// `#x20` or `' '`
+    Space,
+    /// `#x9` or `'\t'`
+    Tab,
+    /// `#xD` or `'\r'`
+    CarriageReturn,
+    /// `#0A` pr `'\n'`
+    Newline,
+}
+
+pub(crate) const STR_SYSTEM: &str = "SYSTEM";
+pub(crate) const STR_PUBLIC: &str = "PUBLIC";
+
+/// ExternalID ::= 'SYSTEM' S SystemLiteral
+///                | 'PUBLIC' S PubidLiteral S SystemLiteral
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ExternalID {
+    /// `SYSTEM "uri"`
+    System(SystemExternalID),
+    /// `PUBLIC "public id" "uri"`
+    Public(PublicExternalID),
+}
+
+impl ExternalID {
+    /// The system literal, which is usually the URI of a DTD.
+    pub fn system_literal(&self) -> &str {
+        match self {
+            ExternalID::System(id) => id.system_literal.value.as_str(),
+            ExternalID::Public(id) => id.system_literal.value.as_str(),
+        }
+    }
+
+    /// The public identifier literal, if this is a `PUBLIC` identifier.
+    pub fn public_id_literal(&self) -> Option<&str> {
+        match self {
+            ExternalID::System(_) => None,
+            ExternalID::Public(id) => Some(id.pub_id_literal.value.as_str()),
+        }
+    }
+}
+
+/// S SystemLiteral
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct SystemExternalID {
+    pub(crate) space_before_literal: Whitespace,
+    pub(crate) system_literal: SystemLiteral,
+}
+
+/// S PubidLiteral S SystemLiteral
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct PublicExternalID {
+    pub(crate) space_before_pub_id: Whitespace,
+    pub(crate) pub_id_literal: PubIDLiteral,
+    pub(crate) space_after_pub_id: Whitespace,
+    pub(crate) system_literal: SystemLiteral,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub(crate) enum Quote {
+    /// Something that is quoted with single ('tick') quotation marks: `'`.
+    Single,
+    /// Something that is quoted with double quotation marks: `"`.
+    Double,
+}
+
+impl Quote {
+    pub(crate) fn new(c: char) -> Result<Self> {
+        match c {
+            '\'' => Ok(Quote::Single),
+            '"' => Ok(Quote::Double),
+            _ => raise!(
+                ErrorKind::InvalidDocument,
+                "expected either single or double quote character"
+            ),
+        }
+    }
+
+    pub(crate) fn char(&self) -> char {
+        match self {
+            Quote::Single => '\'',
+            Quote::Double => '"',
+        }
+    }
+}
+
+/// > SystemLiteral ::= ('"' [^"]* '"') | ("'" [^']* "'")
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct SystemLiteral {
+    pub(crate) quote: Quote,
+    pub(crate) value: String,
+}
+
+/// https://www.w3.org/TR/xml/#NT-PubidLiteral
+/// ```text
+/// PubidLiteral ::= '"' PubidChar* '"' | "'" (PubidChar - "'")* "'"
+/// PubidChar ::= #x20 | #xD | #xA | [a-zA-Z0-9] | [-'()+,./:=?;!*#@$_%]
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct PubIDLiteral {
+    pub(crate) quote: Quote,
+    pub(crate) value: String,
+}
+
+impl PubIDLiteral {
+    pub(crate) fn forbidden(c: char, q: Quote) -> bool {
+        if q == Quote::Single && c == '\'' {
+            return true;
+        }
+        !matches!(c,
+           SPACE |
+           CARRIAGE_RETURN |
+           NEWLINE |
+           'a'..='z' |
+           'A'..='Z' |
+           '0'..='9' |
+           '-' |
+           '\'' |
+           '(' |
+           ')' |
+           '+' |
+           ',' |
+           '.' |
+           '/' |
+           ':' |
+           '=' |
+           '?' |
+           ';' |
+           '!' |
+           '*' |
+           '#' |
+           '@' |
+           '$' |
+           '_' |
+           '%'
+        )
+    }
+}
+
+/// https://www.w3.org/TR/xml/#NT-intSubset
+/// ```text
+/// intSubset ::= (markupdecl | DeclSep)*
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub(crate) struct IntSubsets(pub(crate) Vec<IntSubset>);
+
+impl IntSubsets {
+    pub(crate) fn new(items: Vec<IntSubset>) -> Self {
+        Self(items)
+    }
+}
+
+/// https://www.w3.org/TR/xml/#NT-intSubset
+/// ```text
+/// intSubset ::= (markupdecl | DeclSep)*
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub(crate) enum IntSubset {
+    MarkupDecl(Box<MarkupDeclValue>),
+    DeclSep(DeclSep), // TODO reused name
+}
+
+/// > DeclSep ::= PEReference | S
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub(crate) enum DeclSep {
+    PEReference(PEReferenceValue),
+    Space(Whitespace),
+}
+
+/// https://www.w3.org/TR/xml/#NT-EntityRef
+/// ```text
+/// [68] EntityRef ::= '&' Name ';'
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ReferenceValue {
+    pub(crate) value: DocTypeName,
+}
+
+impl ReferenceValue {
+    /// The name of the referenced entity.
+    pub fn name(&self) -> &str {
+        self.value.as_str()
+    }
+}
+
+/// https://www.w3.org/TR/xml/#NT-PEReference
+/// ```text
+/// [69] PEReference ::= '%' Name ';'
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct PEReferenceValue {
+    pub(crate) value: DocTypeName,
+}
+
+impl PEReferenceValue {
+    /// The name of the referenced parameter entity.
+    pub fn name(&self) -> &str {
+        self.value.as_str()
+    }
+}
+
+pub(crate) const STR_ELEMENT: &str = "ELEMENT";
+pub(crate) const STR_ATTLIST: &str = "ATTLIST";
+
+/// https://www.w3.org/TR/xml/#NT-markupdecl
+/// ```text
+/// markupdecl ::= elementdecl | AttlistDecl | EntityDecl | NotationDecl | PI | Comment
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum MarkupDeclValue {
+    /// `<!ELEMENT ...>`
+    ElementDecl(ElementDeclValue),
+    /// `<!ATTLIST ...>`
+    AttlistDecl(AttlistDeclValue),
+    /// `<!ENTITY ...>`
+    EntityDecl(EntityDeclValue),
+    /// `<!NOTATION ...>`
+    NotationDecl(NotationDeclValue),
+    /// Processing Instruction, e.g. `<?target data?>`
+    PI(Pi),
+    /// Comment, e.g. `<!--some comment-->`
+    Comment(String),
+}
+
+/// https://www.w3.org/TR/xml/#NT-elementdecl
+/// ```text
+/// elementdecl ::= '<!ELEMENT' S Name S contentspec S? '>'
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ElementDeclValue {
+    pub(crate) space_before_name: Whitespace,
+    pub(crate) name: DocTypeName,
+    pub(crate) space_after_name: Whitespace,
+    pub(crate) content_spec: ContentSpec,
+    pub(crate) space_after_content_spec: Option<Whitespace>,
+}
+
+impl ElementDeclValue {
+    /// The name of the element being declared.
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// The content that the element is allowed to have.
+    pub fn content_spec(&self) -> &ContentSpec {
+        &self.content_spec
+    }
+}
+
+pub(crate) const STR_EMPTY: &str = "EMPTY";
+pub(crate) const STR_ANY: &str = "ANY";
+
+/// https://www.w3.org/TR/xml/#NT-contentspec
+/// ```text
+/// [46] contentspec ::= 'EMPTY' | 'ANY' | Mixed | children
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ContentSpec {
+    /// `EMPTY`: the element may not have content.
+    Empty,
+    /// `ANY`: the element may have any content.
+    Any,
+    /// Text, optionally mixed with the named elements, e.g. `(#PCDATA|a|b)*`.
+    Mixed(MixedValue),
+    /// Only elements, as described by a content model, e.g. `(a,(b|c)*)`.
+    Children(ChildrenValue),
+}
+
+/// ```text
+/// [7] Nmtoken ::= (NameChar)+
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct NmToken {
+    pub(crate) value: String,
+}
+
+impl NmToken {
+    /// The token as a string.
+    pub fn as_str(&self) -> &str {
+        self.value.as_str()
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub(crate) struct DelimitedListItem<T> {
+    pub(crate) space_before_delimiter: Option<Whitespace>,
+    pub(crate) space_after_delimiter: Option<Whitespace>,
+    pub(crate) item: T,
+}
+
+pub(crate) const STR_PCDATA: &str = "#PCDATA";
+
+/// > Mixed ::= '(' S? '#PCDATA' (S? '|' S? Name)* S? ')*'
+/// >           | '(' S? '#PCDATA' S? ')'
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct MixedValue {
+    pub(crate) space_after_open_parenthesis: Option<Whitespace>,
+    pub(crate) element_names: Vec<DelimitedListItem<DocTypeName>>,
+    pub(crate) space_before_close_parenthesis: Option<Whitespace>,
+    /// Whether the closing parenthesis is followed by `*`, which is required when there are
+    /// element names.
+    pub(crate) zero_or_more: bool,
+}
+
+impl MixedValue {
+    /// The names of the elements that may be mixed with text.
+    pub fn element_names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.element_names.iter().map(|item| item.item.as_str())
+    }
+}
+
+/// https://www.w3.org/TR/xml/#NT-children
+/// ```text
+/// [47] children ::= (choice | seq) ('?' | '*' | '+')?
+/// [48] cp ::= (Name | choice | seq) ('?' | '*' | '+')?
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Repetitions {
+    /// Something may appear once, i.e. it is suffixed with `?`.
+    Optional,
+    /// Something must appear exactly once, i.e. it does not have a `?`, `*`, or `*`.
+    Once,
+    /// Something may appear any number of times, or not at all, i.e. it is suffixed with `*`.
+    ZeroOrMore,
+    /// Something may appear once or more than once, i.e. it is suffixed with `+`.
+    OneOrMore,
+}
+
+/// https://www.w3.org/TR/xml/#NT-children
+/// ```text
+/// [47] children ::= (choice | seq) ('?' | '*' | '+')?
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ChildrenType {
+    /// A list of alternatives, e.g. `(a|b)`.
+    Choice(ChoiceValue),
+    /// A sequence, e.g. `(a,b)`.
+    Seq(SeqValue),
+}
+
+/// https://www.w3.org/TR/xml/#NT-children
+/// ```text
+///  [47] children ::= (choice | seq) ('?' | '*' | '+')?
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ChildrenValue {
+    pub(crate) children_type: ChildrenType,
+    pub(crate) repetitions: Repetitions,
+}
+
+impl ChildrenValue {
+    /// Whether the content model is a choice or a sequence.
+    pub fn children_type(&self) -> &ChildrenType {
+        &self.children_type
+    }
+
+    /// How many times the content model may be repeated.
+    pub fn repetitions(&self) -> Repetitions {
+        self.repetitions
+    }
+}
+
+/// https://www.w3.org/TR/xml/#NT-cp
+/// ```text
+/// [48] cp ::= (Name | choice | seq) ('?' | '*' | '+')?
+/// ```
+/// This struct represents just the the part of the above represented by
+/// `(Name | choice | seq)`. That is the variant without the number of
+/// repetitions. [`CpValue`] represents the entire `cp` construct, including the
+/// number of repetitions.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum CpItem {
+    /// The name of an element.
+    Name(DocTypeName),
+    /// A nested list of alternatives.
+    Choice(ChoiceValue),
+    /// A nested sequence.
+    Seq(SeqValue),
+}
+
+/// https://www.w3.org/TR/xml/#NT-cp
+/// ```text
+/// [48] cp ::= (Name | choice | seq) ('?' | '*' | '+')?
+/// ```
+/// This struct represents the entire `cp`, that is is has either a `Name`, `choice` or `seq` as
+/// well as the number of repetions. [`CpItem`] represents just the `Name`, `choice` or `seq`
+/// without the number of repetitions.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct CpValue {
+    pub(crate) cp_item: CpItem,
+    pub(crate) repetitions: Repetitions,
+}
+
+impl CpValue {
+    /// The element name, choice or sequence.
+    pub fn cp_item(&self) -> &CpItem {
+        &self.cp_item
+    }
+
+    /// How many times the item may be repeated.
+    pub fn repetitions(&self) -> Repetitions {
+        self.repetitions
+    }
+}
+
+/// https://www.w3.org/TR/xml/#NT-choice
+/// ```text
+/// [49] choice ::= '(' S? cp ( S? '|' S? cp )+ S? ')'
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ChoiceValue {
+    pub(crate) cps: Vec<DelimitedListItem<CpValue>>,
+    pub(crate) space_before_close: Option<Whitespace>,
+}
+
+impl ChoiceValue {
+    /// The alternatives.
+    pub fn cps(&self) -> impl Iterator<Item = &CpValue> + '_ {
+        self.cps.iter().map(|item| &item.item)
+    }
+}
+
+/// https://www.w3.org/TR/xml/#NT-seq
+/// ```text
+/// [50] seq    ::= '(' S? cp ( S? ',' S? cp )* S? ')'
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct SeqValue {
+    pub(crate) cps: Vec<DelimitedListItem<CpValue>>,
+    pub(crate) space_before_close: Option<Whitespace>,
+}
+
+impl SeqValue {
+    /// The members of the sequence, in order.
+    pub fn cps(&self) -> impl Iterator<Item = &CpValue> + '_ {
+        self.cps.iter().map(|item| &item.item)
+    }
+}
+
+/// https://www.w3.org/TR/xml/#NT-AttlistDecl
+/// AttlistDecl ::= '<!ATTLIST' S Name AttDef* S? '>'
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct AttlistDeclValue {
+    pub(crate) space_before_name: Whitespace,
+    pub(crate) name: DocTypeName,
+    pub(crate) att_defs: Vec<AttDef>,
+    pub(crate) space_before_close: Option<Whitespace>,
+}
+
+impl AttlistDeclValue {
+    /// The name of the element that the attributes belong to.
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// The attribute definitions.
+    pub fn att_defs(&self) -> std::slice::Iter<'_, AttDef> {
+        self.att_defs.iter()
+    }
+}
+
+/// https://www.w3.org/TR/xml/#NT-AttDef
+/// AttDef ::= S Name S AttType S DefaultDecl
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct AttDef {
+    pub(crate) space_before_name: Whitespace,
+    pub(crate) name: DocTypeName,
+    pub(crate) space_before_att_type: Whitespace,
+    pub(crate) att_type: AttType,
+    pub(crate) space_before_default_decl: Whitespace,
+    pub(crate) default_decl: DefaultDecl,
+}
+
+impl AttDef {
+    /// The name of the attribute.
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// The type of the attribute.
+    pub fn att_type(&self) -> &AttType {
+        &self.att_type
+    }
+
+    /// Whether the attribute is required, implied or has a default value.
+    pub fn default_decl(&self) -> &DefaultDecl {
+        &self.default_decl
+    }
+}
+
+/// https://www.w3.org/TR/xml/#NT-AttType
+/// StringType | TokenizedType | EnumeratedType
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum AttType {
+    /// `CDATA`
+    CData,
+    /// `ID`
+    ID,
+    /// `IDREF`
+    IDRef,
+    /// `IDREFS`
+    IDRefs,
+    /// `ENTITY`
+    Entity,
+    /// `ENTITIES`
+    Entities,
+    /// `NMTOKEN`
+    NMToken,
+    /// `NMTOKENS`
+    NMTokens,
+    /// `NOTATION (a|b)` or `(a|b)`
+    EnumeratedTypes(EnumeratedType),
+}
+
+pub(crate) const STR_CDATA: &str = "CDATA";
+pub(crate) const STR_ID: &str = "ID";
+pub(crate) const STR_IDREF: &str = "IDREF";
+pub(crate) const STR_IDREFS: &str = "IDREFS";
+pub(crate) const STR_ENTITY: &str = "ENTITY";
+pub(crate) const STR_ENTITIES: &str = "ENTITIES";
+pub(crate) const STR_NMTOKEN: &str = "NMTOKEN";
+pub(crate) const STR_NMTOKENS: &str = "NMTOKENS";
+
+/// https://www.w3.org/TR/xml/#NT-EnumeratedType
+/// ```text
+/// [57] EnumeratedType ::= NotationType | Enumeration
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum EnumeratedType {
+    /// `NOTATION (a|b)`
+    NotationType(NotationTypeValue),
+    /// `(a|b)`
+    Enumeration(EnumerationValue),
+}
+
+/// https://www.w3.org/TR/xml/#NT-NotationType
+/// NotationType ::= 'NOTATION' S '(' S? Name (S? '|' S? Name)* S? ')'
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct NotationTypeValue {
+    pub(crate) space_before_open: Whitespace,
+    pub(crate) names: Vec<DelimitedListItem<DocTypeName>>,
+    pub(crate) space_before_close: Option<Whitespace>,
+}
+
+impl NotationTypeValue {
+    /// The names of the allowed notations.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.names.iter().map(|item| item.item.as_str())
+    }
+}
+
+/// https://www.w3.org/TR/xml/#NT-Enumeration
+/// Enumeration ::= '(' S? Nmtoken (S? '|' S? Nmtoken)* S? ')'
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct EnumerationValue {
+    pub(crate) names: Vec<DelimitedListItem<NmToken>>,
+    pub(crate) space_before_close: Option<Whitespace>,
+}
+
+impl EnumerationValue {
+    /// The allowed values.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.names.iter().map(|item| item.item.as_str())
+    }
+}
+
+/// https://www.w3.org/TR/xml/#NT-EntityDecl
+/// EntityDecl ::=  GEDecl | PEDecl
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum EntityDeclValue {
+    /// A general entity, i.e. one that is referenced in the document as `&name;`.
+    GEDecl(GEDeclValue),
+    /// A parameter entity, i.e. one that is referenced in the DTD as `%name;`.
+    PEDecl(PEDeclValue),
+}
+
+/// https://www.w3.org/TR/xml/#NT-GEDecl
+/// ```text
+/// GEDecl ::= '<!ENTITY' S Name S EntityDef S? '>'
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct GEDeclValue {
+    pub(crate) space_before_name: Whitespace,
+    pub(crate) name: DocTypeName,
+    pub(crate) space_before_entity_def: Whitespace,
+    pub(crate) entity_def: EntityDef,
+    pub(crate) space_before_close: Option<Whitespace>,
+}
+
+impl GEDeclValue {
+    /// The name of the entity.
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// The value or external identifier of the entity.
+    pub fn entity_def(&self) -> &EntityDef {
+        &self.entity_def
+    }
+}
+
+/// https://www.w3.org/TR/xml/#NT-PEDecl
+/// ```text
+/// PEDecl ::=  '<!ENTITY' S '%' S Name S PEDef S? '>'
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct PEDeclValue {
+    pub(crate) space_before_percent: Whitespace,
+    pub(crate) space_before_name: Whitespace,
+    pub(crate) name: DocTypeName,
+    pub(crate) space_before_pe_def: Whitespace,
+    pub(crate) pe_def: PEDef,
+    pub(crate) space_before_close: Option<Whitespace>,
+}
+
+impl PEDeclValue {
+    /// The name of the parameter entity.
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// The value or external identifier of the parameter entity.
+    pub fn pe_def(&self) -> &PEDef {
+        &self.pe_def
+    }
+}
+
+/// https://www.w3.org/TR/xml/#NT-EntityDef
+/// EntityDef ::= EntityValue | (ExternalID NDataDecl?)
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum EntityDef {
+    /// An internal entity, e.g. `"value"`.
+    Entity(EntityValue),
+    /// An external entity, e.g. `SYSTEM "uri"`.
+    External(EntityDefExternal),
+}
+
+/// https://www.w3.org/TR/xml/#NT-EntityDef\
+/// (ExternalID NDataDecl?)
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct EntityDefExternal {
+    pub(crate) external_id: ExternalID,
+    pub(crate) ndata_decl: Option<NDataDecl>,
+}
+
+impl EntityDefExternal {
+    /// Where the entity can be found.
+    pub fn external_id(&self) -> &ExternalID {
+        &self.external_id
+    }
+
+    /// The name of the notation of an unparsed entity, i.e. one with `NDATA`.
+    pub fn ndata(&self) -> Option<&str> {
+        self.ndata_decl.as_ref().map(|n| n.name.as_str())
+    }
+}
+
+pub(crate) const STR_NDATA: &str = "NDATA";
+
+/// https://www.w3.org/TR/xml/#NT-NDataDecl
+/// NDataDecl ::= S 'NDATA' S Name
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub(crate) struct NDataDecl {
+    pub(crate) space_before_ndata: Whitespace,
+    pub(crate) space_before_name: Whitespace,
+    pub(crate) name: DocTypeName,
+}
+
+/// https://www.w3.org/TR/xml/#NT-PEDef
+/// PEDef ::= EntityValue | ExternalID
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum PEDef {
+    /// An internal parameter entity, e.g. `"value"`.
+    Entity(EntityValue),
+    /// An external parameter entity, e.g. `SYSTEM "uri"`.
+    External(ExternalID),
+}
+
+/// https://www.w3.org/TR/xml/#NT-EntityValue
+/// ```text
+/// [9] EntityValue ::= '"' ([^%&"] | PEReference | Reference)* '"'
+///                         | "'" ([^%&'] | PEReference | Reference)* "'"
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct EntityValue {
+    pub(crate) quote: Quote,
+    pub(crate) data: Vec<EntityValueData>,
+}
+
+impl EntityValue {
+    /// The text and references that make up the value, in order.
+    pub fn data(&self) -> std::slice::Iter<'_, EntityValueData> {
+        self.data.iter()
+    }
+}
+
+/// https://www.w3.org/TR/xml/#NT-EntityValue
+/// ```text
+/// [9] EntityValue ::= '"' ([^%&"] | PEReference | Reference)* '"'
+///                         | "'" ([^%&'] | PEReference | Reference)* "'"
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum EntityValueData {
+    /// Literal text.
+    String(String),
+    /// A parameter entity reference, e.g. `%name;`.
+    PEReference(PEReferenceValue),
+    /// An entity or character reference, e.g. `&name;` or `&#60;`.
+    Reference(Reference),
+}
+
+/// https://www.w3.org/TR/xml/#NT-Reference
+/// ```text
+/// [67] Reference ::= EntityRef | CharRef
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Reference {
+    /// An entity reference, e.g. `&name;`.
+    EntityRef(ReferenceValue),
+    /// A character reference, e.g. `&#60;`.
+    CharRef(CharRefValue),
+}
+
+/// https://www.w3.org/TR/xml/#NT-CharRef
+/// ```text
+/// [66] CharRef ::= '&#' [0-9]+ ';'
+///                   | '&#x' [0-9a-fA-F]+ ';'
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct CharRefValue {
+    pub(crate) char_ref_type: CharRefValueType,
+    pub(crate) value: u64,
+}
+
+impl CharRefValue {
+    /// The referenced character, or `None` if the value is not a valid character.
+    pub fn char(&self) -> Option<char> {
+        std::convert::TryFrom::try_from(self.value)
+            .ok()
+            .and_then(char::from_u32)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub(crate) enum CharRefValueType {
+    Decimal,
+    Hex,
+}
+
+pub(crate) const STR_NOTATION: &str = "NOTATION";
+
+/// https://www.w3.org/TR/xml/#NT-NotationDecl
+/// ```text
+/// [82] NotationDecl ::= '<!NOTATION' S Name S (ExternalID | PublicID) S? '>'
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct NotationDeclValue {
+    pub(crate) space_before_name: Whitespace,
+    pub(crate) name: DocTypeName,
+    pub(crate) space_before_id: Whitespace,
+    pub(crate) id: ExternalOrPublicID,
+    pub(crate) space_before_close: Option<Whitespace>,
+}
+
+impl NotationDeclValue {
+    /// The name of the notation.
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// The identifier of the notation.
+    pub fn id(&self) -> &ExternalOrPublicID {
+        &self.id
+    }
+}
+
+/// https://www.w3.org/TR/xml/#NT-NotationDecl
+/// ```text
+/// (ExternalID | PublicID)
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ExternalOrPublicID {
+    /// `SYSTEM "uri"` or `PUBLIC "public id" "uri"`
+    External(ExternalID),
+    /// `PUBLIC "public id"`
+    Public(PublicID),
+}
+
+/// https://www.w3.org/TR/xml/#NT-PublicID
+/// ```text
+/// [83] PublicID ::= 'PUBLIC' S PubidLiteral
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct PublicID {
+    pub(crate) space_before_name: Whitespace,
+    pub(crate) public_id_literal: PubIDLiteral,
+}
+
+impl PublicID {
+    /// The public identifier literal.
+    pub fn public_id_literal(&self) -> &str {
+        self.public_id_literal.value.as_str()
+    }
+}
+
+pub(crate) const STR_REQUIRED: &str = "#REQUIRED";
+pub(crate) const STR_IMPLIED: &str = "#IMPLIED";
+pub(crate) const STR_FIXED: &str = "#FIXED";
+
+/// https://www.w3.org/TR/xml/#NT-DefaultDecl
+/// ```text
+/// [60] DefaultDecl ::= '#REQUIRED' | '#IMPLIED'
+///                      | (('#FIXED' S)? AttValue)
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum DefaultDecl {
+    /// `#REQUIRED`
+    Required,
+    /// `#IMPLIED`
+    Implied,
+    /// A default value, optionally preceded by `#FIXED`.
+    Value(DefaultDeclAttValue),
+}
+
+/// ```text
+/// ('#FIXED' S)? AttValue
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct DefaultDeclAttValue {
+    pub(crate) fixed: Option<Whitespace>,
+    pub(crate) att_value: AttValue,
+}
+
+impl DefaultDeclAttValue {
+    /// Whether the value is preceded by `#FIXED`.
+    pub fn is_fixed(&self) -> bool {
+        self.fixed.is_some()
+    }
+
+    /// The default value.
+    pub fn att_value(&self) -> &AttValue {
+        &self.att_value
+    }
+}
+
+/// https://www.w3.org/TR/xml/#NT-AttValue
+/// ```text
+/// [10] AttValue ::= '"' ([^<&"] | Reference)* '"'
+///                   | "'" ([^<&'] | Reference)* "'"
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct AttValue {
+    pub(crate) quote: Quote,
+    pub(crate) data: Vec<AttValueData>,
+}
+
+impl AttValue {
+    /// The text and references that make up the value, in order.
+    pub fn data(&self) -> std::slice::Iter<'_, AttValueData> {
+        self.data.iter()
+    }
+}
+
+/// https://www.w3.org/TR/xml/#NT-AttValue
+/// ```text
+/// [10] AttValue ::= '"' ([^<&"] | Reference)* '"'
+///                   | "'" ([^<&'] | Reference)* "'"
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum AttValueData {
+    /// Literal text.
+    Text(String),
+    /// An entity or character reference, e.g. `&name;` or `&#60;`.
+    Reference(Reference),
+}
+
+impl AttValueData {
+    pub(crate) fn forbidden(c: char, q: Quote) -> bool {
+        match c {
+            '\'' if q == Quote::Single => true,
+            '"' if q == Quote::Double => true,
+            '&' => true,
+            '<' => true,
+            _ => false,
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// serialization
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+fn write_optional<T: Display>(f: &mut Formatter<'_>, value: &Option<T>) -> fmt::Result {
+    match value {
+        Some(value) => value.fmt(f),
+        None => Ok(()),
+    }
+}
+
+/// Writes `items` separated by `delimiter`. The `space_after_delimiter` of the first item is the
+/// whitespace following the opening parenthesis.
+fn write_delimited<T: Display>(
+    f: &mut Formatter<'_>,
+    items: &[DelimitedListItem<T>],
+    delimiter: char,
+) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        write_optional(f, &item.space_before_delimiter)?;
+        if i > 0 {
+            write!(f, "{delimiter}")?;
+        }
+        write_optional(f, &item.space_after_delimiter)?;
+        item.item.fmt(f)?;
+    }
+    Ok(())
+}
+
+impl Display for DocTypeDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "<!{}{}{}",
+            STR_DOCTYPE, self.space_before_name, self.name
+        )?;
+        if let Some((ws, external_id)) = &self.external_id {
+            write!(f, "{ws}{external_id}")?;
+        }
+        write_optional(f, &self.space_before_int_subset)?;
+        if let Some((int_subsets, ws)) = &self.int_subsets {
+            write!(f, "[{int_subsets}]")?;
+            write_optional(f, ws)?;
+        }
+        write!(f, ">")
+    }
+}
+
+impl Display for DocTypeName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+impl Display for Whitespace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for space in &self.inner {
+            let c = match space {
+                Space::Space => SPACE,
+                Space::Tab => '\t',
+                Space::CarriageReturn => CARRIAGE_RETURN,
+                Space::Newline => NEWLINE,
+            };
+            write!(f, "{c}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for ExternalID {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ExternalID::System(id) => write!(
+                f,
+                "{}{}{}",
+                STR_SYSTEM, id.space_before_literal, id.system_literal
+            ),
+            ExternalID::Public(id) => write!(
+                f,
+                "{}{}{}{}{}",
+                STR_PUBLIC,
+                id.space_before_pub_id,
+                id.pub_id_literal,
+                id.space_after_pub_id,
+                id.system_literal
+            ),
+        }
+    }
+}
+
+impl Display for SystemLiteral {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let q = self.quote.char();
+        write!(f, "{}{}{}", q, self.value, q)
+    }
+}
+
+impl Display for PubIDLiteral {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let q = self.quote.char();
+        write!(f, "{}{}{}", q, self.value, q)
+    }
+}
+
+impl Display for IntSubsets {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for item in &self.0 {
+            match item {
+                IntSubset::MarkupDecl(decl) => decl.fmt(f)?,
+                IntSubset::DeclSep(DeclSep::PEReference(pe_ref)) => pe_ref.fmt(f)?,
+                IntSubset::DeclSep(DeclSep::Space(ws)) => ws.fmt(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for ReferenceValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "&{};", self.value)
+    }
+}
+
+impl Display for PEReferenceValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "%{};", self.value)
+    }
+}
+
+impl Display for MarkupDeclValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MarkupDeclValue::ElementDecl(decl) => decl.fmt(f),
+            MarkupDeclValue::AttlistDecl(decl) => decl.fmt(f),
+            MarkupDeclValue::EntityDecl(decl) => decl.fmt(f),
+            MarkupDeclValue::NotationDecl(decl) => decl.fmt(f),
+            MarkupDeclValue::PI(pi) => pi.fmt(f),
+            MarkupDeclValue::Comment(comment) => write!(f, "<!--{comment}-->"),
+        }
+    }
+}
+
+impl Display for ElementDeclValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "<!{}{}{}{}{}",
+            STR_ELEMENT,
+            self.space_before_name,
+            self.name,
+            self.space_after_name,
+            self.content_spec
+        )?;
+        write_optional(f, &self.space_after_content_spec)?;
+        write!(f, ">")
+    }
+}
+
+impl Display for ContentSpec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentSpec::Empty => f.write_str(STR_EMPTY),
+            ContentSpec::Any => f.write_str(STR_ANY),
+            ContentSpec::Mixed(mixed) => mixed.fmt(f),
+            ContentSpec::Children(children) => children.fmt(f),
+        }
+    }
+}
+
+impl Display for NmToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.value)
+    }
+}
+
+impl Display for MixedValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "(")?;
+        write_optional(f, &self.space_after_open_parenthesis)?;
+        write!(f, "{STR_PCDATA}")?;
+        for item in &self.element_names {
+            write_optional(f, &item.space_before_delimiter)?;
+            write!(f, "|")?;
+            write_optional(f, &item.space_after_delimiter)?;
+            item.item.fmt(f)?;
+        }
+        write_optional(f, &self.space_before_close_parenthesis)?;
+        write!(f, ")")?;
+        if self.zero_or_more {
+            write!(f, "*")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Repetitions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Repetitions::Optional => f.write_str("?"),
+            Repetitions::Once => Ok(()),
+            Repetitions::ZeroOrMore => f.write_str("*"),
+            Repetitions::OneOrMore => f.write_str("+"),
+        }
+    }
+}
+
+impl Display for ChildrenType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ChildrenType::Choice(choice) => choice.fmt(f),
+            ChildrenType::Seq(seq) => seq.fmt(f),
+        }
+    }
+}
+
+impl Display for ChildrenValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.children_type, self.repetitions)
+    }
+}
+
+impl Display for CpItem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CpItem::Name(name) => name.fmt(f),
+            CpItem::Choice(choice) => choice.fmt(f),
+            CpItem::Seq(seq) => seq.fmt(f),
+        }
+    }
+}
+
+impl Display for CpValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.cp_item, self.repetitions)
+    }
+}
+
+impl Display for ChoiceValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "(")?;
+        write_delimited(f, &self.cps, '|')?;
+        write_optional(f, &self.space_before_close)?;
+        write!(f, ")")
+    }
+}
+
+impl Display for SeqValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "(")?;
+        write_delimited(f, &self.cps, ',')?;
+        write_optional(f, &self.space_before_close)?;
+        write!(f, ")")
+    }
+}
+
+impl Display for AttlistDeclValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "<!{}{}{}",
+            STR_ATTLIST, self.space_before_name, self.name
+        )?;
+        for att_def in &self.att_defs {
+            att_def.fmt(f)?;
+        }
+        write_optional(f, &self.space_before_close)?;
+        write!(f, ">")
+    }
+}
+
+impl Display for AttDef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}{}{}{}{}",
+            self.space_before_name,
+            self.name,
+            self.space_before_att_type,
+            self.att_type,
+            self.space_before_default_decl,
+            self.default_decl
+        )
+    }
+}
+
+impl Display for AttType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AttType::CData => f.write_str(STR_CDATA),
+            AttType::ID => f.write_str(STR_ID),
+            AttType::IDRef => f.write_str(STR_IDREF),
+            AttType::IDRefs => f.write_str(STR_IDREFS),
+            AttType::Entity => f.write_str(STR_ENTITY),
+            AttType::Entities => f.write_str(STR_ENTITIES),
+            AttType::NMToken => f.write_str(STR_NMTOKEN),
+            AttType::NMTokens => f.write_str(STR_NMTOKENS),
+            AttType::EnumeratedTypes(enumerated) => enumerated.fmt(f),
+        }
+    }
+}
+
+impl Display for EnumeratedType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            EnumeratedType::NotationType(notation) => {
+                write!(f, "{}{}(", STR_NOTATION, notation.space_before_open)?;
+                write_delimited(f, &notation.names, '|')?;
+                write_optional(f, &notation.space_before_close)?;
+                write!(f, ")")
+            }
+            EnumeratedType::Enumeration(enumeration) => {
+                write!(f, "(")?;
+                write_delimited(f, &enumeration.names, '|')?;
+                write_optional(f, &enumeration.space_before_close)?;
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl Display for EntityDeclValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            EntityDeclValue::GEDecl(ge) => {
+                write!(
+                    f,
+                    "<!{}{}{}{}{}",
+                    STR_ENTITY,
+                    ge.space_before_name,
+                    ge.name,
+                    ge.space_before_entity_def,
+                    ge.entity_def
+                )?;
+                write_optional(f, &ge.space_before_close)?;
+            }
+            EntityDeclValue::PEDecl(pe) => {
+                write!(
+                    f,
+                    "<!{}{}%{}{}{}{}",
+                    STR_ENTITY,
+                    pe.space_before_percent,
+                    pe.space_before_name,
+                    pe.name,
+                    pe.space_before_pe_def,
+                    pe.pe_def
+                )?;
+                write_optional(f, &pe.space_before_close)?;
+            }
+        }
+        write!(f, ">")
+    }
+}
+
+impl Display for EntityDef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            EntityDef::Entity(value) => value.fmt(f),
+            EntityDef::External(external) => {
+                external.external_id.fmt(f)?;
+                if let Some(ndata) = &external.ndata_decl {
+                    write!(
+                        f,
+                        "{}{}{}{}",
+                        ndata.space_before_ndata, STR_NDATA, ndata.space_before_name, ndata.name
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Display for PEDef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PEDef::Entity(value) => value.fmt(f),
+            PEDef::External(external_id) => external_id.fmt(f),
+        }
+    }
+}
+
+impl Display for EntityValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let q = self.quote.char();
+        write!(f, "{q}")?;
+        for data in &self.data {
+            match data {
+                EntityValueData::String(s) => f.write_str(s)?,
+                EntityValueData::PEReference(pe_ref) => pe_ref.fmt(f)?,
+                EntityValueData::Reference(reference) => reference.fmt(f)?,
+            }
+        }
+        write!(f, "{q}")
+    }
+}
+
+impl Display for Reference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Reference::EntityRef(entity_ref) => entity_ref.fmt(f),
+            Reference::CharRef(char_ref) => char_ref.fmt(f),
+        }
+    }
+}
+
+impl Display for CharRefValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.char_ref_type {
+            CharRefValueType::Decimal => write!(f, "&#{};", self.value),
+            CharRefValueType::Hex => write!(f, "&#x{:X};", self.value),
+        }
+    }
+}
+
+impl Display for NotationDeclValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "<!{}{}{}{}{}",
+            STR_NOTATION, self.space_before_name, self.name, self.space_before_id, self.id
+        )?;
+        write_optional(f, &self.space_before_close)?;
+        write!(f, ">")
+    }
+}
+
+impl Display for ExternalOrPublicID {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ExternalOrPublicID::External(external_id) => external_id.fmt(f),
+            ExternalOrPublicID::Public(public_id) => write!(
+                f,
+                "{}{}{}",
+                STR_PUBLIC, public_id.space_before_name, public_id.public_id_literal
+            ),
+        }
+    }
+}
+
+impl Display for DefaultDecl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DefaultDecl::Required => f.write_str(STR_REQUIRED),
+            DefaultDecl::Implied => f.write_str(STR_IMPLIED),
+            DefaultDecl::Value(value) => {
+                if let Some(ws) = &value.fixed {
+                    write!(f, "{STR_FIXED}{ws}")?;
+                }
+                value.att_value.fmt(f)
+            }
+        }
+    }
+}
+
+impl Display for AttValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let q = self.quote.char();
+        write!(f, "{q}")?;
+        for data in &self.data {
+            match data {
+                AttValueData::Text(s) => f.write_str(s)?,
+                AttValueData::Reference(reference) => reference.fmt(f)?,
+            }
+        }
+        write!(f, "{q}")
+    }
+}
@@ -1,12 +1,21 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{Display, Formatter};
 use std::io::{Cursor, Write};
+use std::ops::RangeBounds;
 
+use crate::error::ErrorKind;
 use crate::xdoc::cdata::check_cdata;
 use crate::xdoc::error::{Result, XDocErr};
+use crate::xdoc::metadata::Metadata;
 use crate::xdoc::ord_map::OrdMap;
+use crate::xdoc::traverse::{Edge, Traverse};
 use crate::xdoc::write_ops::write_attribute_value;
 use crate::xdoc::Name;
-use crate::{Node, Pi, WriteOpts};
+use crate::xmlns::{
+    check_binding, declarations, initial_bindings, lookup, resolve, split, Bindings, ExpandedName,
+    Uses, XMLNS_URI,
+};
+use crate::{AttributeOrder, Namespace, NcName, Node, NsErr, Pi, Span, WriteOpts};
 
 #[derive(Debug, Clone, Eq, PartialOrd, Ord, PartialEq, Hash)]
 /// Represents an Element in an XML Document.
@@ -15,8 +24,27 @@ pub struct Element {
     name: Name,
     /// Attributes of this element.
     attributes: OrdMap,
+    /// The keys of the attributes that were not specified but were given their default value by
+    /// the DTD.
+    defaulted_attributes: Metadata<BTreeSet<String>>,
     /// Children of this element.
     nodes: Vec<Node>,
+    /// The namespaces that this element and its attributes were created in, by prefix, which are
+    /// declared when the element is written if they are not already in scope.
+    authored_namespaces: Metadata<BTreeMap<String, String>>,
+    /// The namespace bindings in scope for this element, if they have been resolved.
+    namespaces: Metadata<Option<Bindings>>,
+    /// Where this element was parsed from, if spans were recorded.
+    spans: Metadata<Option<Box<Spans>>>,
+}
+
+/// Where an element, its attributes and its nodes were parsed from.
+#[derive(Debug, Clone, Default)]
+struct Spans {
+    element: Span,
+    attributes: BTreeMap<String, Span>,
+    /// Indexed like the element's nodes. Nodes that were added after parsing do not have a span.
+    nodes: Vec<Option<Span>>,
 }
 
 impl Default for Element {
@@ -36,16 +64,64 @@ impl Display for Element {
     }
 }
 
+impl Drop for Element {
+    /// Dropping the nodes recursively would overflow the stack for deeply nested elements, so the
+    /// descendants are moved onto a stack and dropped one at a time, after their nodes are taken.
+    fn drop(&mut self) {
+        let mut nodes = std::mem::take(&mut self.nodes);
+        while let Some(node) = nodes.pop() {
+            if let Node::Element(mut element) = node {
+                nodes.append(&mut element.nodes);
+            }
+        }
+    }
+}
+
 impl Element {
     /// Create a new element using the given name.
     pub fn from_name<S: Into<String>>(name: S) -> Self {
         Element {
             name: Name::new(name.into()),
             attributes: Default::default(),
+            defaulted_attributes: Default::default(),
             nodes: Default::default(),
+            authored_namespaces: Default::default(),
+            namespaces: Default::default(),
+            spans: Default::default(),
         }
     }
 
+    /// Create a new element named `local` in `namespace`. The element is written with the
+    /// namespace's prefix, or without a prefix if it is a default namespace, and the namespace is
+    /// declared when the element is written unless an ancestor has already declared it.
+    ///
+    /// ```
+    /// use exile::{Element, Namespace};
+    /// let ns = Namespace::new("http://example.com", "ex").unwrap();
+    /// let mut root = Element::from_ns_name(&ns, "root").unwrap();
+    /// root.add_child(Element::from_ns_name(&ns, "child").unwrap());
+    /// assert_eq!(
+    ///     r#"<ex:root xmlns:ex="http://example.com">
+    ///   <ex:child/>
+    /// </ex:root>"#,
+    ///     root.to_string()
+    /// );
+    /// ```
+    pub fn from_ns_name<S: AsRef<str>>(namespace: &Namespace, local: S) -> Result<Self> {
+        let local = better_wrap!(ErrorKind::InvalidName, NcName::new(local.as_ref()))?;
+        let prefix = namespace.local().unwrap_or("");
+        better_wrap!(ErrorKind::Namespace, check_binding(prefix, namespace.uri()))?;
+        let mut element = match namespace.local() {
+            Some(prefix) => Self::from_name(format!("{}:{}", prefix, local)),
+            None => Self::from_name(local.as_str()),
+        };
+        element
+            .authored_namespaces
+            .0
+            .insert(prefix.to_owned(), namespace.uri().to_owned());
+        Ok(element)
+    }
+
     /// Returns the 'child' elements of the current element. Consider the XML document:
     /// ```xml
     /// <r>
@@ -88,6 +164,65 @@ impl Element {
         self.children().find(|&child| child.name.as_str() == name)
     }
 
+    /// Returns the elements that are descendants of this element, i.e. its children, their
+    /// children and so on, in document order. The element itself is not included.
+    pub fn descendants(&self) -> impl Iterator<Item = &Element> {
+        self.descendants_with_nodes().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+
+    /// Returns the nodes (of any kind) that are descendants of this element, in document order.
+    /// The element itself is not included.
+    pub fn descendants_with_nodes(&self) -> impl Iterator<Item = &Node> {
+        self.traverse().filter_map(|edge| match edge {
+            Edge::Enter(node) => Some(node),
+            Edge::Exit(_) => None,
+        })
+    }
+
+    /// Walks the descendants of this element depth-first, entering each node before its children
+    /// and exiting it after them. Like [`crate::Arena::traverse`], the element itself is not
+    /// included, so the first edge enters its first child and the last exits its last child.
+    ///
+    /// ```
+    /// use exile::{Edge, Node};
+    /// let doc = exile::parse("<a><b><c/></b><d/></a>").unwrap();
+    /// let mut depth = 0;
+    /// let mut max_depth = 0;
+    /// for edge in doc.root().traverse() {
+    ///     match edge {
+    ///         Edge::Enter(Node::Element(_)) => depth += 1,
+    ///         Edge::Exit(Node::Element(_)) => depth -= 1,
+    ///         _ => {}
+    ///     }
+    ///     max_depth = max_depth.max(depth);
+    /// }
+    /// assert_eq!(2, max_depth);
+    /// ```
+    pub fn traverse(&self) -> Traverse<'_> {
+        Traverse::new(self)
+    }
+
+    /// Find the first descendant element with the fullname `name`, in document order. Unlike
+    /// [`Element::child`], this searches all levels of children, but not the element itself.
+    pub fn find<S: AsRef<str>>(&self, name: S) -> Option<&Element> {
+        let name = name.as_ref();
+        self.descendants()
+            .find(|element| element.fullname() == name)
+    }
+
+    /// Returns the descendant elements with the fullname `name`, in document order. The element
+    /// itself is not included.
+    pub fn find_all<'a, S>(&'a self, name: S) -> impl Iterator<Item = &'a Element> + 'a
+    where
+        S: AsRef<str> + 'a,
+    {
+        self.descendants()
+            .filter(move |element| element.fullname() == name.as_ref())
+    }
+
     /// Add an element as a child of this element.
     pub fn add_child(&mut self, element: Element) {
         self.nodes.push(Node::Element(element))
@@ -117,6 +252,207 @@ impl Element {
         self.nodes.get(index)
     }
 
+    /// Iterate over the child nodes (of any kind) for modification.
+    pub fn nodes_mut(&mut self) -> std::slice::IterMut<'_, Node> {
+        self.nodes.iter_mut()
+    }
+
+    /// Insert a node of any kind at `index`, shifting the nodes after it. Will error if `index` is
+    /// greater than the number of nodes, or if the node is a `CDATA` node that contains `]]>`.
+    ///
+    /// ```
+    /// use exile::{Element, Node};
+    /// let mut root = Element::from_name("root");
+    /// root.add_text("world");
+    /// root.insert_node(0, Node::Text("hello ".into())).unwrap();
+    /// assert_eq!("<root>hello world</root>", root.to_string());
+    /// ```
+    pub fn insert_node(&mut self, index: usize, node: Node) -> Result<()> {
+        if index > self.nodes.len() {
+            return raise!(
+                ErrorKind::InvalidDocument,
+                "cannot insert a node at {} in an element with {} nodes",
+                index,
+                self.nodes.len()
+            );
+        }
+        if let Node::CData(cdata) = &node {
+            check_cdata(cdata)?;
+        }
+        if let Some(spans) = self.node_spans_mut() {
+            spans.insert(index, None);
+        }
+        self.nodes.insert(index, node);
+        Ok(())
+    }
+
+    /// Remove and return the node at `index`, shifting the nodes after it. `None` if there is no
+    /// node at `index`.
+    pub fn remove_node(&mut self, index: usize) -> Option<Node> {
+        if index >= self.nodes.len() {
+            return None;
+        }
+        if let Some(spans) = self.node_spans_mut() {
+            spans.remove(index);
+        }
+        Some(self.nodes.remove(index))
+    }
+
+    /// Replace the node at `index` with `node`, and return the node that was replaced. Will error
+    /// if there is no node at `index`, or if the node is a `CDATA` node that contains `]]>`.
+    pub fn replace_node(&mut self, index: usize, node: Node) -> Result<Node> {
+        if index >= self.nodes.len() {
+            return raise!(
+                ErrorKind::InvalidDocument,
+                "cannot replace the node at {} in an element with {} nodes",
+                index,
+                self.nodes.len()
+            );
+        }
+        if let Node::CData(cdata) = &node {
+            check_cdata(cdata)?;
+        }
+        if let Some(spans) = self.node_spans_mut() {
+            spans[index] = None;
+        }
+        Ok(std::mem::replace(&mut self.nodes[index], node))
+    }
+
+    /// Keep only the nodes for which `f` returns `true`, in their original order.
+    ///
+    /// ```
+    /// use exile::Node;
+    /// let mut doc = exile::parse("<a><!--x-->text<b/><!--y--></a>").unwrap();
+    /// doc.root_mut().retain_nodes(|node| !matches!(node, Node::Comment(_)));
+    /// assert_eq!(2, doc.root().nodes_len());
+    /// ```
+    pub fn retain_nodes<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&Node) -> bool,
+    {
+        let mut kept = Vec::with_capacity(self.nodes.len());
+        self.nodes.retain(|node| {
+            let keep = f(node);
+            kept.push(keep);
+            keep
+        });
+        if let Some(spans) = self.spans.0.as_mut() {
+            let mut kept = kept.into_iter();
+            spans.nodes.retain(|_| kept.next().unwrap_or_default());
+        }
+    }
+
+    /// Remove the nodes in `range` and return them as an iterator. As with `Vec::drain`, this
+    /// panics if the range is out of bounds.
+    pub fn drain_nodes<R>(&mut self, range: R) -> std::vec::Drain<'_, Node>
+    where
+        R: RangeBounds<usize> + Clone,
+    {
+        if let Some(spans) = self.node_spans_mut() {
+            spans.drain(range.clone());
+        }
+        self.nodes.drain(range)
+    }
+
+    /// Remove all of the child nodes and attributes, leaving an empty element with the same name.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.attributes.clear();
+        self.defaulted_attributes.0.clear();
+        if let Some(spans) = self.spans.0.as_mut() {
+            spans.nodes.clear();
+            spans.attributes.clear();
+        }
+    }
+
+    /// Where this element was parsed from, from the `<` of its start tag to just after the `>` of
+    /// its end tag. Only available when the document was parsed with
+    /// [`crate::ParseOptions::spans`]. Elements that are produced by a [`crate::Reader`] only span
+    /// their start tag.
+    ///
+    /// ```
+    /// let xml = "<doc>\n  <a x=\"1\">text</a>\n</doc>";
+    /// let doc = exile::parse_with(xml, &exile::ParseOptions::new().spans(true)).unwrap();
+    /// let a = doc.root().child("a").unwrap();
+    /// let span = a.span().unwrap();
+    /// assert_eq!((2, 3), (span.start.line, span.start.column));
+    /// assert_eq!("<a x=\"1\">text</a>", &xml[span.range()]);
+    /// assert_eq!("x=\"1\"", &xml[a.attribute_span("x").unwrap().range()]);
+    /// assert_eq!("text", &xml[a.node_span(0).unwrap().range()]);
+    /// ```
+    pub fn span(&self) -> Option<Span> {
+        self.spans.0.as_ref().map(|spans| spans.element)
+    }
+
+    /// Where the attribute at `key` was parsed from, from the start of its name to just after the
+    /// closing quote of its value. `None` if spans were not recorded, or if the attribute was not
+    /// written in the document. See [`Element::span`].
+    pub fn attribute_span<S: AsRef<str>>(&self, key: S) -> Option<Span> {
+        self.spans.0.as_ref()?.attributes.get(key.as_ref()).copied()
+    }
+
+    /// Where the child node (of any kind) at `index` was parsed from. `None` if spans were not
+    /// recorded. See [`Element::span`].
+    pub fn node_span(&self, index: usize) -> Option<Span> {
+        self.spans.0.as_ref()?.nodes.get(index).copied().flatten()
+    }
+
+    pub(crate) fn set_span(&mut self, span: Span) {
+        self.spans.0.get_or_insert_with(Default::default).element = span;
+    }
+
+    pub(crate) fn set_attribute_span<S: Into<String>>(&mut self, key: S, span: Span) {
+        let spans = self.spans.0.get_or_insert_with(Default::default);
+        spans.attributes.insert(key.into(), span);
+    }
+
+    /// Sets the span of the last child node.
+    pub(crate) fn set_last_node_span(&mut self, span: Span) {
+        let index = match self.nodes.len().checked_sub(1) {
+            Some(index) => index,
+            None => return,
+        };
+        let spans = self.spans.0.get_or_insert_with(Default::default);
+        spans.nodes.resize(index + 1, None);
+        spans.nodes[index] = Some(span);
+    }
+
+    /// The spans of the child nodes, with one entry per node, if spans were recorded.
+    fn node_spans_mut(&mut self) -> Option<&mut Vec<Option<Span>>> {
+        let len = self.nodes.len();
+        let spans = &mut self.spans.0.as_mut()?.nodes;
+        spans.resize(len, None);
+        Some(spans)
+    }
+
+    /// Append a node of any kind to this element's nodes.
+    pub(crate) fn add_node(&mut self, node: Node) {
+        self.nodes.push(node)
+    }
+
+    /// Removes all of the child nodes, and returns them along with their spans.
+    pub(crate) fn take_nodes(&mut self) -> Vec<(Node, Option<Span>)> {
+        let spans = match self.spans.0.as_mut() {
+            Some(spans) => std::mem::take(&mut spans.nodes),
+            None => Vec::new(),
+        };
+        let mut spans = spans.into_iter();
+        std::mem::take(&mut self.nodes)
+            .into_iter()
+            .map(|node| (node, spans.next().flatten()))
+            .collect()
+    }
+
+    /// Get the last child node (of any kind) for modification.
+    pub(crate) fn last_node_mut(&mut self) -> Option<&mut Node> {
+        self.nodes.last_mut()
+    }
+
+    /// Iterate over the child nodes (of any kind).
+    pub fn nodes(&self) -> std::slice::Iter<'_, Node> {
+        self.nodes.iter()
+    }
+
     /// The fullname of the element (including both the namespace alias prefix and the name). For
     /// example, if the name of this element is `ns:foo`, this function returns `"ns:foo"`.
     /// [`Element::name`] and [`Element:prefix`] give the parsed sections of the fullname.
@@ -136,6 +472,134 @@ impl Element {
         self.name.prefix()
     }
 
+    /// The URI of the namespace that this element's prefix, or the default namespace, is bound to.
+    /// `None` if the element is not in a namespace. Namespaces are only known once they have been
+    /// resolved, either by parsing with [`crate::ParseOptions::namespaces`] or by calling
+    /// [`crate::Document::resolve_namespaces`], or if the element was created with
+    /// [`Element::from_ns_name`]. Otherwise this returns `None`.
+    pub fn namespace_uri(&self) -> Option<&str> {
+        self.prefix_uri(self.prefix().unwrap_or(""))
+    }
+
+    /// The namespace URI and local name of this element. For example, the expanded name of
+    /// `<ns:foo xmlns:ns="http://example.com"/>` is displayed as `{http://example.com}foo`. See
+    /// [`Element::namespace_uri`] for when namespaces are known.
+    pub fn expanded_name(&self) -> ExpandedName<'_> {
+        ExpandedName::new(self.namespace_uri(), self.name())
+    }
+
+    /// Gets the value of the attribute with the namespace `uri` and the local name `local`,
+    /// whatever prefix it was written with. Attributes without a prefix are not in any namespace,
+    /// and are found with an empty `uri`. See [`Element::namespace_uri`] for when namespaces are
+    /// known.
+    pub fn attribute_ns<U, L>(&self, uri: U, local: L) -> Option<&String>
+    where
+        U: AsRef<str>,
+        L: AsRef<str>,
+    {
+        let (uri, local) = (uri.as_ref(), local.as_ref());
+        self.attributes().find_map(|(key, value)| {
+            let (key_uri, key_local) = match split(key).ok()? {
+                (None, "xmlns") => (XMLNS_URI, ""),
+                (None, name) => ("", name),
+                (Some("xmlns"), name) => (XMLNS_URI, name),
+                (Some(prefix), name) => (self.prefix_uri(prefix)?, name),
+            };
+            if key_uri == uri && key_local == local {
+                Some(value)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Inserts an attribute named `local` in `namespace`, which is written with the namespace's
+    /// prefix. Attributes cannot be in a default namespace, and the prefix must not already be used
+    /// for a different namespace by this element or its other attributes. Returns the old value if
+    /// the attribute was already present.
+    pub fn add_attribute_ns<L, V>(
+        &mut self,
+        namespace: &Namespace,
+        local: L,
+        value: V,
+    ) -> Result<Option<String>>
+    where
+        L: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let local = better_wrap!(ErrorKind::InvalidName, NcName::new(local.as_ref()))?;
+        let prefix = match namespace.local() {
+            Some(prefix) => prefix,
+            None => {
+                return wrap_err!(
+                    ErrorKind::Namespace,
+                    NsErr::Malformed("an attribute cannot be in a default namespace")
+                )
+            }
+        };
+        better_wrap!(ErrorKind::Namespace, check_binding(prefix, namespace.uri()))?;
+        match self.authored_namespaces.0.get(prefix) {
+            Some(uri) if uri != namespace.uri() => {
+                return wrap_err!(
+                    ErrorKind::Namespace,
+                    NsErr::ConflictingPrefix(prefix.to_owned())
+                )
+            }
+            Some(_) => {}
+            None => {
+                self.authored_namespaces
+                    .0
+                    .insert(prefix.to_owned(), namespace.uri().to_owned());
+            }
+        }
+        Ok(self.add_attribute(format!("{}:{}", prefix, local), value))
+    }
+
+    /// The namespace URI that `prefix` is bound to, from the resolved bindings if there are any,
+    /// otherwise from the namespaces that this element was created in.
+    fn prefix_uri(&self, prefix: &str) -> Option<&str> {
+        match self.namespaces.0.as_ref() {
+            Some(bindings) => lookup(bindings, prefix).ok(),
+            None => self
+                .authored_namespaces
+                .0
+                .get(prefix)
+                .map(|uri| uri.as_str())
+                .filter(|uri| !uri.is_empty()),
+        }
+    }
+
+    /// The namespaces that this element and its attributes were created in, as (prefix, URI) pairs.
+    pub(crate) fn authored_namespaces(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.authored_namespaces
+            .0
+            .iter()
+            .map(|(prefix, uri)| (prefix.as_str(), uri.as_str()))
+    }
+
+    /// Resolves the namespaces of this element and its descendants, given the bindings in scope
+    /// for its parent.
+    pub(crate) fn resolve_namespaces(
+        &mut self,
+        parent: &Bindings,
+    ) -> std::result::Result<(), NsErr> {
+        let bindings = resolve(self, parent)?;
+        for child in self.children_mut() {
+            child.resolve_namespaces(&bindings)?;
+        }
+        self.namespaces = Metadata(Some(bindings));
+        Ok(())
+    }
+
+    /// The namespace bindings in scope for this element, if they have been resolved.
+    pub(crate) fn namespaces(&self) -> Option<&Bindings> {
+        self.namespaces.0.as_ref()
+    }
+
+    pub(crate) fn set_namespaces(&mut self, bindings: Bindings) {
+        self.namespaces = Metadata(Some(bindings));
+    }
+
     /// Sets the name of this element without changing the namespace alias prefix. For example, if
     /// the name of this element is `ns:foo` then `set_name("bar")` will change the fullname to
     /// `ns:bar`.
@@ -164,9 +628,10 @@ impl Element {
 
     /// Inserts a key-value pair into the attributes map.
     ///
-    /// If the map did not have this key present, `None` is returned.
+    /// If the map did not have this key present, the attribute is added after the existing
+    /// attributes and `None` is returned.
     ///
-    /// If the map did have this key present, the value is updated, and the old
+    /// If the map did have this key present, the value is updated in place, and the old
     /// value is returned.
     ///
     pub fn add_attribute<K, V>(&mut self, key: K, value: V) -> Option<String>
@@ -174,35 +639,67 @@ impl Element {
         K: AsRef<str>,
         V: AsRef<str>,
     {
+        self.defaulted_attributes.0.remove(key.as_ref());
         self.attributes
-            .mut_map()
             .insert(key.as_ref().into(), value.as_ref().into())
     }
 
+    /// Removes the attribute at `key`, and returns its value if it existed.
+    pub fn remove_attribute<S: AsRef<str>>(&mut self, key: S) -> Option<String> {
+        let key = key.as_ref();
+        self.defaulted_attributes.0.remove(key);
+        if let Some(spans) = self.spans.0.as_mut() {
+            spans.attributes.remove(key);
+        }
+        self.attributes.remove(key)
+    }
+
+    /// Adds an attribute that was not specified in the document, but was given its default value
+    /// by the DTD.
+    pub(crate) fn add_defaulted_attribute<K, V>(&mut self, key: K, value: V)
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let key = key.into();
+        self.attributes.insert(key.clone(), value.into());
+        self.defaulted_attributes.0.insert(key);
+    }
+
+    /// Returns `true` if the attribute at `key` was not specified in the document, but was given
+    /// its default value by an `<!ATTLIST>` declaration in the DTD. Defaulted attributes are not
+    /// written when the element is serialized.
+    pub fn is_attribute_defaulted<S: AsRef<str>>(&self, key: S) -> bool {
+        self.defaulted_attributes.0.contains(key.as_ref())
+    }
+
     /// Gets the attribute value at `key`. `None` if an attribute by that name does not exist.
     pub fn attribute<S: AsRef<str>>(&self, key: S) -> Option<&String> {
-        self.attributes.map().get(key.as_ref())
+        self.attributes.get(key.as_ref())
     }
 
     /// Gets the count of attributes.
     pub fn attributes_len(&self) -> usize {
-        self.attributes.map().len()
+        self.attributes.len()
     }
 
-    /// Gets an iterator over the attribute key/value pairs.
+    /// Gets an iterator over the attribute key/value pairs, in the order that they were parsed or
+    /// added. Attributes that were given their default value by the DTD come after those that were
+    /// specified.
     pub fn attributes(&self) -> impl Iterator<Item = (&String, &String)> + '_ {
-        self.attributes.map().iter()
+        self.attributes.iter()
     }
 
-    /// Gets an iterator over the attribute keys
+    /// Gets an iterator over the attribute keys, in the same order as [`Element::attributes`].
     pub fn attribute_keys(&self) -> impl Iterator<Item = &String> + '_ {
-        self.attributes.map().keys()
+        self.attributes.keys()
     }
 
     /// Creates a new element as the last child of this element and returns a mut ref to it.
     pub fn add_new_child(&mut self) -> Result<&mut Element> {
         self.nodes.push(Node::Element(Element::default()));
         let new_node = self.nodes.last_mut().ok_or_else(|| XDocErr {
+            kind: ErrorKind::Internal,
             message: "the sky is falling".to_string(),
             file: "".to_string(),
             line: 0,
@@ -212,6 +709,7 @@ impl Element {
             Ok(new_element)
         } else {
             Err(XDocErr {
+                kind: ErrorKind::Internal,
                 message: "the sky is still falling".to_string(),
                 file: "".to_string(),
                 line: 0,
@@ -282,27 +780,59 @@ impl Element {
 
     /// Write the element to the `Write` object.
     pub fn write<W>(&self, writer: &mut W, opts: &WriteOpts, depth: usize) -> Result<()>
+    where
+        W: Write,
+    {
+        let uses = better_wrap!(ErrorKind::Namespace, Uses::collect(self))?;
+        self.write_in_scope(writer, opts, depth, &initial_bindings(), uses.as_ref())
+    }
+
+    /// Writes the element given the namespace bindings that are in `scope` for its parent and the
+    /// namespaces that are `used` in its content, so that the namespaces it was created in are only
+    /// declared where they are needed. `used` is `None` if nothing in the tree was created in a
+    /// namespace.
+    fn write_in_scope<W>(
+        &self,
+        writer: &mut W,
+        opts: &WriteOpts,
+        depth: usize,
+        scope: &Bindings,
+        used: Option<&Uses<'_>>,
+    ) -> Result<()>
     where
         W: Write,
     {
         if let Err(e) = self.check() {
-            return raise!(e);
+            return raise!(ErrorKind::InvalidDocument, e);
         }
+        let (declarations, scope) = match used {
+            Some(used) => better_wrap!(ErrorKind::Namespace, declarations(self, used, scope))?,
+            None => (Vec::new(), scope.clone()),
+        };
         opts.indent(writer, depth)?;
         xwrite!(writer, "<")?;
         xwrite!(writer, "{}", self.fullname())?;
-
-        let attribute_keys = self
-            .attributes
-            .map()
-            .keys()
-            .map(|k| k.as_str())
-            .collect::<Vec<&str>>();
-        for &k in attribute_keys.iter() {
-            xwrite!(writer, " {}=\"", &k)?;
-            if let Some(val) = self.attributes.map().get(k) {
-                write_attribute_value(val, writer, opts)?;
+        for (prefix, uri) in &declarations {
+            if prefix.is_empty() {
+                xwrite!(writer, " xmlns=\"")?;
+            } else {
+                xwrite!(writer, " xmlns:{}=\"", prefix)?;
             }
+            write_attribute_value(uri, writer, opts)?;
+            xwrite!(writer, "\"")?;
+        }
+
+        let attributes = match opts.attribute_order {
+            AttributeOrder::Source => self.attributes.iter().collect(),
+            AttributeOrder::Sorted => self.attributes.sorted(),
+        };
+        // defaulted attributes come from the DTD, which supplies them again when the output is read.
+        for (k, val) in attributes
+            .into_iter()
+            .filter(|(k, _)| !self.is_attribute_defaulted(k))
+        {
+            xwrite!(writer, " {}=\"", k)?;
+            write_attribute_value(val, writer, opts)?;
             xwrite!(writer, "\"")?;
         }
 
@@ -313,11 +843,26 @@ impl Element {
             xwrite!(writer, ">")?;
         }
 
+        let mixed_opts = if self.nodes.iter().any(Node::is_text) {
+            opts.for_mixed_content()
+        } else {
+            None
+        };
+        let opts = mixed_opts.as_ref().unwrap_or(opts);
+
+        let mut children = 0;
         for (index, node) in self.nodes.iter().enumerate() {
             if index == 0 && !node.is_text() {
                 opts.newline(writer)?;
             }
-            node.write(writer, opts, depth + 1)?;
+            match node {
+                Node::Element(element) => {
+                    let child_used = used.map(|used| used.child(children));
+                    children += 1;
+                    element.write_in_scope(writer, opts, depth + 1, &scope, child_used)?
+                }
+                _ => node.write(writer, opts, depth + 1)?,
+            }
             if !node.is_text() {
                 opts.newline(writer)?;
             }
@@ -340,7 +885,7 @@ impl Element {
                 return Err("Namespace should not be empty when the option is 'some'.");
             }
         }
-        for attribute_key in self.attributes.map().keys() {
+        for attribute_key in self.attributes.keys() {
             if attribute_key.is_empty() {
                 return Err("Empty attribute name encountered.");
             }
@@ -363,3 +908,205 @@ fn test_children() {
     let end_none = children.next();
     assert!(end_none.is_none());
 }
+
+#[test]
+fn test_write_mixed_content() {
+    use crate::MixedContent;
+    let mut root = Element::from_name("root");
+    let mut p = Element::from_name("p");
+    p.add_text("some ");
+    let mut b = Element::from_name("b");
+    b.add_child(Element::from_name("i"));
+    p.add_child(b);
+    p.add_text(" text");
+    root.add_child(p);
+    let mut opts = WriteOpts::default();
+    let mut indented = Vec::new();
+    root.write(&mut indented, &opts, 0).unwrap();
+    assert_eq!(
+        "<root>\n  <p>some     <b>\n      <i/>\n    </b>\n text  </p>\n</root>",
+        String::from_utf8(indented).unwrap()
+    );
+    opts.mixed_content = MixedContent::Preserve;
+    let mut preserved = Vec::new();
+    root.write(&mut preserved, &opts, 0).unwrap();
+    assert_eq!(
+        "<root>\n  <p>some <b><i/></b> text</p>\n</root>",
+        String::from_utf8(preserved).unwrap()
+    );
+}
+
+#[test]
+fn test_write_namespaces() {
+    let a = Namespace::new("http://a", "a").unwrap();
+    let b = Namespace::new("http://b", "a").unwrap();
+    let d = Namespace::new_default("http://d").unwrap();
+    let mut root = Element::from_name("root");
+    let mut x = Element::from_ns_name(&a, "x").unwrap();
+    x.add_child(Element::from_ns_name(&a, "y").unwrap());
+    x.add_attribute_ns(&a, "id", "1").unwrap();
+    root.add_child(x);
+    let mut z = Element::from_ns_name(&d, "z").unwrap();
+    z.add_child(Element::from_ns_name(&d, "w").unwrap());
+    root.add_child(z);
+    assert_eq!(
+        concat!(
+            r#"<root xmlns:a="http://a">"#,
+            r#"<a:x a:id="1"><a:y/></a:x>"#,
+            r#"<z xmlns="http://d"><w/></z>"#,
+            r#"</root>"#
+        ),
+        root.to_string().replace('\n', "").replace("  ", "")
+    );
+    assert_eq!(Some("http://a"), root.child("a:x").unwrap().namespace_uri());
+    // the namespace an element was created in does not take part in equality.
+    assert_eq!(
+        Element::from_name("a:y"),
+        Element::from_ns_name(&a, "y").unwrap()
+    );
+
+    // the same prefix is used for two namespaces, so each is declared where it is used
+    root.add_child(Element::from_ns_name(&b, "x").unwrap());
+    let written = root.to_string();
+    assert!(written.starts_with("<root>"));
+    assert!(written.contains(r#"<a:x xmlns:a="http://a" a:id="1">"#));
+    assert!(written.contains(r#"<a:x xmlns:a="http://b"/>"#));
+    let mut doc = crate::Document::from_root(root);
+    doc.resolve_namespaces().unwrap();
+    let parsed = crate::parse_with(
+        doc.to_string(),
+        &crate::ParseOptions {
+            namespaces: true,
+            ..crate::ParseOptions::default()
+        },
+    )
+    .unwrap();
+    let names: Vec<String> = parsed
+        .root()
+        .children()
+        .map(|e| e.expanded_name().to_string())
+        .collect();
+    assert_eq!(vec!["{http://a}x", "{http://d}z", "{http://b}x"], names);
+}
+
+#[test]
+fn test_namespace_conflicts() {
+    let a = Namespace::new("http://a", "a").unwrap();
+    let b = Namespace::new("http://b", "a").unwrap();
+    let d = Namespace::new_default("http://d").unwrap();
+    let mut x = Element::from_ns_name(&a, "x").unwrap();
+    let e = x.add_attribute_ns(&b, "id", "1").unwrap_err();
+    assert_eq!(ErrorKind::Namespace, e.kind);
+    assert!(x.add_attribute_ns(&d, "id", "1").is_err());
+    x.add_attribute("xmlns:a", "http://b");
+    assert!(x.write(&mut Vec::new(), &WriteOpts::default(), 0).is_err());
+    let e = Element::from_ns_name(&a, "1x").unwrap_err();
+    assert_eq!(ErrorKind::InvalidName, e.kind);
+    let xml = Namespace::new("http://not-xml", "xml").unwrap();
+    assert!(Element::from_ns_name(&xml, "x").is_err());
+}
+
+#[test]
+fn test_node_mutation() {
+    let xml = r#"<root a="1" b="2"><x/>one<!--c--><y/>two</root>"#;
+    let doc = crate::parse_with(xml, &crate::ParseOptions::new().spans(true)).unwrap();
+    let mut root = doc.root().clone();
+    let span = |root: &Element, index: usize| root.node_span(index).map(|span| &xml[span.range()]);
+
+    root.insert_node(1, Node::Text("new".into())).unwrap();
+    assert_eq!(Some("<x/>"), span(&root, 0));
+    assert_eq!(None, span(&root, 1));
+    assert_eq!(Some("one"), span(&root, 2));
+    assert!(root.insert_node(7, Node::Text("x".into())).is_err());
+    assert!(root.insert_node(0, Node::CData("]]>".into())).is_err());
+
+    assert_eq!(Some(Node::Text("new".into())), root.remove_node(1));
+    assert_eq!(None, root.remove_node(5));
+    assert_eq!(Some("one"), span(&root, 1));
+
+    let old = root.replace_node(1, Node::Text("uno".into())).unwrap();
+    assert_eq!(Node::Text("one".into()), old);
+    assert_eq!(None, span(&root, 1));
+    assert!(root.replace_node(5, Node::Text("x".into())).is_err());
+
+    root.retain_nodes(|node| !matches!(node, Node::Comment(_)));
+    assert_eq!(4, root.nodes_len());
+    assert_eq!(Some("<y/>"), span(&root, 2));
+    assert_eq!(Some("two"), span(&root, 3));
+
+    for node in root.nodes_mut() {
+        if let Node::Text(text) = node {
+            text.make_ascii_uppercase();
+        }
+    }
+    let drained: Vec<Node> = root.drain_nodes(..2).collect();
+    assert_eq!(2, drained.len());
+    assert_eq!(Some("<y/>"), span(&root, 0));
+    assert_eq!(Some(&Node::Text("TWO".into())), root.node(1));
+
+    assert_eq!(Some("1".to_owned()), root.remove_attribute("a"));
+    assert_eq!(None, root.remove_attribute("a"));
+    assert_eq!(None, root.attribute_span("a"));
+    let expected = crate::parse(r#"<root b="2"><y/>TWO</root>"#).unwrap();
+    assert_eq!(expected.root(), &root);
+
+    // a node that is changed in place is checked when it is written.
+    let mut invalid = root.clone();
+    invalid
+        .nodes_mut()
+        .for_each(|node| *node = Node::CData("]]>".into()));
+    assert!(invalid
+        .write(&mut Vec::new(), &WriteOpts::default(), 0)
+        .is_err());
+
+    root.clear();
+    assert_eq!(0, root.nodes_len());
+    assert_eq!(0, root.attributes_len());
+    assert_eq!("<root/>", root.to_string());
+}
+
+#[test]
+fn test_attribute_order() {
+    use crate::AttributeOrder;
+    let doc = crate::parse(r#"<root z="1" b="2" m="3"/>"#).unwrap();
+    let mut root = doc.root().clone();
+    assert_eq!(r#"<root z="1" b="2" m="3"/>"#, root.to_string());
+    root.add_attribute("b", "4");
+    root.add_attribute("a", "5");
+    let keys: Vec<&str> = root.attribute_keys().map(|k| k.as_str()).collect();
+    assert_eq!(vec!["z", "b", "m", "a"], keys);
+    let opts = WriteOpts {
+        attribute_order: AttributeOrder::Sorted,
+        ..WriteOpts::default()
+    };
+    let mut sorted = Vec::new();
+    root.write(&mut sorted, &opts, 0).unwrap();
+    assert_eq!(
+        r#"<root a="5" b="4" m="3" z="1"/>"#,
+        String::from_utf8(sorted).unwrap()
+    );
+    // equality does not depend on the order of the attributes.
+    let mut other = Element::from_name("root");
+    for (key, value) in &[("a", "5"), ("m", "3"), ("z", "1"), ("b", "4")] {
+        other.add_attribute(key, value);
+    }
+    assert_eq!(root, other);
+}
+
+#[test]
+fn test_deep_nesting() {
+    // walking, converting and dropping an element must not recurse, whatever its depth.
+    let depth = 100_000;
+    let mut root = Element::from_name("e");
+    for _ in 0..depth {
+        let mut parent = Element::from_name("e");
+        parent.add_child(root);
+        root = parent;
+    }
+    assert_eq!(depth, root.descendants().count());
+    assert_eq!(depth * 2, root.traverse().count());
+    let arena = crate::Document::from_root(root).into_arena();
+    assert_eq!(depth, arena.descendants(arena.root().unwrap()).count());
+    let document = arena.into_document();
+    assert_eq!(depth, document.root().descendants().count());
+}
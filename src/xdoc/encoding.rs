@@ -0,0 +1,245 @@
+use std::io::Write;
+
+#[derive(Debug, Default, Clone, Copy, Eq, Ord, PartialOrd, PartialEq, Hash)]
+/// The character encoding of the XML Document.
+pub enum Encoding {
+    /// The encoding is UTF-8.
+    #[default]
+    Utf8,
+    /// The encoding is UTF-16, little-endian.
+    Utf16Le,
+    /// The encoding is UTF-16, big-endian.
+    Utf16Be,
+    /// The encoding is ISO-8859-1 (Latin-1).
+    Iso8859_1,
+    /// The encoding is Windows-1252.
+    Windows1252,
+}
+
+impl Encoding {
+    /// Finds the `Encoding` for an `encoding` name found in an XML declaration, ignoring case. A
+    /// plain `UTF-16` does not specify the byte order, and is taken to be big-endian.
+    pub(crate) fn from_label(label: &str) -> Option<Encoding> {
+        match label.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "utf-16" | "utf-16be" => Some(Encoding::Utf16Be),
+            "utf-16le" => Some(Encoding::Utf16Le),
+            "iso-8859-1" | "iso8859-1" | "iso_8859-1" | "latin1" | "l1" => {
+                Some(Encoding::Iso8859_1)
+            }
+            "windows-1252" | "cp1252" => Some(Encoding::Windows1252),
+            _ => None,
+        }
+    }
+
+    /// The name that is written in the `encoding` part of an XML declaration. UTF-16 documents are
+    /// written with a byte order mark, so the byte order is not included in the name.
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le | Encoding::Utf16Be => "UTF-16",
+            Encoding::Iso8859_1 => "ISO-8859-1",
+            Encoding::Windows1252 => "windows-1252",
+        }
+    }
+
+    /// Returns `true` for the encodings in which each character is a single byte.
+    pub(crate) fn is_single_byte(self) -> bool {
+        matches!(self, Encoding::Iso8859_1 | Encoding::Windows1252)
+    }
+
+    /// Decodes a byte of a single-byte encoding.
+    pub(crate) fn decode_byte(self, b: u8) -> char {
+        match self {
+            Encoding::Windows1252 if (0x80..0xA0).contains(&b) => {
+                WINDOWS_1252[usize::from(b - 0x80)]
+            }
+            _ => char::from(b),
+        }
+    }
+
+    /// Encodes a character in a single-byte encoding, returning `None` if it is not representable.
+    pub(crate) fn encode_byte(self, c: char) -> Option<u8> {
+        let value = u32::from(c);
+        match self {
+            Encoding::Iso8859_1 if value < 0x100 => Some(value as u8),
+            Encoding::Windows1252 if value < 0x80 || (0xA0..0x100).contains(&value) => {
+                Some(value as u8)
+            }
+            Encoding::Windows1252 => WINDOWS_1252
+                .iter()
+                .position(|&x| x == c)
+                .map(|i| 0x80 + i as u8),
+            _ => None,
+        }
+    }
+}
+
+/// The characters for bytes `0x80` through `0x9F` in Windows-1252. The five bytes that are not
+/// assigned map to the C1 control characters of the same value, as in ISO-8859-1.
+const WINDOWS_1252: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+/// Takes the UTF-8 that the `Document` writes and passes it to the inner writer in another
+/// encoding. Characters that can not be represented in the encoding result in an error.
+pub(crate) struct EncodingWriter<'a, W: Write> {
+    inner: &'a mut W,
+    encoding: Encoding,
+    /// The bytes of a UTF-8 sequence that was split across calls to `write`.
+    partial: Vec<u8>,
+    /// Set when a character could not be represented in the encoding.
+    unencodable: bool,
+}
+
+impl<'a, W: Write> EncodingWriter<'a, W> {
+    /// Creates the `EncodingWriter`, writing a byte order mark first if the encoding is UTF-16.
+    pub(crate) fn new(inner: &'a mut W, encoding: Encoding) -> std::io::Result<Self> {
+        match encoding {
+            Encoding::Utf16Le => inner.write_all(&[0xFF, 0xFE])?,
+            Encoding::Utf16Be => inner.write_all(&[0xFE, 0xFF])?,
+            _ => {}
+        }
+        Ok(Self {
+            inner,
+            encoding,
+            partial: Vec::new(),
+            unencodable: false,
+        })
+    }
+
+    /// Whether writing failed because a character can not be represented in the encoding, as
+    /// opposed to an error from the inner writer.
+    pub(crate) fn is_unencodable(&self) -> bool {
+        self.unencodable
+    }
+
+    fn encode(&self, s: &str, out: &mut Vec<u8>) -> std::io::Result<()> {
+        for c in s.chars() {
+            match self.encoding {
+                Encoding::Utf8 => {
+                    let mut buf = [0; 4];
+                    out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+                Encoding::Utf16Le | Encoding::Utf16Be => {
+                    let mut buf = [0; 2];
+                    for unit in c.encode_utf16(&mut buf) {
+                        if self.encoding == Encoding::Utf16Le {
+                            out.extend_from_slice(&unit.to_le_bytes());
+                        } else {
+                            out.extend_from_slice(&unit.to_be_bytes());
+                        }
+                    }
+                }
+                Encoding::Iso8859_1 | Encoding::Windows1252 => match self.encoding.encode_byte(c) {
+                    Some(b) => out.push(b),
+                    None => {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!(
+                                "the character '{}' can not be encoded in {}",
+                                c.escape_unicode(),
+                                self.encoding.label()
+                            ),
+                        ))
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for EncodingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.partial.extend_from_slice(buf);
+        let (valid, rest) = match std::str::from_utf8(&self.partial) {
+            Ok(s) => (s, 0),
+            // an incomplete sequence at the end will be completed by the next call to write
+            Err(e) if e.error_len().is_none() => (
+                std::str::from_utf8(&self.partial[..e.valid_up_to()]).unwrap_or_default(),
+                self.partial.len() - e.valid_up_to(),
+            ),
+            Err(e) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+        };
+        let mut out = Vec::with_capacity(valid.len() * 2);
+        if let Err(e) = self.encode(valid, &mut out) {
+            self.unencodable = true;
+            return Err(e);
+        }
+        self.inner.write_all(&out)?;
+        let consumed = self.partial.len() - rest;
+        self.partial.drain(..consumed);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+fn encode_all(s: &str, encoding: Encoding) -> std::io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut w = EncodingWriter::new(&mut out, encoding)?;
+    w.write_all(s.as_bytes())?;
+    Ok(out)
+}
+
+#[test]
+fn encoding_labels() {
+    for encoding in [
+        Encoding::Utf8,
+        Encoding::Utf16Be,
+        Encoding::Iso8859_1,
+        Encoding::Windows1252,
+    ] {
+        assert_eq!(Some(encoding), Encoding::from_label(encoding.label()));
+    }
+    assert_eq!(Some(Encoding::Utf16Le), Encoding::from_label("utf-16le"));
+    assert!(Encoding::from_label("ebcdic").is_none());
+}
+
+#[test]
+fn encoding_windows_1252_round_trip() {
+    for b in 0..=255u8 {
+        let c = Encoding::Windows1252.decode_byte(b);
+        assert_eq!(Some(b), Encoding::Windows1252.encode_byte(c));
+    }
+}
+
+#[test]
+fn encoding_writer_utf16() {
+    assert_eq!(
+        vec![0xFF, 0xFE, b'a', 0x00, 0x3C, 0xD8, 0x7A, 0xDF],
+        encode_all("a🍺", Encoding::Utf16Le).unwrap()
+    );
+    assert_eq!(
+        vec![0xFE, 0xFF, 0x00, b'a'],
+        encode_all("a", Encoding::Utf16Be).unwrap()
+    );
+}
+
+#[test]
+fn encoding_writer_single_byte() {
+    assert_eq!(
+        vec![b'a', 0xE9, 0x80],
+        encode_all("aé€", Encoding::Windows1252).unwrap()
+    );
+    assert!(encode_all("aé€", Encoding::Iso8859_1).is_err());
+}
+
+#[test]
+fn encoding_writer_split_sequence() {
+    let mut out = Vec::new();
+    let mut w = EncodingWriter::new(&mut out, Encoding::Iso8859_1).unwrap();
+    let bytes = "é".as_bytes();
+    w.write_all(&bytes[..1]).unwrap();
+    w.write_all(&bytes[1..]).unwrap();
+    assert_eq!(vec![0xE9], out);
+}
@@ -1,12 +1,16 @@
 use std::error::Error;
 use std::fmt;
 
+use crate::error::ErrorKind;
+
 /// The `Result` type for this library.
 pub type Result<T> = std::result::Result<T, XDocErr>;
 
 /// A generic error type for this library.
 #[derive(Debug)]
 pub struct XDocErr {
+    /// What kind of error this is.
+    pub kind: ErrorKind,
     /// The error message.
     pub message: String,
     /// The sourcecode file where the error was raised.
@@ -1,18 +1,22 @@
+// creates a Result::Err that wraps the error `$err` in an XDocErr of kind `$kind`
 macro_rules! wrap_err {
     // Base case:
-    ($err:expr) => (Err($crate::xdoc::error::XDocErr {
+    ($kind:expr, $err:expr) => (Err($crate::xdoc::error::XDocErr {
+        kind: $kind,
         message: "an error occurred".to_string(),
         file: file!().to_string(),
         line: line!() as u64,
         source: Some($err.into()),
     }));
-    ($err:expr, $msg:expr) => (Err($crate::xdoc::error::XDocErr {
+    ($kind:expr, $err:expr, $msg:expr) => (Err($crate::xdoc::error::XDocErr {
+        kind: $kind,
         message: $msg.to_string(),
         file: file!().to_string(),
         line: line!() as u64,
         source: Some($err.into()),
     }));
-    ($err:expr, $fmt:expr, $($arg:expr),+) => (Err($crate::xdoc::error::XDocErr {
+    ($kind:expr, $err:expr, $fmt:expr, $($arg:expr),+) => (Err($crate::xdoc::error::XDocErr {
+        kind: $kind,
         message: format!($fmt, $($arg),+),
         file: file!().to_string(),
         line: line!() as u64,
@@ -20,11 +24,12 @@ macro_rules! wrap_err {
     }));
 }
 
+// converts the error of `$result` into an XDocErr of kind `$kind`
 macro_rules! better_wrap {
-    ($result:expr) => {
+    ($kind:expr, $result:expr) => {
         match $result {
             Ok(value) => Ok(value),
-            Err(e) => wrap_err!(e),
+            Err(e) => wrap_err!($kind, e),
         }
     };
 }
@@ -32,13 +37,15 @@ macro_rules! better_wrap {
 // a convenience macro for creating a Result::Err
 macro_rules! raise {
     // Base case:
-    ($msg:expr) => (Err($crate::xdoc::error::XDocErr {
+    ($kind:expr, $msg:expr) => (Err($crate::xdoc::error::XDocErr {
+        kind: $kind,
         message: $msg.to_string(),
         file: file!().to_string(),
         line: line!() as u64,
         source: None,
     }));
-    ($fmt:expr, $($arg:expr),+) => ($Err($crate::xdoc::error::XErr {
+    ($kind:expr, $fmt:expr, $($arg:expr),+) => (Err($crate::xdoc::error::XDocErr {
+        kind: $kind,
         message: format!($fmt, $($arg),+),
         file: file!().to_string(),
         line: line!() as u64,
@@ -46,11 +53,12 @@ macro_rules! raise {
     }));
 }
 
+// writes to `$writer`, a failure is an XDocErr of kind `ErrorKind::Io`
 macro_rules! xwrite {
     ($writer:expr, $fmt:expr) => ({
-        better_wrap!(write!($writer, $fmt))
+        better_wrap!($crate::error::ErrorKind::Io, write!($writer, $fmt))
     });
     ($writer:expr, $fmt:expr, $($arg:expr),+) => ({
-        better_wrap!(write!($writer, $fmt, $($arg)*))
+        better_wrap!($crate::error::ErrorKind::Io, write!($writer, $fmt, $($arg)*))
     });
 }
@@ -0,0 +1,32 @@
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+/// Holds information about a node that is not part of its XML content, such as where it was parsed
+/// from. `Metadata` does not take part in comparisons or hashing, so that a node compares equal to
+/// one that has identical content but was built differently.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Metadata<T>(pub(crate) T);
+
+impl<T> PartialEq for Metadata<T> {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl<T> Eq for Metadata<T> {}
+
+impl<T> PartialOrd for Metadata<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Metadata<T> {
+    fn cmp(&self, _: &Self) -> Ordering {
+        Ordering::Equal
+    }
+}
+
+impl<T> Hash for Metadata<T> {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
@@ -9,32 +9,42 @@ Specifically, the `xtest` crate uses `xdoc` to generate tests for `exile`.
 The public concepts in `xdoc` are re-exported by `exile`.
 */
 
+pub use arena::{Arena, Children, NodeData, NodeId};
 pub use chars::is_whitespace;
 pub use doc::Document;
-pub use doc::{Declaration, Encoding, Version};
+pub use doc::{Declaration, Version};
+pub use doctype::DocTypeDecl;
 pub use element::Element;
-pub use index::Index;
+pub use encoding::Encoding;
 pub(crate) use name::Name;
 pub use node::{Misc, Node};
 pub use pi::Pi;
-pub use write_ops::WriteOpts;
+pub use span::{Location, Span};
+pub use traverse::{Edge, Traverse};
+pub use validate::DtdViolation;
+pub use write_ops::{AttributeOrder, MixedContent, WriteOpts};
 
 #[macro_use]
 mod macros;
 
+mod arena;
+pub mod borrowed;
 mod cdata;
 mod chars;
 mod doc;
+pub mod doctype;
 mod element;
+mod encoding;
 pub mod error;
-mod index;
+mod metadata;
 mod name;
 mod node;
 pub(crate) mod ord_map;
 mod pi;
+mod span;
+mod traverse;
+mod validate;
 mod write_ops;
-#[cfg(feature = "doctype_wip")]
-pub(crate) mod xdocv2;
 
 #[cfg(test)]
 mod tests {
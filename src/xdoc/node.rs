@@ -14,10 +14,6 @@ pub enum Node {
     /// Comment, e.g. `<!--some comment-->`
     Comment(String),
 
-    // TODO - support doctypes https://github.com/webern/exile/issues/22
-    /// `<!DOCTYPE doc>` - not implemented
-    DocType(String),
-
     /// `<element/>`
     Element(Element),
 
@@ -43,7 +39,6 @@ impl Node {
         match self {
             Node::CData(cdata) => write_cdata(cdata, writer),
             Node::Comment(comment) => write_comment(writer, opts, depth, comment),
-            Node::DocType(_) => panic!("doctypes unsupported"),
             Node::Element(data) => data.write(writer, opts, depth),
             Node::Pi(pi) => pi.write(writer, opts, depth),
             Node::Text(s) => write_element_text(s.as_str(), writer, opts, depth),
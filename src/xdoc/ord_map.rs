@@ -1,30 +1,28 @@
 use core::fmt;
 use std::cmp::Ordering;
-use std::collections::BTreeMap;
+use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 
-/// OrdMap implements some conveniences like Clone and PartialEq for BTreeMap so that we can compare
-/// XML Documents.
-#[derive(Default)]
-pub(crate) struct OrdMap(BTreeMap<String, String>);
-
-impl Clone for OrdMap {
-    fn clone(&self) -> Self {
-        let mut result = BTreeMap::new();
-        for (k, v) in self.0.iter() {
-            result.insert(k.clone(), v.clone());
-        }
-        Self(result)
-    }
+/// OrdMap holds the attributes of an element in the order that they were inserted, which is the
+/// order that they were parsed in, and implements conveniences like Clone and PartialEq so that we
+/// can compare XML Documents. Equality, ordering and hashing do not depend on the order of the
+/// entries. Entries are found through an index of their keys, so that parsing an element with many
+/// attributes is not quadratic.
+#[derive(Default, Clone)]
+pub(crate) struct OrdMap {
+    /// The entries, in insertion order.
+    entries: Vec<(String, String)>,
+    /// The position of each key in `entries`.
+    index: HashMap<String, usize>,
 }
 
 impl PartialEq for OrdMap {
     fn eq(&self, other: &Self) -> bool {
-        if self.0.len() != other.0.len() {
+        if self.entries.len() != other.entries.len() {
             return false;
         }
-        for (k, v) in self.0.iter() {
-            if let Some(other_v) = other.0.get(k) {
+        for (k, v) in self.iter() {
+            if let Some(other_v) = other.get(k) {
                 if other_v != v {
                     return false;
                 }
@@ -39,22 +37,67 @@ impl PartialEq for OrdMap {
 impl Eq for OrdMap {}
 
 impl OrdMap {
-    /// Return the inner BTreeMap as immutable.
-    pub(crate) fn map(&self) -> &BTreeMap<String, String> {
-        &self.0
+    /// Gets the value at `key`.
+    pub(crate) fn get(&self, key: &str) -> Option<&String> {
+        self.index.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    /// Inserts a key-value pair. If `key` is already present, its value is replaced in place and
+    /// the old value is returned, otherwise the pair is added after the existing entries.
+    pub(crate) fn insert(&mut self, key: String, value: String) -> Option<String> {
+        match self.index.get(&key) {
+            Some(&i) => Some(std::mem::replace(&mut self.entries[i].1, value)),
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                None
+            }
+        }
     }
 
-    /// Return the inner BTreeMap as mutable.
-    pub(crate) fn mut_map(&mut self) -> &mut BTreeMap<String, String> {
-        &mut self.0
+    /// Removes the entry at `key`, keeping the order of the remaining entries.
+    pub(crate) fn remove(&mut self, key: &str) -> Option<String> {
+        let removed = self.index.remove(key)?;
+        for (k, _) in &self.entries[removed + 1..] {
+            if let Some(i) = self.index.get_mut(k) {
+                *i -= 1;
+            }
+        }
+        Some(self.entries.remove(removed).1)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.index.clear();
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Iterates over the entries in insertion order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&String, &String)> + '_ {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+
+    /// Iterates over the keys in insertion order.
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &String> + '_ {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    /// The entries sorted by key.
+    pub(crate) fn sorted(&self) -> Vec<(&String, &String)> {
+        let mut entries: Vec<(&String, &String)> = self.iter().collect();
+        entries.sort();
+        entries
     }
 
     fn size_le(&self, other: &Self) -> bool {
-        self.0.len() < other.0.len()
+        self.entries.len() < other.entries.len()
     }
 
     fn size_gt(&self, other: &Self) -> bool {
-        self.0.len() > other.0.len()
+        self.entries.len() > other.entries.len()
     }
 }
 
@@ -99,8 +142,8 @@ impl Ord for OrdMap {
         } else if self.size_gt(other) {
             return Ordering::Greater;
         }
-        for (k, my_val) in self.0.iter() {
-            let get_opt = other.0.get(k.as_str());
+        for (k, my_val) in self.sorted() {
+            let get_opt = other.get(k.as_str());
             match get_opt {
                 None => {
                     return Ordering::Greater;
@@ -120,13 +163,13 @@ impl Ord for OrdMap {
 
 impl fmt::Debug for OrdMap {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0.fmt(f)
+        f.debug_map().entries(self.iter()).finish()
     }
 }
 
 impl Hash for OrdMap {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        for (k, v) in self.0.iter() {
+        for (k, v) in self.sorted() {
             k.hash(state);
             v.hash(state);
         }
@@ -135,43 +178,62 @@ impl Hash for OrdMap {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::BTreeMap;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
 
     use super::OrdMap;
 
-    /// Although this test does nothing but explore the behavior of a built-in data structure, it's
-    /// here to demonstrate the desired characteristic of that data structure. We want attributes to
-    /// serialize in the order that they were inserted.
+    fn hash(map: &OrdMap) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        map.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// We want attributes to serialize in the order that they were inserted.
     #[test]
     fn map_insertion_order() {
-        let mut a = OrdMap(BTreeMap::new());
-        a.mut_map().insert("0".to_string(), String::new());
-        a.mut_map().insert("1".to_string(), String::new());
-        a.mut_map().insert("2".to_string(), String::new());
-        a.mut_map().insert("3".to_string(), String::new());
-        a.mut_map().insert("4".to_string(), String::new());
-        let entries = a.map().keys();
-        for (i, item) in entries.enumerate() {
-            assert_eq!(format!("{i}"), item.to_owned());
+        let mut a = OrdMap::default();
+        for key in &["3", "1", "4", "0", "2"] {
+            a.insert(key.to_string(), String::new());
         }
+        // replacing a value does not move it, removing an entry keeps the order of the others.
+        assert_eq!(
+            Some(String::new()),
+            a.insert("1".to_string(), "x".to_string())
+        );
+        assert_eq!(Some(String::new()), a.remove("4"));
+        assert_eq!(None, a.remove("4"));
+        let keys: Vec<&str> = a.keys().map(|k| k.as_str()).collect();
+        assert_eq!(vec!["3", "1", "0", "2"], keys);
+        assert_eq!(Some(&"x".to_string()), a.get("1"));
+        // the entries after a removed one are still found.
+        assert_eq!(Some("x".to_string()), a.remove("1"));
+        assert_eq!(Some(&String::new()), a.get("2"));
+        a.insert("1".to_string(), "y".to_string());
+        let entries: Vec<(&str, &str)> = a.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        assert_eq!(vec![("3", ""), ("0", ""), ("2", ""), ("1", "y")], entries);
+        let sorted: Vec<&str> = a.sorted().into_iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(vec!["0", "1", "2", "3"], sorted);
     }
 
     /// This test demonstrates that two maps containing the same entries are equal irrespective of
     /// insertion order.
     #[test]
     fn map_equality() {
-        let mut a = OrdMap(BTreeMap::new());
-        a.mut_map().insert("0".to_string(), "a".to_string());
-        a.mut_map().insert("1".to_string(), "b".to_string());
-        a.mut_map().insert("2".to_string(), "c".to_string());
-        a.mut_map().insert("3".to_string(), "d".to_string());
-        a.mut_map().insert("4".to_string(), "e".to_string());
-        let mut b = OrdMap(BTreeMap::new());
-        b.mut_map().insert("4".to_string(), "e".to_string());
-        b.mut_map().insert("3".to_string(), "d".to_string());
-        b.mut_map().insert("1".to_string(), "b".to_string());
-        b.mut_map().insert("2".to_string(), "c".to_string());
-        b.mut_map().insert("0".to_string(), "a".to_string());
+        let mut a = OrdMap::default();
+        a.insert("0".to_string(), "a".to_string());
+        a.insert("1".to_string(), "b".to_string());
+        a.insert("2".to_string(), "c".to_string());
+        a.insert("3".to_string(), "d".to_string());
+        a.insert("4".to_string(), "e".to_string());
+        let mut b = OrdMap::default();
+        b.insert("4".to_string(), "e".to_string());
+        b.insert("3".to_string(), "d".to_string());
+        b.insert("1".to_string(), "b".to_string());
+        b.insert("2".to_string(), "c".to_string());
+        b.insert("0".to_string(), "a".to_string());
         assert_eq!(a, b);
+        assert_eq!(std::cmp::Ordering::Equal, a.cmp(&b));
+        assert_eq!(hash(&a), hash(&b));
     }
 }
@@ -2,6 +2,7 @@ use core::fmt;
 use std::fmt::{Display, Formatter};
 use std::io::{Cursor, Write};
 
+use crate::error::ErrorKind;
 use crate::xdoc::error::{Result, XDocErr};
 use crate::xdoc::WriteOpts;
 
@@ -43,6 +44,7 @@ impl Pi {
         if !pi_str_ok(target.as_ref()) {
             // TODO - improve this error mess
             return Err(XDocErr {
+                kind: ErrorKind::InvalidName,
                 message: format!(
                     "invalid processing instruction target '{}'",
                     target.as_ref()
@@ -55,6 +57,7 @@ impl Pi {
         if !pi_str_ok(data.as_ref()) {
             // TODO - improve this error mess
             return Err(XDocErr {
+                kind: ErrorKind::InvalidDocument,
                 message: format!("invalid processing instruction data '{}'", target.as_ref()),
                 file: file!().into(),
                 line: line!() as u64,
@@ -0,0 +1,45 @@
+use std::fmt::{Display, Formatter};
+
+/// A location in the source text of a parsed document.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialOrd, Ord, PartialEq, Hash)]
+pub struct Location {
+    /// The line, starting from 1.
+    pub line: u64,
+    /// The character within the line, starting from 1.
+    pub column: u64,
+    /// The number of bytes of UTF-8 that come before this location in the document.
+    pub offset: u64,
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// The range of source text that a node of a parsed document was read from. `start` is the
+/// location of the first character of the node and `end` is the location just after its last
+/// character. Spans are only recorded when parsing with [`crate::ParseOptions::spans`].
+///
+/// Text that comes from the replacement text of an entity is located at the entity reference.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialOrd, Ord, PartialEq, Hash)]
+pub struct Span {
+    /// The location of the first character.
+    pub start: Location,
+    /// The location just after the last character.
+    pub end: Location,
+}
+
+impl Span {
+    /// The byte offsets of the span, e.g. for slicing the source text of the node out of the
+    /// document.
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.start.offset as usize..self.end.offset as usize
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
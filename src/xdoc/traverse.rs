@@ -0,0 +1,108 @@
+/*!
+The `traverse` module provides the depth-first walk over the nodes of an element, which the
+`descendants` iterators and the `find` helpers are built on.
+!*/
+
+use crate::{Element, Node};
+
+/// One step of a depth-first traversal. Every node is entered, then its children are traversed,
+/// then it is exited. Nodes other than elements are exited immediately after they are entered.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Edge<T> {
+    /// The traversal reaches the node, before any of its children.
+    Enter(T),
+    /// The traversal leaves the node, after all of its children.
+    Exit(T),
+}
+
+/// An iterator over the descendants of an element, in document order, see [`Element::traverse`].
+/// Nested elements are walked without recursion.
+#[derive(Debug, Clone)]
+pub struct Traverse<'a> {
+    /// The nodes being traversed, outermost first, with the children that have not been entered.
+    /// The outermost entry has no node, it holds the children of the element being traversed.
+    stack: Vec<(Option<&'a Node>, std::slice::Iter<'a, Node>)>,
+}
+
+impl<'a> Traverse<'a> {
+    pub(crate) fn new(element: &'a Element) -> Self {
+        Self {
+            stack: vec![(None, element.nodes())],
+        }
+    }
+}
+
+impl<'a> Iterator for Traverse<'a> {
+    type Item = Edge<&'a Node>;
+
+    fn next(&mut self) -> Option<Edge<&'a Node>> {
+        let (node, children) = self.stack.last_mut()?;
+        match children.next() {
+            Some(child) => {
+                let grandchildren = match child {
+                    Node::Element(element) => element.nodes(),
+                    _ => [].iter(),
+                };
+                self.stack.push((Some(child), grandchildren));
+                Some(Edge::Enter(child))
+            }
+            None => {
+                let node = *node;
+                self.stack.pop();
+                node.map(Edge::Exit)
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// tests
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn traverse_edges() {
+    let doc = crate::parse("<a><b>x<c/></b><!--y--><d/></a>").unwrap();
+    let edges: Vec<String> = doc
+        .root()
+        .traverse()
+        .map(|edge| {
+            let (prefix, node) = match edge {
+                Edge::Enter(node) => ("enter", node),
+                Edge::Exit(node) => ("exit", node),
+            };
+            let name = match node {
+                Node::Element(element) => element.name().to_owned(),
+                Node::Text(text) => text.clone(),
+                Node::Comment(comment) => comment.clone(),
+                _ => String::new(),
+            };
+            format!("{} {}", prefix, name)
+        })
+        .collect();
+    let expected = vec![
+        "enter b", "enter x", "exit x", "enter c", "exit c", "exit b", "enter y", "exit y",
+        "enter d", "exit d",
+    ];
+    assert_eq!(expected, edges);
+}
+
+#[test]
+fn descendants_and_find() {
+    let xml = "<a><b id=\"1\"><c/><b id=\"2\"/></b>text<c><b id=\"3\"/></c></a>";
+    let doc = crate::parse(xml).unwrap();
+    let root = doc.root();
+    let names: Vec<&str> = root.descendants().map(|e| e.name()).collect();
+    assert_eq!(vec!["b", "c", "b", "c", "b"], names);
+    assert_eq!(6, root.descendants_with_nodes().count());
+    let ids: Vec<&str> = root
+        .find_all("b")
+        .filter_map(|b| b.attribute("id"))
+        .map(|id| id.as_str())
+        .collect();
+    assert_eq!(vec!["1", "2", "3"], ids);
+    assert_eq!("1", root.find("b").unwrap().attribute("id").unwrap());
+    assert!(root.find("a").is_none());
+    assert_eq!("a", doc.find("a").unwrap().name());
+    assert_eq!(3, doc.find_all("b").count());
+    assert!(doc.find("z").is_none());
+}
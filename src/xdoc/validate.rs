@@ -0,0 +1,610 @@
+/*!
+The `validate` module checks a `Document` against the element and attribute-list declarations of
+its DTD, as described by the validity constraints of the XML specification.
+!*/
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+use crate::doctype::{
+    AttDef, AttType, AttValue, AttValueData, ChildrenType, ChildrenValue, ContentSpec, CpItem,
+    CpValue, DefaultDecl, EntityDeclValue, EntityDef, EntityValueData, EnumeratedType,
+    MarkupDeclValue, Reference, Repetitions,
+};
+use crate::parser::chars::{is_name_char, is_name_start_char};
+use crate::parser::ElementPath;
+use crate::xdoc::is_whitespace;
+use crate::{DocTypeDecl, Document, Element, Node};
+
+/// A way in which a document does not conform to the declarations of its DTD, and where it was
+/// found.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct DtdViolation {
+    path: String,
+    message: String,
+}
+
+impl DtdViolation {
+    fn new<P: Into<String>, M: Into<String>>(path: P, message: M) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Where the violation was found, given as a path of element names from the root in which a
+    /// name is followed by its position among the siblings of the same name if it is not the
+    /// first, e.g. `/doc/a[2]`, as in [`crate::parser::ParseError::element_path`]. Attributes are
+    /// given by appending `/@name`, e.g. `/doc/a[2]/@id`.
+    pub fn path(&self) -> &str {
+        self.path.as_str()
+    }
+
+    /// A description of the violation.
+    pub fn message(&self) -> &str {
+        self.message.as_str()
+    }
+}
+
+impl Display for DtdViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Checks `doc` against its DTD, returning every violation that is found in document order.
+pub(crate) fn validate(doc: &Document) -> Vec<DtdViolation> {
+    let root_path = format!("/{}", doc.root().fullname());
+    let doctype = match doc.doctype() {
+        Some(doctype) => doctype,
+        None => {
+            return vec![DtdViolation::new(
+                root_path,
+                "the document does not have a DOCTYPE declaration",
+            )]
+        }
+    };
+    let mut validator = Validator::new(doctype);
+    if doctype.name() != doc.root().fullname() {
+        validator.violation(
+            &root_path,
+            format!(
+                "the root element '{}' does not match the DOCTYPE name '{}'",
+                doc.root().fullname(),
+                doctype.name()
+            ),
+        );
+    }
+    validator.element(doc.root(), &root_path);
+    validator.check_idrefs();
+    validator.violations
+}
+
+struct Validator<'a> {
+    doctype: &'a DocTypeDecl,
+    /// The content spec of each declared element. The first declaration is used.
+    elements: HashMap<&'a str, &'a ContentSpec>,
+    /// The attribute definitions of each element. The first definition of an attribute is used.
+    attlists: HashMap<&'a str, Vec<&'a AttDef>>,
+    /// The names of the declared unparsed entities.
+    unparsed: HashSet<&'a str>,
+    /// The values of the ID attributes seen so far.
+    ids: HashSet<String>,
+    /// The values of the IDREF(S) attributes seen so far, and where they were seen.
+    idrefs: Vec<(String, String)>,
+    violations: Vec<DtdViolation>,
+}
+
+impl<'a> Validator<'a> {
+    fn new(doctype: &'a DocTypeDecl) -> Self {
+        let mut validator = Self {
+            doctype,
+            elements: HashMap::new(),
+            attlists: HashMap::new(),
+            unparsed: HashSet::new(),
+            ids: HashSet::new(),
+            idrefs: Vec::new(),
+            violations: Vec::new(),
+        };
+        for decl in doctype.markup_decls() {
+            match decl {
+                MarkupDeclValue::ElementDecl(e) => {
+                    validator
+                        .elements
+                        .entry(e.name())
+                        .or_insert(e.content_spec());
+                }
+                MarkupDeclValue::AttlistDecl(a) => {
+                    let defs = validator.attlists.entry(a.name()).or_default();
+                    for def in a.att_defs() {
+                        if !defs.iter().any(|d| d.name() == def.name()) {
+                            defs.push(def);
+                        }
+                    }
+                }
+                MarkupDeclValue::EntityDecl(EntityDeclValue::GEDecl(ge)) => {
+                    if let EntityDef::External(external) = ge.entity_def() {
+                        if external.ndata().is_some() {
+                            validator.unparsed.insert(ge.name());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        validator
+    }
+
+    fn violation<S: Into<String>>(&mut self, path: &str, message: S) {
+        self.violations.push(DtdViolation::new(path, message));
+    }
+
+    fn element(&mut self, element: &Element, path: &str) {
+        let name = element.fullname();
+        match self.elements.get(name).copied() {
+            None => self.violation(path, format!("the element '{}' is not declared", name)),
+            Some(content_spec) => self.content(element, content_spec, path),
+        }
+        self.attributes(element, path);
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for child in element.children() {
+            let count = counts.entry(child.fullname()).or_insert(0);
+            *count += 1;
+            let child_path = format!("{}/{}", path, ElementPath::step(child.fullname(), *count));
+            self.element(child, &child_path);
+        }
+    }
+
+    fn content(&mut self, element: &Element, content_spec: &ContentSpec, path: &str) {
+        let name = element.fullname();
+        match content_spec {
+            ContentSpec::Empty => {
+                if element.nodes_len() > 0 {
+                    self.violation(
+                        path,
+                        format!("the element '{}' is declared EMPTY but has content", name),
+                    );
+                }
+            }
+            ContentSpec::Any => {}
+            ContentSpec::Mixed(mixed) => {
+                for child in element.children() {
+                    if !mixed.element_names().any(|n| n == child.fullname()) {
+                        self.violation(
+                            path,
+                            format!(
+                                "the element '{}' is not allowed in '{}', which is declared {}",
+                                child.fullname(),
+                                name,
+                                mixed
+                            ),
+                        );
+                    }
+                }
+            }
+            ContentSpec::Children(children) => {
+                // whitespace between the children is allowed, but CDATA sections are not, even
+                // when they only hold whitespace.
+                if element.nodes().any(|n| match n {
+                    Node::Text(text) => !text.chars().all(is_whitespace),
+                    Node::CData(_) => true,
+                    _ => false,
+                }) {
+                    self.violation(
+                        path,
+                        format!(
+                            "the element '{}' may only contain elements, but it contains text",
+                            name
+                        ),
+                    );
+                }
+                let names: Vec<&str> = element.children().map(|c| c.fullname()).collect();
+                if !matches_children(children, &names) {
+                    self.violation(
+                        path,
+                        format!(
+                            "the children of '{}' do not match its declaration {}",
+                            name, children
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    fn attributes(&mut self, element: &Element, path: &str) {
+        let name = element.fullname();
+        let defs = self.attlists.get(name).cloned().unwrap_or_default();
+        for (key, value) in element.attributes() {
+            let attribute_path = format!("{}/@{}", path, key);
+            match defs.iter().find(|d| d.name() == key) {
+                None => self.violation(
+                    &attribute_path,
+                    format!(
+                        "the attribute '{}' is not declared for the element '{}'",
+                        key, name
+                    ),
+                ),
+                Some(def) => self.attribute_value(def, value, &attribute_path),
+            }
+        }
+        for def in defs {
+            let value = element.attribute(def.name());
+            match (def.default_decl(), value) {
+                (DefaultDecl::Required, None) => self.violation(
+                    path,
+                    format!(
+                        "the required attribute '{}' is missing from the element '{}'",
+                        def.name(),
+                        name
+                    ),
+                ),
+                (DefaultDecl::Value(default), Some(value)) if default.is_fixed() => {
+                    let mut fixed = att_value_string(self.doctype, default.att_value());
+                    let mut value = value.clone();
+                    if *def.att_type() != AttType::CData {
+                        fixed = normalize(&fixed);
+                        value = normalize(&value);
+                    }
+                    if fixed != value {
+                        self.violation(
+                            &format!("{}/@{}", path, def.name()),
+                            format!(
+                                "the attribute '{}' must have the fixed value '{}', found '{}'",
+                                def.name(),
+                                fixed,
+                                value
+                            ),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn attribute_value(&mut self, def: &AttDef, value: &str, path: &str) {
+        let value = match def.att_type() {
+            AttType::CData => return,
+            _ => normalize(value),
+        };
+        let tokens: Vec<&str> = value.split(' ').filter(|t| !t.is_empty()).collect();
+        let problem = match def.att_type() {
+            AttType::CData => None,
+            AttType::ID if !is_name(&value) => Some("is not a valid ID"),
+            AttType::ID if !self.ids.insert(value.clone()) => Some("is not a unique ID"),
+            AttType::ID => None,
+            AttType::IDRef | AttType::Entity | AttType::NMToken if tokens.len() != 1 => {
+                Some("must be a single token")
+            }
+            AttType::IDRefs | AttType::Entities | AttType::NMTokens if tokens.is_empty() => {
+                Some("must have at least one token")
+            }
+            AttType::IDRef | AttType::IDRefs if !tokens.iter().all(|t| is_name(t)) => {
+                Some("is not a valid IDREF")
+            }
+            AttType::IDRef | AttType::IDRefs => {
+                for token in tokens {
+                    self.idrefs.push((path.to_owned(), token.to_owned()));
+                }
+                None
+            }
+            AttType::Entity | AttType::Entities
+                if !tokens.iter().all(|t| self.unparsed.contains(t)) =>
+            {
+                Some("does not name a declared unparsed entity")
+            }
+            AttType::Entity | AttType::Entities => None,
+            AttType::NMToken | AttType::NMTokens if !tokens.iter().all(|t| is_nmtoken(t)) => {
+                Some("is not a valid NMTOKEN")
+            }
+            AttType::NMToken | AttType::NMTokens => None,
+            AttType::EnumeratedTypes(EnumeratedType::Enumeration(e))
+                if !e.names().any(|n| n == value) =>
+            {
+                Some("is not one of the enumerated values")
+            }
+            AttType::EnumeratedTypes(EnumeratedType::NotationType(n))
+                if !n.names().any(|n| n == value) =>
+            {
+                Some("is not one of the enumerated notations")
+            }
+            AttType::EnumeratedTypes(_) => None,
+        };
+        if let Some(problem) = problem {
+            self.violation(
+                path,
+                format!(
+                    "the value '{}' of the {} attribute '{}' {}",
+                    value,
+                    def.att_type(),
+                    def.name(),
+                    problem
+                ),
+            );
+        }
+    }
+
+    fn check_idrefs(&mut self) {
+        let idrefs = std::mem::take(&mut self.idrefs);
+        for (path, idref) in idrefs {
+            if !self.ids.contains(&idref) {
+                self.violation(
+                    &path,
+                    format!(
+                        "the IDREF '{}' does not match any ID in the document",
+                        idref
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// Returns `true` if the sequence of element `names` matches the content model `children`.
+fn matches_children(children: &ChildrenValue, names: &[&str]) -> bool {
+    let item = match children.children_type() {
+        ChildrenType::Choice(choice) => CpItemRef::Choice(choice.cps().collect()),
+        ChildrenType::Seq(seq) => CpItemRef::Seq(seq.cps().collect()),
+    };
+    let ends = match_repeated(&item, children.repetitions(), names, 0);
+    ends.contains(&names.len())
+}
+
+/// The part of a content model that is being matched. This lets the top-level choice or sequence of
+/// a `ChildrenValue` be matched in the same way as a nested one.
+enum CpItemRef<'a> {
+    Name(&'a str),
+    Choice(Vec<&'a CpValue>),
+    Seq(Vec<&'a CpValue>),
+}
+
+impl<'a> CpItemRef<'a> {
+    fn from_cp(cp: &'a CpValue) -> Self {
+        match cp.cp_item() {
+            CpItem::Name(name) => CpItemRef::Name(name.as_str()),
+            CpItem::Choice(choice) => CpItemRef::Choice(choice.cps().collect()),
+            CpItem::Seq(seq) => CpItemRef::Seq(seq.cps().collect()),
+        }
+    }
+}
+
+/// Returns every position in `names` at which a match of `item`, starting at `start`, could end.
+fn match_item(item: &CpItemRef<'_>, names: &[&str], start: usize) -> BTreeSet<usize> {
+    match item {
+        CpItemRef::Name(name) => names
+            .get(start)
+            .filter(|&n| n == name)
+            .map(|_| start + 1)
+            .into_iter()
+            .collect(),
+        CpItemRef::Choice(cps) => cps
+            .iter()
+            .flat_map(|cp| match_cp(cp, names, start))
+            .collect(),
+        CpItemRef::Seq(cps) => {
+            let mut ends: BTreeSet<usize> = std::iter::once(start).collect();
+            for cp in cps {
+                ends = ends.iter().flat_map(|&p| match_cp(cp, names, p)).collect();
+            }
+            ends
+        }
+    }
+}
+
+fn match_cp(cp: &CpValue, names: &[&str], start: usize) -> BTreeSet<usize> {
+    match_repeated(&CpItemRef::from_cp(cp), cp.repetitions(), names, start)
+}
+
+fn match_repeated(
+    item: &CpItemRef<'_>,
+    repetitions: Repetitions,
+    names: &[&str],
+    start: usize,
+) -> BTreeSet<usize> {
+    let once = match_item(item, names, start);
+    let mut ends = match repetitions {
+        Repetitions::Once => return once,
+        Repetitions::Optional => {
+            let mut ends = once;
+            ends.insert(start);
+            return ends;
+        }
+        Repetitions::ZeroOrMore => {
+            let mut ends = once;
+            ends.insert(start);
+            ends
+        }
+        Repetitions::OneOrMore => once,
+    };
+    let mut frontier = ends.clone();
+    while !frontier.is_empty() {
+        let next: BTreeSet<usize> = frontier
+            .iter()
+            .flat_map(|&p| match_item(item, names, p))
+            .filter(|p| !ends.contains(p))
+            .collect();
+        ends.extend(next.iter().copied());
+        frontier = next;
+    }
+    ends
+}
+
+/// Builds the string value of an attribute's default from the DTD, replacing references.
+fn att_value_string(doctype: &DocTypeDecl, value: &AttValue) -> String {
+    let mut s = String::new();
+    for data in value.data() {
+        match data {
+            AttValueData::Text(text) => s.push_str(text),
+            AttValueData::Reference(reference) => push_reference(doctype, reference, &mut s, 0),
+        }
+    }
+    s
+}
+
+/// Pushes the replacement text of `reference` onto `s`. References to undeclared or external
+/// entities are left as they are, as are entities that are nested too deeply to be sensible.
+fn push_reference(doctype: &DocTypeDecl, reference: &Reference, s: &mut String, depth: usize) {
+    const MAX_DEPTH: usize = 16;
+    let name = match reference {
+        Reference::CharRef(char_ref) => {
+            if let Some(c) = char_ref.char() {
+                s.push(c);
+            }
+            return;
+        }
+        Reference::EntityRef(entity_ref) => entity_ref.name(),
+    };
+    let predefined = match name {
+        "amp" => Some('&'),
+        "apos" => Some('\''),
+        "gt" => Some('>'),
+        "lt" => Some('<'),
+        "quot" => Some('"'),
+        _ => None,
+    };
+    if let Some(c) = predefined {
+        s.push(c);
+        return;
+    }
+    let value = doctype.markup_decls().find_map(|decl| match decl {
+        MarkupDeclValue::EntityDecl(EntityDeclValue::GEDecl(ge)) if ge.name() == name => {
+            match ge.entity_def() {
+                EntityDef::Entity(value) => Some(value),
+                EntityDef::External(_) => None,
+            }
+        }
+        _ => None,
+    });
+    match value {
+        Some(value) if depth < MAX_DEPTH => {
+            for data in value.data() {
+                match data {
+                    EntityValueData::String(text) => s.push_str(text),
+                    EntityValueData::Reference(r) => push_reference(doctype, r, s, depth + 1),
+                    EntityValueData::PEReference(_) => {}
+                }
+            }
+        }
+        _ => s.push_str(&format!("&{};", name)),
+    }
+}
+
+/// Trims the value and collapses runs of whitespace into a single space, as is done for attributes
+/// that are not of type `CDATA`.
+fn normalize(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if is_name_start_char(c) => chars.all(is_name_char),
+        _ => false,
+    }
+}
+
+fn is_nmtoken(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(is_name_char)
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// tests
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+fn violations(xml: &str) -> Vec<String> {
+    crate::parse(xml)
+        .unwrap()
+        .validate_dtd()
+        .iter()
+        .map(|v| v.path().to_owned())
+        .collect()
+}
+
+#[test]
+fn validate_no_doctype() {
+    assert_eq!(vec!["/doc"], violations("<doc/>"));
+}
+
+#[test]
+fn validate_content_models() {
+    let dtd = r#"<!DOCTYPE doc [
+<!ELEMENT doc (head?, (a | b)*, foot+)>
+<!ELEMENT head (#PCDATA)>
+<!ELEMENT a EMPTY>
+<!ELEMENT b (#PCDATA | a)*>
+<!ELEMENT foot ANY>
+]>"#;
+    let valid = format!(
+        "{}<doc><head>h</head><a/><b>x<a/></b><a/><foot><a/></foot><foot/></doc>",
+        dtd
+    );
+    assert!(violations(&valid).is_empty());
+    let no_foot = format!("{}<doc><a/></doc>", dtd);
+    assert_eq!(vec!["/doc"], violations(&no_foot));
+    let text_in_elements = format!("{}<doc>text<foot/></doc>", dtd);
+    assert_eq!(vec!["/doc"], violations(&text_in_elements));
+    let bad_children = format!("{}<doc><foot/><b><head/></b><a>x</a><foot/></doc>", dtd);
+    assert_eq!(vec!["/doc", "/doc/b", "/doc/a"], violations(&bad_children));
+    let undeclared = format!("{}<doc><foot><zz/></foot></doc>", dtd);
+    assert_eq!(vec!["/doc/foot/zz"], violations(&undeclared));
+    let wrong_root = format!("{}<foot/>", dtd);
+    assert_eq!(vec!["/foot"], violations(&wrong_root));
+}
+
+#[test]
+fn validate_attributes() {
+    let dtd = r#"<!DOCTYPE doc [
+<!ELEMENT doc (item*)>
+<!ELEMENT item EMPTY>
+<!ATTLIST doc version CDATA #FIXED "1 &amp; 2">
+<!ATTLIST item
+    id ID #REQUIRED
+    ref IDREF #IMPLIED
+    size (small | large) "small"
+    tags NMTOKENS #IMPLIED>
+]>"#;
+    let valid = format!(
+        r#"{}<doc version="1 &amp; 2"><item id="a" size="large" tags=" x y "/><item id="b" ref="a"/></doc>"#,
+        dtd
+    );
+    assert!(violations(&valid).is_empty());
+    let invalid = format!(
+        r#"{}<doc version="2" other="x"><item id="a" ref="zz" size="medium" tags="!"/><item id="a"/><item/></doc>"#,
+        dtd
+    );
+    let got = crate::parse(invalid).unwrap().validate_dtd();
+    let paths: Vec<&str> = got.iter().map(|v| v.path()).collect();
+    assert_eq!(
+        vec![
+            "/doc/@other",
+            "/doc/@version",
+            "/doc/item/@size",
+            "/doc/item/@tags",
+            "/doc/item[2]/@id",
+            "/doc/item[3]",
+            "/doc/item/@ref",
+        ],
+        paths
+    );
+    assert_eq!(
+        "/doc/item[2]/@id: the value 'a' of the ID attribute 'id' is not a unique ID",
+        got[4].to_string()
+    );
+}
+
+#[test]
+fn validate_whitespace_in_element_content() {
+    use crate::{ParseOptions, WhitespacePolicy};
+    let validate = |xml: &str| {
+        let opts = ParseOptions::new().whitespace(WhitespacePolicy::Preserve);
+        crate::parse_with(xml, &opts).unwrap().validate_dtd()
+    };
+    let dtd = "<!DOCTYPE doc [\n<!ELEMENT doc (a)>\n<!ELEMENT a EMPTY>\n]>";
+    assert!(validate(&format!("{}<doc>\n  <a/>\n</doc>", dtd)).is_empty());
+    assert_eq!(1, validate(&format!("{}<doc> x <a/></doc>", dtd)).len());
+    assert_eq!(
+        1,
+        validate(&format!("{}<doc><![CDATA[ ]]><a/></doc>", dtd)).len()
+    );
+}
@@ -1,6 +1,9 @@
 use std::io::Write;
 
+use crate::error::ErrorKind;
+use crate::parser::chars::{is_restricted_char, LINE_SEPARATOR, NEXT_LINE};
 use crate::xdoc::error::Result;
+use crate::Version;
 
 /// The type of newline character to use when writing the XML Document
 #[derive(Debug, Default, Clone, Eq, PartialOrd, PartialEq, Hash)]
@@ -28,6 +31,30 @@ impl Default for Indent {
     }
 }
 
+/// How elements that contain text are written.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialOrd, PartialEq, Hash)]
+pub enum MixedContent {
+    /// Every node that is not text is written on its own line and indented, which changes the
+    /// text of elements that mix text with other nodes.
+    #[default]
+    Indent,
+    /// The content of an element that contains text, including that of its descendants, is written
+    /// without adding newlines or indentation. This keeps text that was parsed with
+    /// `WhitespacePolicy::Preserve` unchanged.
+    Preserve,
+}
+
+/// The order that the attributes of an element are written in.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialOrd, PartialEq, Hash)]
+pub enum AttributeOrder {
+    /// Attributes are written in the order that they were parsed or added, so that a document
+    /// round-trips without reordering its attributes.
+    #[default]
+    Source,
+    /// Attributes are written sorted by name.
+    Sorted,
+}
+
 /// Options for controlling how the XML Document is written when serialized.
 #[derive(Debug, Clone, Eq, PartialOrd, PartialEq, Hash, Default)]
 pub struct WriteOpts {
@@ -35,9 +62,31 @@ pub struct WriteOpts {
     pub indent: Indent,
     /// The type of newline to use when writing the document.
     pub newline: Newline,
+    /// Whether the content of elements that contain text is indented.
+    pub mixed_content: MixedContent,
+    /// The XML version that text and attribute values are escaped for. In XML 1.1, control
+    /// characters and the line ends `U+0085` and `U+2028` are written as character references.
+    /// When a `Document` is written, the version of its `Declaration` is used instead.
+    pub version: Version,
+    /// The order that the attributes of each element are written in.
+    pub attribute_order: AttributeOrder,
 }
 
 impl WriteOpts {
+    /// The options to use for the content of an element that contains text.
+    pub(crate) fn for_mixed_content(&self) -> Option<Self> {
+        match self.mixed_content {
+            MixedContent::Indent => None,
+            MixedContent::Preserve => Some(Self {
+                indent: Indent::None,
+                newline: Newline::None,
+                mixed_content: MixedContent::Preserve,
+                version: self.version,
+                attribute_order: self.attribute_order,
+            }),
+        }
+    }
+
     fn newline_str(&self) -> &'static str {
         match self.newline {
             Newline::None => "",
@@ -81,7 +130,22 @@ impl WriteOpts {
     }
 }
 
-pub(crate) fn write_attribute_value<W, S>(s: S, writer: &mut W, _opts: &WriteOpts) -> Result<()>
+/// Writes `c` as a character reference if the XML version of `opts` requires it. Returns `false`
+/// if `c` has not been written.
+fn write_version_escape<W>(c: char, writer: &mut W, opts: &WriteOpts) -> Result<bool>
+where
+    W: Write,
+{
+    if opts.version == Version::V11
+        && (is_restricted_char(c) || c == NEXT_LINE || c == LINE_SEPARATOR)
+    {
+        better_wrap!(ErrorKind::Io, write!(writer, "&#x{:X};", c as u32))?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+pub(crate) fn write_attribute_value<W, S>(s: S, writer: &mut W, opts: &WriteOpts) -> Result<()>
 where
     W: Write,
     S: AsRef<str>,
@@ -89,12 +153,15 @@ where
     // TODO - support single quoted attributes https://github.com/webern/exile/issues/45
     // TODO - support additional escapes https://github.com/webern/exile/issues/44
     for c in s.as_ref().chars() {
+        if write_version_escape(c, writer, opts)? {
+            continue;
+        }
         match c {
-            '<' => better_wrap!(write!(writer, "&lt;"))?,
-            '>' => better_wrap!(write!(writer, "&gt;"))?,
-            '&' => better_wrap!(write!(writer, "&amp;"))?,
-            '"' => better_wrap!(write!(writer, "&quot;"))?,
-            _ => better_wrap!(write!(writer, "{}", c))?,
+            '<' => better_wrap!(ErrorKind::Io, write!(writer, "&lt;"))?,
+            '>' => better_wrap!(ErrorKind::Io, write!(writer, "&gt;"))?,
+            '&' => better_wrap!(ErrorKind::Io, write!(writer, "&amp;"))?,
+            '"' => better_wrap!(ErrorKind::Io, write!(writer, "&quot;"))?,
+            _ => better_wrap!(ErrorKind::Io, write!(writer, "{}", c))?,
         }
     }
     Ok(())
@@ -104,7 +171,7 @@ where
 pub(crate) fn write_element_text<W, S>(
     s: S,
     writer: &mut W,
-    _opts: &WriteOpts,
+    opts: &WriteOpts,
     _depth: usize,
 ) -> Result<()>
 where
@@ -113,12 +180,15 @@ where
 {
     // TODO - support additional escapes https://github.com/webern/exile/issues/44
     for c in s.as_ref().chars() {
+        if write_version_escape(c, writer, opts)? {
+            continue;
+        }
         match c {
-            '<' => better_wrap!(write!(writer, "&lt;"))?,
-            '>' => better_wrap!(write!(writer, "&gt;"))?,
-            '&' => better_wrap!(write!(writer, "&amp;"))?,
-            '\u{a0}' => better_wrap!(write!(writer, "&#xA0;"))?,
-            _ => better_wrap!(write!(writer, "{}", c))?,
+            '<' => better_wrap!(ErrorKind::Io, write!(writer, "&lt;"))?,
+            '>' => better_wrap!(ErrorKind::Io, write!(writer, "&gt;"))?,
+            '&' => better_wrap!(ErrorKind::Io, write!(writer, "&amp;"))?,
+            '\u{a0}' => better_wrap!(ErrorKind::Io, write!(writer, "&#xA0;"))?,
+            _ => better_wrap!(ErrorKind::Io, write!(writer, "{}", c))?,
         }
     }
     Ok(())
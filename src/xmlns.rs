@@ -1,8 +1,17 @@
 use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::{TryFrom, TryInto};
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::Element;
+
+/// The namespace that the `xml` prefix is bound to.
+pub(crate) const XML_NS_URI: &str = "http://www.w3.org/XML/1998/namespace";
+/// The namespace of `xmlns` attributes, i.e. namespace declarations.
+pub(crate) const XMLNS_URI: &str = "http://www.w3.org/2000/xmlns/";
 
 /// The error type for errors related to the parsing of namespace strings.
 #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Hash)]
@@ -12,6 +21,8 @@ pub enum NsErr {
     InvalidStart(char),
     InvalidChar(char),
     Malformed(&'static str),
+    UnboundPrefix(String),
+    ConflictingPrefix(String),
 }
 
 impl Display for NsErr {
@@ -21,6 +32,13 @@ impl Display for NsErr {
             NsErr::InvalidStart(c) => write!(f, "invalid start char '{c}'"),
             NsErr::InvalidChar(c) => write!(f, "invalid char '{c}'"),
             NsErr::Malformed(msg) => write!(f, "malformed: {msg}"),
+            NsErr::UnboundPrefix(prefix) => write!(f, "the prefix '{prefix}' is not bound"),
+            NsErr::ConflictingPrefix(prefix) => {
+                write!(
+                    f,
+                    "the prefix '{prefix}' is bound to more than one namespace"
+                )
+            }
         }
     }
 }
@@ -147,7 +165,7 @@ impl QName<'_> {
     fn parse<S: AsRef<str>>(value: S) -> std::result::Result<Option<usize>, NsErr> {
         let s = value.as_ref();
         let mut colon_index = None;
-        for (ix, c) in s.chars().enumerate() {
+        for (ix, c) in s.char_indices() {
             if c == ':' {
                 if colon_index.is_some() {
                     return Err(NsErr::Malformed("more than one colon encountered in QName"));
@@ -158,7 +176,7 @@ impl QName<'_> {
         }
 
         if let Some(colon) = colon_index {
-            let prefix = &s[..colon - 1];
+            let prefix = &s[..colon];
             let local = &s[colon + 1..];
             validate_ncname(prefix)?;
             validate_ncname(local)?;
@@ -278,6 +296,299 @@ impl Namespace {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// ExpandedName
+
+/// https://www.w3.org/TR/xml-names/#dt-expname
+///
+/// The namespace URI and local name that together identify an element or attribute, regardless of
+/// the prefix that was used in the document. It is displayed in Clark notation, e.g.
+/// `{http://example.com}root`.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Hash)]
+pub struct ExpandedName<'a> {
+    namespace_uri: Option<&'a str>,
+    local: &'a str,
+}
+
+impl<'a> ExpandedName<'a> {
+    pub(crate) fn new(namespace_uri: Option<&'a str>, local: &'a str) -> Self {
+        Self {
+            namespace_uri,
+            local,
+        }
+    }
+
+    /// The namespace URI, or `None` if the name is not in a namespace.
+    pub fn namespace_uri(&self) -> Option<&'a str> {
+        self.namespace_uri
+    }
+
+    /// The local part of the name, i.e. the name without its prefix.
+    pub fn local(&self) -> &'a str {
+        self.local
+    }
+}
+
+impl Display for ExpandedName<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.namespace_uri {
+            Some(uri) => write!(f, "{{{}}}{}", uri, self.local),
+            None => Display::fmt(self.local, f),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+// Resolution
+
+/// The namespace URI that each prefix in scope is bound to. The default namespace is held under the
+/// empty prefix, where an empty URI means that there is no default namespace.
+pub(crate) type Bindings = Arc<BTreeMap<String, String>>;
+
+/// The bindings that are in scope before any are declared, i.e. just the `xml` prefix.
+pub(crate) fn initial_bindings() -> Bindings {
+    let mut bindings = BTreeMap::new();
+    bindings.insert("xml".to_owned(), XML_NS_URI.to_owned());
+    Arc::new(bindings)
+}
+
+/// Adds the namespaces declared by the `xmlns` attributes of `element`, and those that it was
+/// created in, to the `parent` bindings, and checks that the element and attribute names only use
+/// prefixes that are in scope.
+pub(crate) fn resolve(element: &Element, parent: &Bindings) -> Result<Bindings, NsErr> {
+    let explicit = explicit_declarations(element)?;
+    let mut declared: Option<BTreeMap<String, String>> = None;
+    for &(prefix, uri) in explicit.iter().chain(required(element, &explicit)?.iter()) {
+        if prefix != "xml" {
+            declared
+                .get_or_insert_with(|| parent.as_ref().clone())
+                .insert(prefix.to_owned(), uri.to_owned());
+        }
+    }
+    let bindings = declared.map(Arc::new).unwrap_or_else(|| parent.clone());
+    if let (Some(prefix), _) = split(element.fullname())? {
+        lookup(&bindings, prefix)?;
+    }
+    let mut expanded = Vec::new();
+    for key in element.attribute_keys() {
+        match split(key)? {
+            (None, "xmlns") | (Some("xmlns"), _) | (None, _) => {}
+            (Some(prefix), local) => expanded.push((lookup(&bindings, prefix)?, local)),
+        }
+    }
+    expanded.sort_unstable();
+    if expanded.windows(2).any(|w| w[0] == w[1]) {
+        return Err(NsErr::Malformed(
+            "two attributes have the same namespace and local name",
+        ));
+    }
+    Ok(bindings)
+}
+
+/// Returns the namespace declarations that must be written on `element`, in addition to its
+/// `xmlns` attributes, given the bindings in `scope` for its parent and the namespaces that are
+/// `used` in its content. Also returns the bindings in scope for its content.
+///
+/// The namespaces that `element` was created in are declared on it if they are not already in
+/// scope. So that each declaration is written once, at the highest sensible ancestor, a prefixed
+/// namespace is also declared on `element` if every use of the prefix in its content is for that
+/// namespace. Default namespaces are only declared on the elements that are in them, so that
+/// unprefixed ancestors are not moved into the namespace.
+pub(crate) fn declarations(
+    element: &Element,
+    used: &Uses<'_>,
+    scope: &Bindings,
+) -> Result<(Vec<(String, String)>, Bindings), NsErr> {
+    let explicit = explicit_declarations(element)?;
+    let required = required(element, &explicit)?;
+    let unqualified: BTreeSet<&str> = prefixes(element)?
+        .into_iter()
+        .filter(|&prefix| !required.iter().any(|&(p, _)| p == prefix))
+        .collect();
+    let hoisted = used
+        .content
+        .iter()
+        .filter_map(|(&prefix, uris)| match uris.iter().next() {
+            Some(&Some(uri))
+                if uris.len() == 1 && !prefix.is_empty() && !unqualified.contains(prefix) =>
+            {
+                Some((prefix, uri))
+            }
+            _ => None,
+        });
+    let mut wanted: BTreeMap<&str, &str> = hoisted.collect();
+    wanted.extend(required);
+    let mut declarations = Vec::new();
+    for (prefix, uri) in wanted {
+        let in_scope = scope.get(prefix).map(|s| s.as_str()).unwrap_or("");
+        if prefix != "xml" && in_scope != uri && !explicit.iter().any(|&(p, _)| p == prefix) {
+            declarations.push((prefix.to_owned(), uri.to_owned()));
+        }
+    }
+    if explicit.is_empty() && declarations.is_empty() {
+        return Ok((declarations, scope.clone()));
+    }
+    let mut bindings = scope.as_ref().clone();
+    for &(prefix, uri) in &explicit {
+        bindings.insert(prefix.to_owned(), uri.to_owned());
+    }
+    for (prefix, uri) in &declarations {
+        bindings.insert(prefix.clone(), uri.clone());
+    }
+    Ok((declarations, Arc::new(bindings)))
+}
+
+/// The namespace that each prefix is used for, where `None` means that the prefix is used in a name
+/// that was not created in a namespace.
+type PrefixUses<'a> = BTreeMap<&'a str, BTreeSet<Option<&'a str>>>;
+
+/// The namespaces that are used in the content of an element and of each of its descendant
+/// elements, which are collected in one pass over the tree before it is written.
+#[derive(Debug, Default)]
+pub(crate) struct Uses<'a> {
+    /// The uses in the content of the element. Prefixes that are declared by an `xmlns` attribute
+    /// inside of the content are not visible here.
+    content: PrefixUses<'a>,
+    /// The uses of each child element, in order.
+    children: Vec<Uses<'a>>,
+}
+
+impl<'a> Uses<'a> {
+    /// Collects the uses for `element` and its descendants. Returns `None` if no element in the
+    /// tree was created in a namespace, in which case no declarations need to be written.
+    pub(crate) fn collect(element: &'a Element) -> Result<Option<Self>, NsErr> {
+        let authored = std::iter::once(element)
+            .chain(element.descendants())
+            .any(|e| e.authored_namespaces().next().is_some());
+        if !authored {
+            return Ok(None);
+        }
+        Ok(Some(Self::collect_tree(element)?.0))
+    }
+
+    /// The uses of the child element at `index`, counting only elements.
+    pub(crate) fn child(&self, index: usize) -> &Self {
+        &self.children[index]
+    }
+
+    /// Collects the uses for `element` and its descendants, bottom up. Also returns the uses in the
+    /// subtree of `element` that are visible outside of it.
+    fn collect_tree(element: &'a Element) -> Result<(Self, PrefixUses<'a>), NsErr> {
+        let mut uses = Uses::default();
+        for child in element.children() {
+            let (child_uses, visible) = Self::collect_tree(child)?;
+            for (prefix, uris) in visible {
+                uses.content.entry(prefix).or_default().extend(uris);
+            }
+            uses.children.push(child_uses);
+        }
+        let mut visible = uses.content.clone();
+        let explicit = explicit_declarations(element)?;
+        let required = required(element, &explicit)?;
+        for &(prefix, uri) in &required {
+            visible.entry(prefix).or_default().insert(Some(uri));
+        }
+        for prefix in prefixes(element)? {
+            if !required.iter().any(|&(p, _)| p == prefix) {
+                visible.entry(prefix).or_default().insert(None);
+            }
+        }
+        for (prefix, _) in explicit {
+            visible.remove(prefix);
+        }
+        Ok((uses, visible))
+    }
+}
+
+/// Checks that `prefix` may be bound to `uri`, where an empty `prefix` is the default namespace.
+pub(crate) fn check_binding(prefix: &str, uri: &str) -> Result<(), NsErr> {
+    match (prefix, uri) {
+        ("xmlns", _) => Err(NsErr::Malformed("the 'xmlns' prefix cannot be declared")),
+        ("xml", XML_NS_URI) => Ok(()),
+        ("xml", _) | (_, XML_NS_URI) | (_, XMLNS_URI) => Err(NsErr::Malformed(
+            "the 'xml' and 'xmlns' namespaces cannot be bound to another prefix",
+        )),
+        (prefix, "") if !prefix.is_empty() => {
+            Err(NsErr::Malformed("a prefix cannot be bound to an empty URI"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The namespaces declared by the `xmlns` attributes of `element`, as (prefix, URI) pairs.
+fn explicit_declarations(element: &Element) -> Result<Vec<(&str, &str)>, NsErr> {
+    let mut explicit = Vec::new();
+    for (key, value) in element.attributes() {
+        let prefix = match split(key)? {
+            (None, "xmlns") => "",
+            (Some("xmlns"), prefix) => prefix,
+            _ => continue,
+        };
+        check_binding(prefix, value)?;
+        explicit.push((prefix, value.as_str()));
+    }
+    Ok(explicit)
+}
+
+/// The namespaces that `element` and its attributes were created in, as (prefix, URI) pairs, which
+/// must agree with its `explicit` declarations.
+fn required<'a>(
+    element: &'a Element,
+    explicit: &[(&str, &str)],
+) -> Result<Vec<(&'a str, &'a str)>, NsErr> {
+    let mut required = Vec::new();
+    for (prefix, uri) in element.authored_namespaces() {
+        if explicit.iter().any(|&(p, u)| p == prefix && u != uri) {
+            return Err(NsErr::ConflictingPrefix(prefix.to_owned()));
+        }
+        required.push((prefix, uri));
+    }
+    Ok(required)
+}
+
+/// The prefixes used by the name and attribute names of `element`, other than `xmlns`.
+fn prefixes(element: &Element) -> Result<BTreeSet<&str>, NsErr> {
+    let mut prefixes = BTreeSet::new();
+    if let (Some(prefix), _) = split(element.fullname())? {
+        prefixes.insert(prefix);
+    }
+    for key in element.attribute_keys() {
+        match split(key)? {
+            (Some("xmlns"), _) => {}
+            (Some(prefix), _) => {
+                prefixes.insert(prefix);
+            }
+            (None, _) => {}
+        }
+    }
+    Ok(prefixes)
+}
+
+/// Returns the URI that `prefix` is bound to, or an error if it is not bound.
+pub(crate) fn lookup<'a>(bindings: &'a Bindings, prefix: &str) -> Result<&'a str, NsErr> {
+    match bindings.get(prefix) {
+        Some(uri) if !uri.is_empty() => Ok(uri.as_str()),
+        _ => Err(NsErr::UnboundPrefix(prefix.to_owned())),
+    }
+}
+
+/// Splits a name into its prefix and local part, checking that neither is empty.
+pub(crate) fn split(name: &str) -> Result<(Option<&str>, &str), NsErr> {
+    match name.find(':') {
+        None => Ok((None, name)),
+        Some(pos) => {
+            let (prefix, local) = (&name[..pos], &name[pos + 1..]);
+            if prefix.is_empty() || local.is_empty() || local.contains(':') {
+                Err(NsErr::Malformed(
+                    "a name must be a local name optionally preceded by a prefix and a colon",
+                ))
+            } else {
+                Ok((Some(prefix), local))
+            }
+        }
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // Character Sets
 
@@ -286,7 +597,7 @@ fn validate_ncname<S: AsRef<str>>(value: S) -> std::result::Result<(), NsErr> {
     if value.is_empty() {
         return Err(NsErr::Empty);
     }
-    let mut first = false;
+    let mut first = true;
     for c in value.chars() {
         if first {
             if !is_letter(c) && c != '_' {
@@ -649,3 +960,35 @@ fn is_digit(c: char) -> bool {
 
 #[test]
 fn uri_test() {}
+
+#[test]
+fn qname_test() {
+    let qname = QName::new("ns:root").unwrap();
+    assert_eq!(Some("ns"), qname.prefix());
+    assert_eq!("root", qname.local());
+    assert!(QName::new("a:b:c").is_err());
+    assert!(NcName::new("1abc").is_err());
+}
+
+#[test]
+fn resolve_test() {
+    let mut element = Element::from_name("a:root");
+    element.add_attribute("xmlns", "http://default");
+    element.add_attribute("xmlns:a", "http://a");
+    element.add_attribute("a:x", "1");
+    let bindings = resolve(&element, &initial_bindings()).unwrap();
+    assert_eq!("http://a", lookup(&bindings, "a").unwrap());
+    assert_eq!("http://default", lookup(&bindings, "").unwrap());
+    assert_eq!(XML_NS_URI, lookup(&bindings, "xml").unwrap());
+
+    let mut child = Element::from_name("b:child");
+    assert_eq!(
+        NsErr::UnboundPrefix("b".to_owned()),
+        resolve(&child, &bindings).unwrap_err()
+    );
+    child.set_fullname("child").unwrap();
+    child.add_attribute("a:y", "1");
+    child.add_attribute("xmlns:c", "http://a");
+    child.add_attribute("c:y", "2");
+    assert!(resolve(&child, &bindings).is_err());
+}
@@ -110,6 +110,15 @@ import java.nio.file.Path;
         return getPrefix().equals(ExileConstants.EXILE);
     }
 
+    /**
+     * Returns true if this test can be run without external entities or namespace processing, i.e. the document
+     * is well-formed or not on its own.
+     */
+    boolean isStandalone() {
+        boolean ns = recommendation == Recommendation.NS1_0 || recommendation == Recommendation.NS1_1;
+        return entities == Entities.None && namespaces == XNamespaces.OFF && !ns;
+    }
+
     /**
      * True if there is an XML file that represents the expected serialization outcome for a test
      *
@@ -20,8 +20,6 @@ import java.util.regex.Pattern;
 class ConfTestGenerator {
     /// The maximum number of W3C tests of ConfType.Valid that will be generated.
     private static final int MAX_VALID = 20;
-    /// The maximum number of W3C tests of ConfType.NotWellFormed that will be generated.
-    private static final int MAX_NOT_WELL_FORMED = 5;
     /// The tests directory, e.g. exile_repo/exile/tests
     private final File outDir;
     /// The root of the generated tests, e.g. exile_repo/exile/tests/generated
@@ -36,8 +34,6 @@ class ConfTestGenerator {
     private final File modRs;
     /// The number of W3C tests of ConfType.Valid that have been generated.
     private int validTestCount;
-    /// The number of W3C tests of ConfType.NotWellFormed that have been generated.
-    private int notWellFormedTestCount;
 
     ConfTestGenerator(List<ConfTest> tests, ProgramOptions opts) throws TestGenException {
         outDir = F.canonicalize(opts.getXmlOutdir());
@@ -57,6 +53,8 @@ class ConfTestGenerator {
         private final String version;
         @Getter
         private final String encoding;
+        @Getter
+        private final String standalone;
 
         boolean hasVersion() {
             return version != null && version.length() == 3;
@@ -66,6 +64,10 @@ class ConfTestGenerator {
             return encoding != null && encoding.length() > 0;
         }
 
+        boolean hasStandalone() {
+            return standalone != null && standalone.length() > 0;
+        }
+
         XmlVersion getVersion() throws TestGenException {
             if (!hasVersion()) {
                 throw new TestGenException("function only works when there is a version string");
@@ -163,7 +165,8 @@ class ConfTestGenerator {
             case Valid:
                 return validTestCount >= MAX_VALID;
             case NotWellFormed:
-                return notWellFormedTestCount >= MAX_NOT_WELL_FORMED;
+                // we generate all of the standalone W3C not-well-formed tests
+                return !t.isStandalone();
             case Error:
             case Invalid:
             default:
@@ -177,12 +180,10 @@ class ConfTestGenerator {
             return;
         }
         switch (t.getConfType()) {
-            case NotWellFormed:
-                notWellFormedTestCount++;
-                break;
             case Valid:
                 validTestCount++;
                 break;
+            case NotWellFormed:
             case Error:
             case Invalid:
             default:
@@ -275,6 +276,10 @@ class ConfTestGenerator {
         F.writeln(os, "");
         writeTestFunction(t, os);
         F.writeln(os, "");
+        if (!t.isExileTest()) {
+            writeValidityTestFunction(t, os);
+            F.writeln(os, "");
+        }
         if (t.hasOutputFile()) {
             writeSerializationTestFunction(t, os);
         }
@@ -315,15 +320,23 @@ class ConfTestGenerator {
     private static void writeUseStatements(ConfTest t,
                                            FoundDecl foundDecl,
                                            OutputStreamWriter os) throws TestGenException {
+        List<String> utils = new ArrayList<>();
         if (t.hasOutputFile()) {
-            F.writeln(os, "use crate::test_utils::{run_output_test, run_parse_test};");
+            utils.add("run_output_test");
+        }
+        utils.add("run_parse_test");
+        if (!t.isExileTest()) {
+            utils.add("run_validity_test");
+        }
+        if (utils.size() == 1) {
+            F.writeln(os, "use crate::test_utils::%s;", utils.get(0));
         } else {
-            F.writeln(os, "use crate::test_utils::run_parse_test;");
+            F.writeln(os, "use crate::test_utils::{%s};", String.join(", ", utils));
         }
         List<String> structs = new ArrayList<>();
         structs.add("Document");
-        structs.add("Declaration");
         if (foundDecl.hasVersion()) {
+            structs.add("Declaration");
             structs.add("Version");
         }
         if (foundDecl.hasEncoding()) {
@@ -365,6 +378,14 @@ class ConfTestGenerator {
     }
 
 
+    private static void writeValidityTestFunction(ConfTest t, OutputStreamWriter os) throws TestGenException {
+        F.writeln(os, "#[test]");
+        F.writeln(os, "/// Check that the document is valid according to its DTD.");
+        F.writeln(os, "fn %s_validate() {", t.getSnakeCase());
+        F.writeln(os, "    run_validity_test(INPUT_FILE);");
+        F.writeln(os, "}");
+    }
+
     private static void writeSerializationTestFunction(ConfTest t, OutputStreamWriter os) throws TestGenException {
         F.writeln(os, "#[test]");
         F.writeln(os, "/// Check that the serialization of this XML document matches what we expect.");
@@ -384,21 +405,21 @@ class ConfTestGenerator {
         writeExpectedXmlDeclaration(foundDecl, os);
         List<Node> prelude = findPrelude(doc);
         List<Node> postlude = findPostlude(doc);
-        DocumentType doctype = doc.getDoctype();
-        if (doctype != null) {
-            writeExpectedDoctype(doctype, os, foundDoctype);
-        }
-        writeExpectedPrelude(prelude, os);
+        writeExpectedPrelude(prelude, os, foundDoctype);
         writeExpectedContents(t, doc, os);
         writeExpectedPostlude(postlude, os);
         F.writeln(os, "doc");
         F.writeln(os, "}");
     }
 
-    private static void writeExpectedPrelude(List<Node> prelude, OutputStreamWriter os) throws TestGenException {
+    private static void writeExpectedPrelude(List<Node> prelude,
+                                             OutputStreamWriter os,
+                                             String foundDoctype) throws TestGenException {
         for (Node node : prelude) {
             XType xtype = XType.fromNode(node);
-            if (xtype == XType.ProcessingInstruction) {
+            if (xtype == XType.DocumentType) {
+                writeExpectedDoctype((DocumentType) node, os, foundDoctype);
+            } else if (xtype == XType.ProcessingInstruction) {
                 ProcessingInstruction piNode = (ProcessingInstruction) node;
                 PI pi = parseProcessingInstruction(piNode);
                 F.write(os, "doc.add_prolog_pi(");
@@ -661,24 +682,38 @@ class ConfTestGenerator {
 
     private static void writeExpectedXmlDeclaration(FoundDecl foundDecl,
                                                     OutputStreamWriter os) throws TestGenException {
-        String rsVersion = "None";
-        if (foundDecl.hasVersion()) {
-            XmlVersion version = foundDecl.getVersion();
-            if (version == XmlVersion.V10) {
-                rsVersion = "Some(Version::V10)";
-            } else if (version == XmlVersion.V11) {
-                rsVersion = "Some(Version::V11)";
-            }
+        // a document without an XML declaration has no Declaration, and every declaration has a version.
+        if (!foundDecl.hasVersion()) {
+            return;
+        }
+        String rsVersion = "Version::V10";
+        if (foundDecl.getVersion() == XmlVersion.V11) {
+            rsVersion = "Version::V11";
         }
         String rsEncoding = "None";
         if (foundDecl.hasEncoding()) {
-            if (foundDecl.getEncoding().equals("UTF-8")) {
+            String encoding = foundDecl.getEncoding();
+            if (encoding.equalsIgnoreCase("UTF-8")) {
                 rsEncoding = "Some(Encoding::Utf8)";
+            } else if (encoding.equalsIgnoreCase("UTF-16")) {
+                rsEncoding = "Some(Encoding::Utf16Be)";
+            } else if (encoding.equalsIgnoreCase("ISO-8859-1")) {
+                rsEncoding = "Some(Encoding::Iso8859_1)";
+            } else if (encoding.equalsIgnoreCase("windows-1252")) {
+                rsEncoding = "Some(Encoding::Windows1252)";
             } else {
                 throw new TestGenException("Unsupported XML encoding parsed: " + foundDecl.getEncoding());
             }
         }
-        F.writeln(os, "doc.set_declaration(Declaration{ version: %s, encoding: %s });", rsVersion, rsEncoding);
+        String rsStandalone = "None";
+        if (foundDecl.hasStandalone()) {
+            rsStandalone = foundDecl.getStandalone().equals("yes") ? "Some(true)" : "Some(false)";
+        }
+        F.writeln(os,
+                "doc.set_declaration(Declaration{ version: %s, encoding: %s, standalone: %s });",
+                rsVersion,
+                rsEncoding,
+                rsStandalone);
     }
 
     private static void writeExpectedDoctype(DocumentType dt,
@@ -690,7 +725,6 @@ class ConfTestGenerator {
         if (fromRegex == null || fromRegex.isEmpty()) {
             return;
         }
-        F.writeln(os, "// TODO - support doctype https://github.com/webern/exile/issues/22");
         F.writeln(os, "doc.set_doctype(");
         F.writeln(os, "%s", rustStringLiteral(fromRegex));
         F.writeln(os, ").unwrap();");
@@ -707,9 +741,11 @@ class ConfTestGenerator {
     private static FoundDecl findDecl(ConfTest t) throws TestGenException {
         List<String> lines = readAllLines(t.getPath(), StandardCharsets.UTF_8);
         Pattern rxVersion = Pattern.compile("version=[\"']([0-9]+.[0-9]+)[\"']", 0);
-        Pattern rxEncoding = Pattern.compile("encoding=[\"'](.+)[\"']", 0);
+        Pattern rxEncoding = Pattern.compile("encoding=[\"']([^\"']+)[\"']", 0);
+        Pattern rxStandalone = Pattern.compile("standalone=[\"'](yes|no)[\"']", 0);
         String version = null;
         String encoding = null;
+        String standalone = null;
         for (String line : lines) {
             if (line.contains("<?xml")) {
                 Matcher versionMatcher = rxVersion.matcher(line);
@@ -728,10 +764,14 @@ class ConfTestGenerator {
                         // ignore
                     }
                 }
+                Matcher standaloneMatcher = rxStandalone.matcher(line);
+                if (standaloneMatcher.find()) {
+                    standalone = standaloneMatcher.group(1);
+                }
                 break;
             }
         }
-        return new FoundDecl(version, encoding);
+        return new FoundDecl(version, encoding, standalone);
     }
 
     private static List<Node> findPrelude(Document doc) throws TestGenException {
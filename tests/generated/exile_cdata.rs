@@ -1,7 +1,7 @@
 // generated file, do not edit
 
 use crate::test_utils::{run_output_test, run_parse_test};
-use exile::{Declaration, Document};
+use exile::Document;
 
 const INPUT_FILE: &str = "exile_cdata.xml";
 const OUTPUT_FILE: &str = "exile_cdata.output.xml";
@@ -20,10 +20,6 @@ fn cdata_serialize() {
 
 fn expected() -> Document {
     let mut doc = Document::new();
-    doc.set_declaration(Declaration {
-        version: None,
-        encoding: None,
-    });
     let root = doc.root_mut();
     root.set_name(r#"root"#);
     root.add_text(r#"before"#);
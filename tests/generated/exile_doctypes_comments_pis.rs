@@ -14,16 +14,16 @@ fn doctypes_comments_pis_parse() {
 fn expected() -> Document {
     let mut doc = Document::new();
     doc.set_declaration(Declaration {
-        version: Some(Version::V10),
+        version: Version::V10,
         encoding: None,
+        standalone: None,
     });
-    // TODO - support doctype https://github.com/webern/exile/issues/22
-    doc.set_doctype(
-"<!DOCTYPE note [\n<!ELEMENT note (to,from,heading,body)>\n<!ELEMENT to (#PCDATA)>\n<!ELEMENT from (#PCDATA)>\n<!ELEMENT heading (#PCDATA)>\n<!ELEMENT body (#PCDATA)>\n]>"
-).unwrap();
     doc.add_prolog_comment(r#" comment before doctype "#)
         .unwrap();
     doc.add_prolog_pi(exile::Pi::new(r#"pi"#, r#"before doctype "#).unwrap());
+    doc.set_doctype(
+"<!DOCTYPE note [\n<!ELEMENT note (to,from,heading,body)>\n<!ELEMENT to (#PCDATA)>\n<!ELEMENT from (#PCDATA)>\n<!ELEMENT heading (#PCDATA)>\n<!ELEMENT body (#PCDATA)>\n]>"
+).unwrap();
     doc.add_prolog_comment(r#" comment after doctype "#)
         .unwrap();
     doc.add_prolog_pi(exile::Pi::new(r#"pi"#, r#"after doctype "#).unwrap());
@@ -21,8 +21,9 @@ fn escapes_serialize() {
 fn expected() -> Document {
     let mut doc = Document::new();
     doc.set_declaration(Declaration {
-        version: Some(Version::V10),
+        version: Version::V10,
         encoding: None,
+        standalone: None,
     });
     let root = doc.root_mut();
     root.set_name(r#"escapes"#);
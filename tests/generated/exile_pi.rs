@@ -21,8 +21,9 @@ fn pi_serialize() {
 fn expected() -> Document {
     let mut doc = Document::new();
     doc.set_declaration(Declaration {
-        version: Some(Version::V11),
+        version: Version::V11,
         encoding: Some(Encoding::Utf8),
+        standalone: None,
     });
     doc.add_prolog_pi(exile::Pi::new(r#"a"#, r#"b"#).unwrap());
     let root = doc.root_mut();
@@ -21,8 +21,9 @@ fn single_quotes_serialize() {
 fn expected() -> Document {
     let mut doc = Document::new();
     doc.set_declaration(Declaration {
-        version: Some(Version::V11),
+        version: Version::V11,
         encoding: Some(Encoding::Utf8),
+        standalone: None,
     });
     let root = doc.root_mut();
     root.set_name(r#"foo"#);
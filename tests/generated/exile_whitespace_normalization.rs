@@ -21,8 +21,9 @@ fn whitespace_normalization_serialize() {
 fn expected() -> Document {
     let mut doc = Document::new();
     doc.set_declaration(Declaration {
-        version: Some(Version::V10),
+        version: Version::V10,
         encoding: Some(Encoding::Utf8),
+        standalone: None,
     });
     let root = doc.root_mut();
     root.set_name(r#"root"#);
@@ -14,10 +14,10 @@ fn xml_xsd_parse() {
 fn expected() -> Document {
     let mut doc = Document::new();
     doc.set_declaration(Declaration {
-        version: Some(Version::V10),
+        version: Version::V10,
         encoding: None,
+        standalone: None,
     });
-    // TODO - support doctype https://github.com/webern/exile/issues/22
     doc.set_doctype(
         r#"<!DOCTYPE xs:schema PUBLIC "-//W3C//DTD XMLSCHEMA 200102//EN" "XMLSchema.dtd" >"#,
     )
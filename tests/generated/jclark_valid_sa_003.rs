@@ -1,7 +1,7 @@
 // generated file, do not edit
 
-use crate::test_utils::run_parse_test;
-use exile::{Declaration, Document};
+use crate::test_utils::{run_parse_test, run_validity_test};
+use exile::Document;
 
 const INPUT_FILE: &str = "jclark_valid_sa_003.xml";
 
@@ -11,13 +11,14 @@ fn valid_sa_003_parse() {
     run_parse_test(INPUT_FILE, &expected());
 }
 
+#[test]
+/// Check that the document is valid according to its DTD.
+fn valid_sa_003_validate() {
+    run_validity_test(INPUT_FILE);
+}
+
 fn expected() -> Document {
     let mut doc = Document::new();
-    doc.set_declaration(Declaration {
-        version: None,
-        encoding: None,
-    });
-    // TODO - support doctype https://github.com/webern/exile/issues/22
     doc.set_doctype("<!DOCTYPE doc [\r\n<!ELEMENT doc (#PCDATA)>\r\n]>")
         .unwrap();
     let root = doc.root_mut();
@@ -51,6 +51,12 @@ pub fn run_not_well_formed_test(filename: &str, throw_site: Option<XmlSite>) {
     let e = result.err().unwrap();
     match e {
         exile::error::Error::Parse(parse_error) => {
+            // the error must say what is wrong with the document, not that the parser failed.
+            assert_ne!(
+                exile::error::ErrorKind::Internal,
+                parse_error.kind,
+                "{filename}: {parse_error}"
+            );
             if let Some(actual_site) = throw_site {
                 assert_eq!(actual_site, parse_error.xml_site.unwrap());
             }
@@ -80,3 +86,12 @@ pub fn run_output_test(output_filename: &str, doc: &exile::Document) {
     let expected = std::fs::read_to_string(output_filepath).unwrap();
     assert_eq!(expected, actual);
 }
+
+/// Loads `filename` and asserts that it is valid according to its DTD.
+#[allow(dead_code)]
+pub fn run_validity_test(filename: &str) {
+    let path = path(filename);
+    let doc = exile::load(path).unwrap();
+    let violations = doc.validate_dtd();
+    assert!(violations.is_empty(), "{:?}", violations);
+}